@@ -1,104 +1,285 @@
 use mymatrix;
 use needleman::Direction::{Diag, Done, Left, Up};
 use std::f64;
-use std::fmt;
 use needleman::{Direction, Scores, Alignment};
+use smith_waterman_no_diag::{in_diagonal_band, MatrixWindow};
+
+/// Gotoh's three-state alignment keeps one score matrix per way an alignment column can end: aligned bases
+/// (match), a base of seq1 against a gap (insertion) or a base of seq2 against a gap (deletion). A gap of
+/// length L costs gap_open + L * gap_ext. Each traceback matrix records which state the previous column was
+/// in: Diag for match, Up for insertion, Left for deletion, and Done where a local alignment starts.
+struct AffineMatrices {
+    match_matrix: mymatrix::MyMatrix<f64>,
+    ins_matrix: mymatrix::MyMatrix<f64>,
+    del_matrix: mymatrix::MyMatrix<f64>,
+    match_trc: mymatrix::MyMatrix<Direction>,
+    ins_trc: mymatrix::MyMatrix<Direction>,
+    del_trc: mymatrix::MyMatrix<Direction>,
+}
 
-/// Aligns two sequences using the Needleman Wunsch global alignment with simple gap scoring
-pub fn affine_align(seq1: &Vec<char>, seq2: &Vec<char>, scores: &Scores) -> Alignment {
-    let seq1_limit = seq1.len() + 1;
-    let seq2_limit = seq2.len() + 1;
+impl AffineMatrices {
+    fn new(rows: usize, cols: usize) -> AffineMatrices {
+        AffineMatrices {
+            match_matrix: mymatrix::MyMatrix::new(rows, cols, f64::NEG_INFINITY),
+            ins_matrix: mymatrix::MyMatrix::new(rows, cols, f64::NEG_INFINITY),
+            del_matrix: mymatrix::MyMatrix::new(rows, cols, f64::NEG_INFINITY),
+            match_trc: mymatrix::MyMatrix::new(rows, cols, Done),
+            ins_trc: mymatrix::MyMatrix::new(rows, cols, Done),
+            del_trc: mymatrix::MyMatrix::new(rows, cols, Done),
+        }
+    }
 
-    let mut match_matrix = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0.0);
-    let mut ins_matrix = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0.0);
-    let mut del_matrix = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0.0);
-
-    println!("made matrix of {} {}", match_matrix.rows(), match_matrix.cols());
-    let mut match_trc: mymatrix::MyMatrix<Direction> = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, Done);
-    let mut ins_trc: mymatrix::MyMatrix<Direction> = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, Done);
-    let mut del_trc: mymatrix::MyMatrix<Direction> = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, Done);
-
-    affine_borrow(seq1,
-                  seq2,
-                  &mut match_matrix,
-                  &mut ins_matrix,
-                  &mut del_matrix,
-                  &mut match_trc,
-                  &mut ins_trc,
-                  &mut del_trc,
-                  scores)
+    /// the best way into the insertion and deletion states at a cell
+    fn fill_gaps(&mut self, ix: usize, iy: usize, scores: &Scores) {
+        let open = scores.gap_open + scores.gap_ext;
+        let ins_max = max2(max2((self.match_matrix.get(ix - 1, iy) + open, Diag),
+                                (self.ins_matrix.get(ix - 1, iy) + scores.gap_ext, Up)),
+                           (self.del_matrix.get(ix - 1, iy) + open, Left));
+        self.ins_matrix.set(ix, iy, ins_max.0);
+        self.ins_trc.set(ix, iy, ins_max.1);
+
+        let del_max = max2(max2((self.match_matrix.get(ix, iy - 1) + open, Diag),
+                                (self.del_matrix.get(ix, iy - 1) + scores.gap_ext, Left)),
+                           (self.ins_matrix.get(ix, iy - 1) + open, Up));
+        self.del_matrix.set(ix, iy, del_max.0);
+        self.del_trc.set(ix, iy, del_max.1);
+    }
+
+    /// the best way into the match state at a cell, not counting the score of the aligned bases
+    fn best_match_predecessor(&self, ix: usize, iy: usize) -> (f64, Direction) {
+        max2(max2((self.ins_matrix.get(ix - 1, iy - 1), Up),
+                  (self.del_matrix.get(ix - 1, iy - 1), Left)),
+             (self.match_matrix.get(ix - 1, iy - 1), Diag))
+    }
 }
 
-pub fn affine_borrow(seq1: &Vec<char>,
-                     seq2: &Vec<char>,
-                     match_matrix: &mut mymatrix::MyMatrix<f64>,
-                     ins_matrix: &mut mymatrix::MyMatrix<f64>,
-                     del_matrix: &mut mymatrix::MyMatrix<f64>,
-                     match_trc: &mut mymatrix::MyMatrix<Direction>,
-                     ins_trc: &mut mymatrix::MyMatrix<Direction>,
-                     del_trc: &mut mymatrix::MyMatrix<Direction>,
-                     scores: &Scores) -> Alignment {
+/// Aligns two sequences globally (Needleman-Wunsch) with affine gap scoring
+pub fn affine_align(seq1: &[char], seq2: &[char], scores: &Scores) -> Alignment {
+    let mut matrices = AffineMatrices::new(seq1.len() + 1, seq2.len() + 1);
+    affine_borrow(seq1, seq2, &mut matrices, scores)
+}
+
+fn affine_borrow(seq1: &[char], seq2: &[char], matrices: &mut AffineMatrices, scores: &Scores) -> Alignment {
     let seq1_limit = seq1.len() + 1;
     let seq2_limit = seq2.len() + 1;
 
-    // we want a practical MAX, but not at the limit of F64 values
-    let practical_max = 10000000000.0;
-
     // first square
-    match_matrix.set(0, 0, practical_max);
+    matrices.match_matrix.set(0, 0, 0.0);
 
-    // initialize the top row and first column
+    // initialize the top row and first column: a single gap running from the corner
     for n in 1..seq1_limit {
-        match_matrix.set(n, 0, practical_max);
-        ins_matrix.set(n, 0, scores.gap_ext * (n as f64));
-        del_matrix.set(n, 0, practical_max);
-        trc.set(n, 0, Up);
+        matrices.ins_matrix.set(n, 0, scores.gap_open + scores.gap_ext * (n as f64));
+        matrices.ins_trc.set(n, 0, if n == 1 { Diag } else { Up });
     }
     for n in 1..seq2_limit {
-        match_matrix.set(0, n, practical_max);
-        ins_matrix.set(n, 0, practical_max);
-        del_matrix.set(n, 0, scores.gap_ext * (n as f64));
-        trc.set(0, n, Left);
+        matrices.del_matrix.set(0, n, scores.gap_open + scores.gap_ext * (n as f64));
+        matrices.del_trc.set(0, n, if n == 1 { Diag } else { Left });
     }
 
-
     // fill in the matrix
     for ix in 1..seq1_limit {
         for iy in 1..seq2_limit {
             let score = Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
+            let match_max = matrices.best_match_predecessor(ix, iy);
+            matrices.match_matrix.set(ix, iy, score + match_max.0);
+            matrices.match_trc.set(ix, iy, match_max.1);
+            matrices.fill_gaps(ix, iy, scores);
+        }
+    }
+
+    let (ix, iy) = (seq1_limit - 1, seq2_limit - 1);
+    let end_state = max2(max2((matrices.ins_matrix.get(ix, iy), Up),
+                              (matrices.del_matrix.get(ix, iy), Left)),
+                         (matrices.match_matrix.get(ix, iy), Diag));
+    traceback(seq1, seq2, matrices, end_state.0, end_state.1, ix, iy)
+}
+
+/// Aligns two sequences locally (Smith-Waterman) with affine gap scoring, within a window of the full
+/// matrix. Cells closer than min_diag_distance to the self-alignment diagonal can't be part of an alignment,
+/// the same way smith_waterman_no_diag excludes them; pass 0 to allow every cell.
+pub fn affine_local(seq1: &[char], seq2: &[char], scores: &Scores, min_diag_distance: i32, window: &MatrixWindow) -> Alignment {
+    let seq1_limit = window.x_end - window.x_start + 1;
+    let seq2_limit = window.y_end - window.y_start + 1;
+    let window_one = &seq1[window.x_start..window.x_end];
+    let window_two = &seq2[window.y_start..window.y_end];
+
+    let mut matrices = AffineMatrices::new(seq1_limit, seq2_limit);
+
+    let mut top_score = 0.0;
+    let mut topx = 0;
+    let mut topy = 0;
+    for ix in 1..seq1_limit {
+        for iy in 1..seq2_limit {
+            if in_diagonal_band(window.x_start + ix, window.y_start + iy, seq1.len(), seq2.len(), min_diag_distance) {
+                // leave the gap states unreachable, and let an alignment start fresh from here
+                matrices.match_matrix.set(ix, iy, 0.0);
+                continue;
+            }
+            let score = Scores::scoring_function(window_one[ix - 1], window_two[iy - 1], scores);
+            let match_max = max2(matrices.best_match_predecessor(ix, iy), (0.0, Done));
+            let match_score = score + match_max.0;
+            matrices.match_matrix.set(ix, iy, match_score);
+            matrices.match_trc.set(ix, iy, match_max.1);
+            matrices.fill_gaps(ix, iy, scores);
+
+            if match_score > top_score {
+                top_score = match_score;
+                topx = ix;
+                topy = iy;
+            }
+        }
+    }
 
-            let match_max = max2(
-                    max2((score + ins_matrix.get(ix - 1, iy - 1),Up),
-                    (score + del_matrix.get(ix - 1, iy - 1),Left)),
-                (score + match_matrix.get(ix - 1, iy - 1),Diag));
+    let mut alignment = traceback(window_one, window_two, &matrices, top_score, if top_score > 0.0 { Diag } else { Done }, topx, topy);
+    alignment.seq_one = seq1.to_vec();
+    alignment.seq_two = seq2.to_vec();
+    alignment.start_x += window.x_start;
+    alignment.end_x += window.x_start;
+    alignment.start_y += window.y_start;
+    alignment.end_y += window.y_start;
+    alignment
+}
 
-            match_matrix.set(ix, iy, match_max.0);
-            match_trc.set(ix, iy, match_max.1);
+/// the bytes affine_local needs for a window of the given size
+pub fn affine_matrix_bytes(seq1_length: usize, seq2_length: usize) -> usize {
+    (seq1_length + 1) * (seq2_length + 1) * 3 * (std::mem::size_of::<f64>() + std::mem::size_of::<Direction>())
+}
 
-            let ins_max = max2(
-                max2((scores.gap_open + scores.gap_ext + match_matrix.get(ix, iy - 1),Diag),
-                     (ins_matrix.get(ix, iy - 1),Up)),
-                (scores.gap_open + scores.gap_ext + del_matrix.get(ix, iy - 1),Left));
+/// the bytes affine_align_linear needs: score and gap rows over seq2 for the forward and reverse passes,
+/// and the two gapped sequences it returns
+pub fn affine_linear_bytes(seq1_length: usize, seq2_length: usize) -> usize {
+    4 * (seq2_length + 1) * std::mem::size_of::<f64>() + 2 * (seq1_length + seq2_length) * std::mem::size_of::<char>()
+}
 
-            ins_matrix.set(ix, iy, ins_max.0);
-            ins_trc.set(ix, iy, ins_max.1);
+/// The same global affine alignment as affine_align, in memory linear in the sequence lengths (Myers and
+/// Miller's version of Hirschberg's divide and conquer). Where several alignments tie it may pick a
+/// different one than the full matrices do, but the score is the same.
+pub fn affine_align_linear(seq1: &[char], seq2: &[char], scores: &Scores) -> Alignment {
+    let mut seq_one_aligned = Vec::with_capacity(seq1.len() + seq2.len());
+    let mut seq_two_aligned = Vec::with_capacity(seq1.len() + seq2.len());
+    myers_miller(seq1, seq2, scores.gap_open, scores.gap_open, scores, &mut seq_one_aligned, &mut seq_two_aligned);
+    Alignment {
+        seq_one: seq1.to_vec(),
+        seq_two: seq2.to_vec(),
+        score: affine_score(&seq_one_aligned, &seq_two_aligned, scores),
+        start_x: 0,
+        start_y: 0,
+        end_x: seq1.len(),
+        end_y: seq2.len(),
+        seq_one_aligned,
+        seq_two_aligned,
+    }
+}
 
+/// Align seq1 against seq2 globally and append the gapped sequences. open_start and open_end are what it
+/// costs to open a gap in seq2 (seq1 bases against gaps) at either end: gap_open, or 0 where it carries on
+/// a gap the caller has already opened next to this block.
+fn myers_miller(seq1: &[char], seq2: &[char], open_start: f64, open_end: f64, scores: &Scores, aligned_one: &mut Vec<char>, aligned_two: &mut Vec<char>) {
+    let gap = '-';
+    if seq1.is_empty() || seq2.is_empty() {
+        aligned_one.extend(seq1.iter().cloned().chain(seq2.iter().map(|_| gap)));
+        aligned_two.extend(seq1.iter().map(|_| gap).chain(seq2.iter().cloned()));
+        return;
+    }
+    if seq1.len() == 1 {
+        // either the single base lines up with one position of seq2, or it's gapped out next to whichever
+        // end is cheaper and seq2 goes against one gap
+        let gap_run = |length: usize| if length == 0 { 0.0 } else { scores.gap_open + scores.gap_ext * length as f64 };
+        let gapped = open_start.max(open_end) + scores.gap_ext + gap_run(seq2.len());
+        let (best_position, best_score) = seq2.iter().enumerate()
+            .map(|(position, &base)| (position, gap_run(position) + Scores::scoring_function(seq1[0], base, scores) + gap_run(seq2.len() - position - 1)))
+            .fold((0, f64::NEG_INFINITY), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+        if best_score >= gapped {
+            aligned_one.extend((0..seq2.len()).map(|position| if position == best_position { seq1[0] } else { gap }));
+            aligned_two.extend_from_slice(seq2);
+        } else if open_start >= open_end {
+            aligned_one.push(seq1[0]);
+            aligned_one.extend(seq2.iter().map(|_| gap));
+            aligned_two.push(gap);
+            aligned_two.extend_from_slice(seq2);
+        } else {
+            aligned_one.extend(seq2.iter().map(|_| gap));
+            aligned_one.push(seq1[0]);
+            aligned_two.extend_from_slice(seq2);
+            aligned_two.push(gap);
+        }
+        return;
+    }
 
-            let del_max = max2(
-                max2((scores.gap_open + scores.gap_ext + match_matrix.get(ix, iy - 1),Diag),
-                     (del_matrix.get(ix, iy - 1),Left)),
-                (scores.gap_open + scores.gap_ext + ins_matrix.get(ix, iy - 1),Up));
+    let middle = seq1.len() / 2;
+    let (upper, upper_insertion) = affine_score_rows(&seq1[..middle], seq2, open_start, scores);
+    let reversed_one: Vec<char> = seq1[middle..].iter().rev().cloned().collect();
+    let reversed_two: Vec<char> = seq2.iter().rev().cloned().collect();
+    let (lower, lower_insertion) = affine_score_rows(&reversed_one, &reversed_two, open_end, scores);
+
+    // the best path crosses the middle either between two columns of its own, or inside a gap in seq2 that
+    // both halves opened, which only really opens once
+    let mut split = 0;
+    let mut best = f64::NEG_INFINITY;
+    let mut through_gap = false;
+    for position in 0..seq2.len() + 1 {
+        let crossing = upper[position] + lower[seq2.len() - position];
+        if crossing > best {
+            best = crossing;
+            split = position;
+            through_gap = false;
+        }
+        let joined = upper_insertion[position] + lower_insertion[seq2.len() - position] - scores.gap_open;
+        if joined > best {
+            best = joined;
+            split = position;
+            through_gap = true;
+        }
+    }
 
-            del_matrix.set(ix, iy, del_max.0);
-            del_trc.set(ix, iy, del_max.1);
+    if through_gap {
+        myers_miller(&seq1[..middle - 1], &seq2[..split], open_start, 0.0, scores, aligned_one, aligned_two);
+        aligned_one.extend_from_slice(&seq1[middle - 1..middle + 1]);
+        aligned_two.extend(&[gap, gap]);
+        myers_miller(&seq1[middle + 1..], &seq2[split..], 0.0, open_end, scores, aligned_one, aligned_two);
+    } else {
+        myers_miller(&seq1[..middle], &seq2[..split], open_start, scores.gap_open, scores, aligned_one, aligned_two);
+        myers_miller(&seq1[middle..], &seq2[split..], scores.gap_open, open_end, scores, aligned_one, aligned_two);
+    }
+}
 
-            if ix % 1000 == 0 && iy % 1000 == 0 {
-                println!("top is now {} from {},{}", match_matrix.0, ix, iy);
-            }
+/// The last row of the global affine score matrices, keeping one row at a time: the best score for each
+/// prefix of seq2 in any state, and the best in the insertion state (the last base of seq1 against a gap). A gap in
+/// seq2 running down from the first row opens at open_start instead of gap_open.
+fn affine_score_rows(seq1: &[char], seq2: &[char], open_start: f64, scores: &Scores) -> (Vec<f64>, Vec<f64>) {
+    let open = scores.gap_open + scores.gap_ext;
+    let mut best: Vec<f64> = (0..seq2.len() + 1).map(|iy| if iy == 0 { 0.0 } else { open + scores.gap_ext * (iy - 1) as f64 }).collect();
+    let mut insertion = vec![f64::NEG_INFINITY; seq2.len() + 1];
+    for ix in 1..seq1.len() + 1 {
+        let mut diagonal = best[0];
+        best[0] = open_start + scores.gap_ext * ix as f64;
+        insertion[0] = best[0];
+        let mut deletion = f64::NEG_INFINITY;
+        for iy in 1..seq2.len() + 1 {
+            deletion = (deletion + scores.gap_ext).max(best[iy - 1] + open);
+            insertion[iy] = (insertion[iy] + scores.gap_ext).max(best[iy] + open);
+            let matched = diagonal + Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
+            diagonal = best[iy];
+            best[iy] = matched.max(deletion).max(insertion[iy]);
+        }
+    }
+    (best, insertion)
+}
+
+/// score a gapped pair the way the affine aligners do, each run of gaps in one sequence costing
+/// gap_open + length * gap_ext
+fn affine_score(aligned_one: &[char], aligned_two: &[char], scores: &Scores) -> f64 {
+    let gap = '-';
+    let mut score = 0.0;
+    for (column, (&one, &two)) in aligned_one.iter().zip(aligned_two).enumerate() {
+        if one == gap || two == gap {
+            let opens = column == 0 || (aligned_one[column - 1] == gap) != (one == gap) || (aligned_two[column - 1] == gap) != (two == gap);
+            score += scores.gap_ext + if opens { scores.gap_open } else { 0.0 };
+        } else {
+            score += Scores::scoring_function(one, two, scores);
         }
     }
-    //trc.print_matrix(8);
-    traceback(seq1, seq2, trc, mtx.get(seq1_limit - 1, seq2_limit - 1), seq1_limit - 1, seq2_limit - 1)
+    score
 }
 
 #[inline]
@@ -106,62 +287,57 @@ fn max2(x: (f64, Direction), y: (f64, Direction)) -> (f64, Direction) {
     if x.0 > y.0 { x } else { y }
 }
 
-// a convenience struct for matching movement tuples
-#[allow(dead_code)]
-struct TruthSet {
-    same_row: bool,
-    same_col: bool,
-    on_diag: bool,
-}
-
-/// traceback a matrix into an alignment struct
-pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, trc: &mymatrix::MyMatrix<Direction>, top_score: f64, topx: usize, topy: usize) -> Alignment {
-    assert_eq!(seq1.len(), trc.rows() - 1, "The matrix doesn't have the right number of rows; rows: {}, expected: {}", seq1.len(), trc.rows() - 1);
-    assert_eq!(seq2.len(), trc.cols() - 1, "The matrix doesn't have the right number of columns; columns: {}, expected: {}", seq2.len(), trc.cols() - 1);
+/// walk the three traceback matrices back from a cell, starting in the given state
+fn traceback(seq1: &[char],
+             seq2: &[char],
+             matrices: &AffineMatrices,
+             top_score: f64,
+             end_state: Direction,
+             topx: usize,
+             topy: usize) -> Alignment {
+    assert_eq!(seq1.len(), matrices.match_trc.rows() - 1, "The matrix doesn't have the right number of rows; rows: {}, expected: {}", seq1.len(), matrices.match_trc.rows() - 1);
+    assert_eq!(seq2.len(), matrices.match_trc.cols() - 1, "The matrix doesn't have the right number of columns; columns: {}, expected: {}", seq2.len(), matrices.match_trc.cols() - 1);
 
     let mut alignment1 = Vec::new();
     let mut alignment2 = Vec::new();
 
-    let mut row_index = seq1.len(); // topx;
-    let mut column_index = seq2.len(); // topx;
+    let mut row_index = topx;
+    let mut column_index = topy;
+    let mut state = end_state;
 
     let gap = '-';
-
-    let mut current_pointer = trc.get(row_index, column_index);
-
-    loop {
-        match current_pointer {
+    while row_index > 0 || column_index > 0 {
+        let previous_state = match state {
+            Diag => {
+                let previous = matrices.match_trc.get(row_index, column_index);
+                alignment1.push(seq1[row_index - 1]);
+                alignment2.push(seq2[column_index - 1]);
+                row_index -= 1;
+                column_index -= 1;
+                previous
+            }
             Up => {
+                let previous = matrices.ins_trc.get(row_index, column_index);
                 alignment1.push(seq1[row_index - 1]);
                 alignment2.push(gap);
-                // println!("LEFT: Moving from {},{} to {},{}", row_index, column_index, row_index - 1, column_index);
                 row_index -= 1;
+                previous
             }
             Left => {
+                let previous = matrices.del_trc.get(row_index, column_index);
                 alignment1.push(gap);
                 alignment2.push(seq2[column_index - 1]);
-                // println!("LEFT: Moving from {},{} to {},{}", row_index, column_index, row_index, column_index - 1);
                 column_index -= 1;
+                previous
             }
-            Diag => {
-                alignment1.push(seq1[row_index - 1]);
-                alignment2.push(seq2[column_index - 1]);
-                // println!("DIAG: Moving from {},{} to {},{}", row_index, column_index, row_index - 1, column_index - 1);
-                row_index -= 1;
-                column_index -= 1;
-            }
-            Done => {
-                //println!("{},{}",row_index,column_index);
-                break;
-            }
-        }
-        current_pointer = trc.get(row_index, column_index);
+            Done => break,
+        };
+        state = previous_state;
     }
 
     alignment1.reverse();
     alignment2.reverse();
-    // println!("{},{}",alignment1.len(),alignment2.len());
-    return Alignment {
+    Alignment {
         seq_one: seq1.to_vec(),
         seq_two: seq2.to_vec(),
         score: top_score,
@@ -171,13 +347,14 @@ pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, trc: &mymatrix::MyMatrix<Di
         end_y: topy,
         seq_one_aligned: alignment1,
         seq_two_aligned: alignment2,
-    };
+    }
 }
 
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use test_support::random_sequence;
 
     #[test]
     fn test_max_2() {
@@ -206,7 +383,7 @@ mod tests {
     #[test]
     fn test_basic_alignment() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'A', 'A'], &vec!['A', 'A', 'A'], &scores);
+        let alignment = affine_align(&['A', 'A', 'A'], &['A', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -223,7 +400,7 @@ mod tests {
     #[test]
     fn test_basic_alignment_ns() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'N', 'A'], &vec!['A', 'T', 'A'], &scores);
+        let alignment = affine_align(&['A', 'N', 'A'], &['A', 'T', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -240,7 +417,7 @@ mod tests {
     #[test]
     fn test_basic_alignment_unequal() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A'], &vec!['A', 'A', 'A'], &scores);
+        let alignment = affine_align(&['A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A'], &['A', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -256,7 +433,7 @@ mod tests {
     #[test]
     fn test_basic_alignment_unequal_second() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'A', 'A'], &vec!['A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A'], &scores);
+        let alignment = affine_align(&['A', 'A', 'A'], &['A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -272,7 +449,7 @@ mod tests {
     #[test]
     fn test_mismatch_alignment() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'T', 'A'], &vec!['A', 'A', 'A'], &scores);
+        let alignment = affine_align(&['A', 'T', 'A'], &['A', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -290,7 +467,7 @@ mod tests {
     #[test]
     fn test_mismatch_length() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'T', 'T', 'A', 'A'], &vec!['T', 'A', 'A'], &scores);
+        let alignment = affine_align(&['A', 'T', 'T', 'A', 'A'], &['T', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -309,7 +486,7 @@ mod tests {
     #[test]
     fn test_mismatch_length2() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['G', 'G', 'G', 'A', 'T', 'T', 'A', 'A'], &vec!['G', 'G', 'T', 'A', 'A'], &scores);
+        let alignment = affine_align(&['G', 'G', 'G', 'A', 'T', 'T', 'A', 'A'], &['G', 'G', 'T', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -320,8 +497,87 @@ mod tests {
         assert_eq!(str2, "GGTAA");
         println!("Alignment 1: {} alignment 2: {}", str1align, str2align);
 
+        // one long gap beats the two short ones a linear gap penalty picks
         assert_eq!(str1align, "GGGATTAA");
-        assert_eq!(str2align, "-GG--TAA");
+        assert_eq!(str2align, "GG---TAA");
+        assert_eq!(alignment.score, 5.0 * scores.match_score + scores.gap_open + 3.0 * scores.gap_ext);
+    }
+
+    fn hand_scores() -> Scores {
+        Scores { match_score: 1.0, mismatch_score: -1.0, gap_open: -2.0, gap_ext: -1.0, gap_start: -2.0, gap_end: -2.0, substitution_matrix: None }
+    }
+
+    #[test]
+    fn test_global_long_gap() {
+        // AAACCCGGG
+        // AAA---GGG   6 matches, one gap of 3: 6 - 2 - 3 = 1
+        let alignment = affine_align(&"AAACCCGGG".chars().collect::<Vec<char>>(), &"AAAGGG".chars().collect::<Vec<char>>(), &hand_scores());
+        assert_eq!(alignment.seq_one_aligned.iter().collect::<String>(), "AAACCCGGG");
+        assert_eq!(alignment.seq_two_aligned.iter().collect::<String>(), "AAA---GGG");
+        assert_eq!(alignment.score, 1.0);
+    }
+
+    #[test]
+    fn test_global_gaps_in_both() {
+        // ACGTTTTAC-
+        // AC-----ACG  4 matches, gaps of 5 and 1: 4 - 7 - 3 = -6, better than any mismatch-heavy layout
+        let alignment = affine_align(&"ACGTTTTAC".chars().collect::<Vec<char>>(), &"ACACG".chars().collect::<Vec<char>>(), &hand_scores());
+        assert_eq!(alignment.score, -6.0);
+        assert_eq!(alignment.seq_one_aligned.iter().filter(|&&base| base != '-').count(), 9);
+        assert_eq!(alignment.seq_two_aligned.iter().filter(|&&base| base != '-').count(), 5);
+    }
+
+    #[test]
+    fn test_linear_matches_full_matrices() {
+        for (seed, scores) in [(1, hand_scores()), (2, Scores::default_scores()), (3, Scores { gap_open: -8.0, mismatch_score: -3.0, ..hand_scores() })].iter() {
+            for offset in 0..10 {
+                let seed = seed * 100 + offset;
+                let seq1 = random_sequence(40 + offset as usize * 7, seed);
+                let mut seq2 = seq1.clone();
+                seq2.drain(10..14 + offset as usize);
+                seq2.splice(20..20, random_sequence(3 + offset as usize, seed + 50));
+                seq2[5] = if seq2[5] == 'A' { 'C' } else { 'A' };
+
+                let full = affine_align(&seq1, &seq2, scores);
+                let linear = affine_align_linear(&seq1, &seq2, scores);
+                assert_eq!(linear.score, full.score, "seed {}", seed);
+                assert_eq!(affine_score(&full.seq_one_aligned, &full.seq_two_aligned, scores), full.score);
+                assert_eq!(linear.seq_one_aligned.iter().filter(|&&base| base != '-').cloned().collect::<Vec<char>>(), seq1);
+                assert_eq!(linear.seq_two_aligned.iter().filter(|&&base| base != '-').cloned().collect::<Vec<char>>(), seq2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_linear_long_gap() {
+        let alignment = affine_align_linear(&"AAACCCGGG".chars().collect::<Vec<char>>(), &"AAAGGG".chars().collect::<Vec<char>>(), &hand_scores());
+        assert_eq!(alignment.seq_one_aligned.iter().collect::<String>(), "AAACCCGGG");
+        assert_eq!(alignment.seq_two_aligned.iter().collect::<String>(), "AAA---GGG");
+        assert_eq!(alignment.score, 1.0);
+    }
+
+    #[test]
+    fn test_local_single_gap() {
+        // AAAAACGGGGG
+        // AAAAA-GGGGG   10 matches, one gap of 1: 10 - 2 - 1 = 7, better than AAAAACGGGG/AAAAAGGGGG at 9 - 3 = 6
+        let scores = Scores { mismatch_score: -3.0, ..hand_scores() };
+        let seq1: Vec<char> = "TTAAAAACGGGGGTT".chars().collect();
+        let seq2: Vec<char> = "CCAAAAAGGGGGCC".chars().collect();
+        let alignment = affine_local(&seq1, &seq2, &scores, 0, &MatrixWindow::whole(&seq1, &seq2));
+        assert_eq!(alignment.score, 7.0);
+        assert_eq!(alignment.seq_one_aligned.iter().collect::<String>(), "AAAAACGGGGG");
+        assert_eq!(alignment.seq_two_aligned.iter().collect::<String>(), "AAAAA-GGGGG");
+        assert_eq!((alignment.start_x, alignment.end_x, alignment.start_y, alignment.end_y), (2, 13, 2, 12));
+    }
+
+    #[test]
+    fn test_local_skips_diagonal() {
+        let seq1: Vec<char> = "GATTACACCCCCCCGATTACA".chars().collect();
+        let alignment = affine_local(&seq1, &seq1, &hand_scores(), 5, &MatrixWindow::whole(&seq1, &seq1));
+        assert_eq!(alignment.score, 7.0);
+        assert_eq!(alignment.seq_one_aligned.iter().collect::<String>(), "GATTACA");
+        assert_eq!(alignment.end_x - alignment.start_x, 7);
+        assert!(alignment.start_x != alignment.start_y);
     }
 
 
@@ -334,7 +590,7 @@ mod tests {
         let str2 = vec!['A'; size];
 
         for _ in 0..1000 {
-            let _alignment = needleman_wunsch(&str1, &str2, &scores);
+            let _alignment = affine_align(&str1, &str2, &scores);
         }
 
         // assert_eq!(alignment.score, (size as f64)  * scores.match_score);
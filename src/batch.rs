@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+use std::any::Any;
+use std::env;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use csv;
+
+use dedup::Deduplicator;
+use error::DupScoopError;
+use header::NameTemplate;
+use pipeline;
+use report::{EventKind, ReportEvent};
+
+/// One plasmid assembly to deduplicate: a row of the sample sheet, or a file matched by the input glob
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchSample {
+    pub sample_id: String,
+    pub input: String,
+    pub output: String,
+}
+
+/// whether a sample made it through
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleStatus {
+    Ok,
+    Failed,
+}
+
+/// A row of the batch summary table. The lengths are summed over every record of the sample's file, and
+/// the counts are left empty (TSV) or null (JSON) when it failed.
+#[derive(Serialize, Debug, Clone)]
+pub struct SampleSummary {
+    pub sample_id: String,
+    pub input: String,
+    pub output: String,
+    pub status: SampleStatus,
+    pub records: Option<usize>,
+    pub original_length: Option<usize>,
+    pub final_length: Option<usize>,
+    /// duplications found, which are only reported (not removed) in detect-only runs
+    pub duplications_removed: Option<usize>,
+    pub error: Option<String>,
+}
+
+impl SampleSummary {
+    fn from_events(sample: &BatchSample, events: &[ReportEvent]) -> SampleSummary {
+        let summaries: Vec<&ReportEvent> = events.iter().filter(|event| event.event == EventKind::Summary).collect();
+        SampleSummary {
+            status: SampleStatus::Ok,
+            records: Some(summaries.len()),
+            original_length: Some(summaries.iter().map(|event| event.length_before).sum()),
+            final_length: Some(summaries.iter().map(|event| event.length_after).sum()),
+            duplications_removed: Some(events.iter().filter(|event| event.event == EventKind::Duplication).count()),
+            error: None,
+            ..SampleSummary::failed(sample, String::new())
+        }
+    }
+
+    fn failed(sample: &BatchSample, error: String) -> SampleSummary {
+        SampleSummary {
+            sample_id: sample.sample_id.clone(),
+            input: sample.input.clone(),
+            output: sample.output.clone(),
+            status: SampleStatus::Failed,
+            records: None,
+            original_length: None,
+            final_length: None,
+            duplications_removed: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// read a CSV sample sheet with sample_id, input and output columns
+pub fn read_sample_sheet(path: &str) -> Result<Vec<BatchSample>, DupScoopError> {
+    let sheet_error = |message: String| DupScoopError::InvalidArgument(format!("sample sheet {}: {}", path, message));
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_path(path).map_err(|error| sheet_error(error.to_string()))?;
+    let mut samples = Vec::new();
+    let mut seen = HashSet::new();
+    for row in reader.deserialize() {
+        let sample: BatchSample = row.map_err(|error| sheet_error(error.to_string()))?;
+        if !seen.insert(sample.sample_id.clone()) {
+            return Err(sheet_error(format!("sample {} is listed more than once", sample.sample_id)));
+        }
+        samples.push(sample);
+    }
+    if samples.is_empty() {
+        return Err(sheet_error("there are no samples in it".to_string()));
+    }
+    check_outputs(&samples).map_err(sheet_error)?;
+    Ok(samples)
+}
+
+/// Every file in a directory whose name matches the pattern (the last component of the glob, where * matches
+/// any run of characters and ? any one), written under the same name into output_dir. The sample ID is the
+/// file name without its sequence and compression extensions.
+pub fn samples_from_glob(glob: &str, output_dir: &str) -> Result<Vec<BatchSample>, DupScoopError> {
+    let glob_path = Path::new(glob);
+    let pattern: Vec<char> = glob_path.file_name().map(|name| name.to_string_lossy().chars().collect()).unwrap_or_default();
+    let directory = match glob_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut samples = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let file_name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => continue,
+        };
+        if path.is_file() && wildcard_match(&pattern, &file_name.chars().collect::<Vec<char>>()) {
+            samples.push(BatchSample {
+                sample_id: sample_id(&file_name),
+                input: path.to_string_lossy().to_string(),
+                output: Path::new(output_dir).join(&file_name).to_string_lossy().to_string(),
+            });
+        }
+    }
+    if samples.is_empty() {
+        return Err(DupScoopError::InvalidArgument(format!("no files match {}", glob)));
+    }
+    samples.sort_by(|first, second| first.sample_id.cmp(&second.sample_id));
+    check_outputs(&samples).map_err(|message| DupScoopError::InvalidArgument(format!("{} into {}: {}", glob, output_dir, message)))?;
+    Ok(samples)
+}
+
+/// Make sure no sample overwrites an input or races another sample for its output: every output has to
+/// be a different file from its own input, from every other input, and from every other output.
+fn check_outputs(samples: &[BatchSample]) -> Result<(), String> {
+    let inputs: HashMap<PathBuf, &str> = samples.iter().map(|sample| (canonical_path(&sample.input), sample.sample_id.as_str())).collect();
+    let mut outputs: HashMap<PathBuf, &str> = HashMap::new();
+    for sample in samples {
+        let output = canonical_path(&sample.output);
+        if let Some(input_sample) = inputs.get(&output) {
+            return Err(format!("the output of sample {} would overwrite the input of sample {} ({})", sample.sample_id, input_sample, sample.output));
+        }
+        if let Some(other) = outputs.insert(output, &sample.sample_id) {
+            return Err(format!("samples {} and {} would both write to {}", other, sample.sample_id, sample.output));
+        }
+    }
+    Ok(())
+}
+
+/// the absolute path a sample reads or writes, with symbolic links and .. resolved as far as it exists yet
+fn canonical_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (fs::canonicalize(parent), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => env::current_dir().map(|directory| directory.join(path)).unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
+
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skipped| wildcard_match(rest, &name[skipped..])),
+        Some((&character, rest)) => match name.split_first() {
+            Some((&first, name_rest)) => (character == '?' || character == first) && wildcard_match(rest, name_rest),
+            None => false,
+        },
+    }
+}
+
+/// the file name without a compression extension and then its sequence extension
+fn sample_id(file_name: &str) -> String {
+    let name = file_name.trim_end_matches(".gz").trim_end_matches(".bgz");
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => name[..dot].to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// Deduplicate every sample on a pool of threads, each taking the next unstarted sample until none are
+/// left. The memory budget is shared out between the threads, since each can be aligning at once. A
+/// sample that fails (or panics) is recorded as failed in its summary row rather than stopping the others.
+/// The summaries come back in the order of the samples.
+pub fn run_batch(samples: &[BatchSample], deduplicator: &Deduplicator, detect_only: bool, name_template: Option<&NameTemplate>, threads: usize) -> Vec<SampleSummary> {
+    let workers = threads.max(1).min(samples.len());
+    let mut worker_deduplicator = deduplicator.clone();
+    worker_deduplicator.max_memory = deduplicator.max_memory / workers.max(1);
+    let deduplicator = &worker_deduplicator;
+    let next_sample = AtomicUsize::new(0);
+    let summaries: Mutex<Vec<Option<SampleSummary>>> = Mutex::new(vec![None; samples.len()]);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next_sample.fetch_add(1, Ordering::SeqCst);
+                if index >= samples.len() {
+                    break;
+                }
+                let sample = &samples[index];
+                eprintln!("Starting sample {} ({})", sample.sample_id, sample.input);
+                let result = panic::catch_unwind(|| pipeline::deduplicate_file(deduplicator, &sample.input, &sample.output, detect_only, name_template));
+                let summary = match result {
+                    Ok(Ok(result)) => SampleSummary::from_events(sample, &result.events),
+                    Ok(Err(error)) => {
+                        eprintln!("Error: sample {} failed: {}", sample.sample_id, error);
+                        SampleSummary::failed(sample, error.to_string())
+                    }
+                    Err(payload) => {
+                        eprintln!("Error: sample {} panicked", sample.sample_id);
+                        SampleSummary::failed(sample, format!("internal failure: {}", panic_message(payload.as_ref())))
+                    }
+                };
+                eprintln!("Finished sample {}", sample.sample_id);
+                summaries.lock().unwrap()[index] = Some(summary);
+            });
+        }
+    });
+    summaries.into_inner().unwrap().into_iter().map(|summary| summary.expect("every sample is processed")).collect()
+}
+
+/// the message a panic was raised with, if it was a string
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload.downcast_ref::<String>().map(|message| message.as_str()).unwrap_or("panic"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use needleman::Scores;
+
+    #[test]
+    fn test_wildcards_and_sample_ids() {
+        let matches = |pattern: &str, name: &str| wildcard_match(&pattern.chars().collect::<Vec<_>>(), &name.chars().collect::<Vec<_>>());
+        assert!(matches("*.fa", "sample_1.fa"));
+        assert!(matches("sample_?.fa*", "sample_2.fa.gz"));
+        assert!(!matches("*.fa", "sample_1.fa.gz"));
+        assert!(!matches("sample_?.fa", "sample_10.fa"));
+        assert_eq!(sample_id("sample_1.fa.gz"), "sample_1");
+        assert_eq!(sample_id("pUC19.gbk"), "pUC19");
+    }
+
+    #[test]
+    fn test_batch_over_sample_sheet() {
+        let directory = std::env::temp_dir().join(format!("dupscoop_batch_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = |name: &str| directory.join(name).to_string_lossy().to_string();
+        fs::write(path("first.fa"), ">first\nACGTACGGTCAGTTCA\n").unwrap();
+        fs::write(path("second.fa"), ">second_a\nACGTTGCA\n>second_b\nAAGGCCTT\n").unwrap();
+        fs::write(path("samples.csv"), format!("sample_id,input,output\nfirst,{},{}\nmissing,{},{}\nsecond,{},{}\n",
+                                               path("first.fa"), path("first.out.fasta"),
+                                               path("missing.fa"), path("missing.out.fasta"),
+                                               path("second.fa"), path("second.out.fasta"))).unwrap();
+
+        let samples = read_sample_sheet(&path("samples.csv")).unwrap();
+        assert_eq!(samples.len(), 3);
+        let deduplicator = Deduplicator::new(Scores::default_scores(), 1000, 0.9, 10);
+        let summaries = run_batch(&samples, &deduplicator, false, None, 2);
+
+        let statuses: Vec<(&str, SampleStatus)> = summaries.iter().map(|summary| (summary.sample_id.as_str(), summary.status)).collect();
+        assert_eq!(statuses, vec![("first", SampleStatus::Ok), ("missing", SampleStatus::Failed), ("second", SampleStatus::Ok)]);
+        assert_eq!((summaries[0].records, summaries[0].original_length, summaries[0].final_length), (Some(1), Some(16), Some(16)));
+        assert_eq!(summaries[0].duplications_removed, Some(0));
+        assert_eq!((summaries[2].records, summaries[2].original_length), (Some(2), Some(16)));
+        assert!(summaries[1].error.is_some());
+        assert!(Path::new(&path("second.out.fasta")).exists());
+
+        let globbed = samples_from_glob(&path("*.fa"), &path("out")).unwrap();
+        assert_eq!(globbed.iter().map(|sample| sample.sample_id.as_str()).collect::<Vec<_>>(), vec!["first", "second"]);
+        assert_eq!(globbed[1].output, path("out/second.fa"));
+        // writing back into the directory being globbed would replace the inputs
+        assert!(samples_from_glob(&path("*.fa"), &path(".")).is_err());
+
+        fs::create_dir_all(path("out")).unwrap();
+        fs::write(path("clashing.csv"), format!("sample_id,input,output\nfirst,{},{}\nsecond,{},{}\n",
+                                                path("first.fa"), path("shared.fasta"),
+                                                path("second.fa"), path("out/../shared.fasta"))).unwrap();
+        assert!(read_sample_sheet(&path("clashing.csv")).is_err());
+        fs::write(path("in_place.csv"), format!("sample_id,input,output\nfirst,{},{}\n", path("first.fa"), path("first.fa"))).unwrap();
+        assert!(read_sample_sheet(&path("in_place.csv")).is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use std::io;
+use std::io::Write;
+
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+
+/// the most uncompressed data htslib puts in one block, leaving room for incompressible data to grow and
+/// still fit the 64KB block limit
+const BLOCK_DATA: usize = 0xff00;
+/// the empty block that marks the end of a BGZF file
+const EOF_BLOCK: [u8; 28] = [0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 0x06, 0, b'B', b'C', 0x02, 0, 0x1b, 0, 0x03, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// Writes the blocked gzip (BGZF) format samtools and htslib use: a series of gzip members of at most
+/// 64KB, each recording its own size in a BC extra field, then an empty end-of-file block. Any gzip reader
+/// can still decompress it. finish has to be called to write the last block and the end-of-file marker.
+pub struct BgzfWriter<W: Write> {
+    inner: Option<W>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub fn new(inner: W) -> BgzfWriter<W> {
+        BgzfWriter { inner: Some(inner), buffer: Vec::with_capacity(BLOCK_DATA) }
+    }
+
+    /// write out whatever is buffered and the end-of-file block, and hand back the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_block()?;
+        let mut inner = self.inner.take().expect("finish is only called once");
+        inner.write_all(&EOF_BLOCK)?;
+        inner.flush()?;
+        Ok(inner)
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.buffer)?;
+        let compressed = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(&self.buffer);
+
+        // BSIZE is the size of the whole block minus one: an 18 byte header and an 8 byte footer around the data
+        let block_size = (18 + compressed.len() + 8 - 1) as u16;
+        let mut block = vec![0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 0x06, 0, b'B', b'C', 0x02, 0];
+        block.extend_from_slice(&block_size.to_le_bytes());
+        block.extend_from_slice(&compressed);
+        block.extend_from_slice(&crc.sum().to_le_bytes());
+        block.extend_from_slice(&(self.buffer.len() as u32).to_le_bytes());
+        self.inner.as_mut().expect("no writes after finish").write_all(&block)?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let taken = data.len().min(BLOCK_DATA - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..taken]);
+        if self.buffer.len() == BLOCK_DATA {
+            self.write_block()?;
+        }
+        Ok(taken)
+    }
+
+    /// blocks are only written once they're full, so this just flushes the underlying writer
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.as_mut().expect("no writes after finish").flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::MultiGzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_blocks_decompress() {
+        let data: Vec<u8> = (0..200_000u32).map(|index| b"ACGT"[(index * 7 % 13 % 4) as usize]).collect();
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        assert_eq!(&compressed[..4], &[0x1f, 0x8b, 0x08, 0x04]);
+        assert!(compressed.ends_with(&EOF_BLOCK));
+        let block_size = u16::from_le_bytes([compressed[16], compressed[17]]) as usize + 1;
+        assert_eq!(&compressed[block_size..block_size + 4], &[0x1f, 0x8b, 0x08, 0x04]);
+
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(&compressed[..]).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, data);
+    }
+}
@@ -0,0 +1,310 @@
+use std::f64;
+use std::mem::size_of;
+
+use mymatrix;
+use needleman::Alignment;
+use needleman::Direction;
+use needleman::Direction::{Diag, Done, Left, Up};
+use needleman::Scores;
+use smith_waterman_no_diag;
+use smith_waterman_no_diag::VisitedCells;
+
+/// the bytes the circular self-alignment needs: one n x n matrix instead of n x 2n, and the bitmap of
+/// cells the suboptimal search has traced through
+pub fn circular_matrix_bytes(length: usize) -> usize {
+    (length + 1) * (length + 1) * (size_of::<f64>() + size_of::<Direction>()) + VisitedCells::bytes(length + 1, length + 1)
+}
+
+/// whether a cell is within min_diag_distance of the trivial self-alignment diagonal, measured going either
+/// way round the circle
+#[inline]
+pub fn in_circular_band(ix: usize, iy: usize, length: usize, min_diag_distance: i32) -> bool {
+    let offset = (iy + length - ix % length) % length;
+    (offset as i64) < min_diag_distance as i64 || ((length - offset) as i64) < min_diag_distance as i64
+}
+
+/// how many bases of the circular sequence the second copy covers; it wraps the origin when start_y + span > n
+pub fn circular_span(alignment: &Alignment) -> usize {
+    alignment.seq_two_aligned.iter().filter(|&&base| base != '-').count()
+}
+
+/// The best local alignment of a circular sequence against itself, away from the trivial diagonal. Column
+/// 0 of the matrix is column n, so the second copy can run across the origin without doubling the
+/// sequence. The first copy never needs to wrap: when both copies can't contain the origin, one of the two
+/// mirror-image alignments has a first copy that doesn't. start_y and end_y are circular, so start_y is
+/// past end_y when the second copy wraps.
+pub fn smith_waterman_circular(seq: &[char], scores: &Scores, min_diag_distance: i32) -> Alignment {
+    let (mtx, trc, top_score, topx, topy) = circular_matrices(seq, scores, min_diag_distance);
+    traceback_circular(seq, &trc, &mtx, top_score, topx, topy, None)
+}
+
+/// the circular counterpart of smith_waterman_no_diag_suboptimal: every non-overlapping local alignment
+/// scoring at least min_score, best first
+pub fn smith_waterman_circular_suboptimal<F>(seq: &[char],
+                                             scores: &Scores,
+                                             min_diag_distance: i32,
+                                             min_score: f64,
+                                             max_candidates: usize,
+                                             overlaps: F) -> Vec<Alignment> where F: Fn(&Alignment, &Alignment) -> bool {
+    let (mtx, trc, _, _, _) = circular_matrices(seq, scores, min_diag_distance);
+    let limit = seq.len() + 1;
+
+    let candidates = smith_waterman_no_diag::best_cells(&mtx, limit, limit, min_score, max_candidates);
+    let mut traced = VisitedCells::new(limit, limit);
+    let mut alignments: Vec<Alignment> = Vec::new();
+    for (score, ix, iy) in candidates {
+        if traced.contains(ix, iy) {
+            continue;
+        }
+        let alignment = traceback_circular(seq, &trc, &mtx, score, ix, iy, Some(&mut traced));
+        if !alignments.iter().any(|kept| overlaps(kept, &alignment)) {
+            alignments.push(alignment);
+        }
+    }
+    alignments
+}
+
+fn circular_matrices(seq: &[char], scores: &Scores, min_diag_distance: i32) -> (mymatrix::MyMatrix<f64>, mymatrix::MyMatrix<Direction>, f64, usize, usize) {
+    let limit = seq.len() + 1;
+    let mut mtx = mymatrix::MyMatrix::new(limit, limit, 0.0);
+    let mut trc: mymatrix::MyMatrix<Direction> = mymatrix::MyMatrix::new(limit, limit, Done);
+    let (top_score, topx, topy) = fill_circular(seq, &mut mtx, &mut trc, scores, min_diag_distance);
+    (mtx, trc, top_score, topx, topy)
+}
+
+/// Fill the matrix a row at a time. Diagonal and vertical moves into column 1 read column n of the row
+/// above, which is already final. A horizontal gap can also run across the origin within a row, so after
+/// the first pass a second one carries column n's score into column 1 and on while that beats what's
+/// there. Cells in the band are zeroed, and the best cell is only taken from outside the band.
+fn fill_circular(seq: &[char],
+                 mtx: &mut mymatrix::MyMatrix<f64>,
+                 trc: &mut mymatrix::MyMatrix<Direction>,
+                 scores: &Scores,
+                 min_diag_distance: i32) -> (f64, usize, usize) {
+    let length = seq.len();
+    let mut top_score = 0.0;
+    let mut topx = 0;
+    let mut topy = 0;
+    if length == 0 {
+        return (top_score, topx, topy);
+    }
+    // column 0 of any row past the first is column n
+    let wrapped = |column: usize| if column == 0 { length } else { column };
+
+    let bar = indicatif::ProgressBar::new(length as u64);
+    for ix in 1..length + 1 {
+        for iy in 1..length + 1 {
+            if in_circular_band(ix, iy, length, min_diag_distance) {
+                mtx.set(ix, iy, 0.0);
+                trc.set(ix, iy, Done);
+                continue;
+            }
+            let above = if ix == 1 { 0.0 } else { mtx.get(ix - 1, iy) };
+            let diagonal = if ix == 1 { 0.0 } else { mtx.get(ix - 1, wrapped(iy - 1)) };
+            let left = if iy == 1 { 0.0 } else { mtx.get(ix, iy - 1) };
+            let score = Scores::scoring_function(seq[ix - 1], seq[iy - 1], scores);
+
+            let up_t = (above + scores.gap_ext, Up);
+            let left_t = (left + scores.gap_ext, Left);
+            let diag_t = (diagonal + score, Diag);
+            let max = max2(max2(max2(up_t, left_t), diag_t), (0.0, Diag));
+            mtx.set(ix, iy, max.0);
+            trc.set(ix, iy, max.1);
+        }
+
+        // carry a horizontal gap across the origin
+        let mut carried = mtx.get(ix, length) + scores.gap_ext;
+        for iy in 1..length + 1 {
+            if in_circular_band(ix, iy, length, min_diag_distance) || carried <= mtx.get(ix, iy) {
+                break;
+            }
+            mtx.set(ix, iy, carried);
+            trc.set(ix, iy, Left);
+            carried += scores.gap_ext;
+        }
+
+        for iy in 1..length + 1 {
+            if mtx.get(ix, iy) > top_score {
+                top_score = mtx.get(ix, iy);
+                topx = ix;
+                topy = iy;
+            }
+        }
+        bar.inc(1);
+    }
+    bar.finish();
+    (top_score, topx, topy)
+}
+
+#[inline]
+fn max2(x: (f64, Direction), y: (f64, Direction)) -> (f64, Direction) {
+    if x.0 > y.0 { x } else { y }
+}
+
+/// traceback from a cell, stepping from column 1 back into column n when the path crosses the origin
+fn traceback_circular(seq: &[char],
+                      trc: &mymatrix::MyMatrix<Direction>,
+                      mtx: &mymatrix::MyMatrix<f64>,
+                      top_score: f64,
+                      topx: usize,
+                      topy: usize,
+                      mut visited: Option<&mut VisitedCells>) -> Alignment {
+    let length = seq.len();
+    let mut alignment1: Vec<char> = Vec::new();
+    let mut alignment2: Vec<char> = Vec::new();
+    let gap = '-';
+
+    let mut row_index = topx;
+    let mut column_index = topy;
+    while row_index > 0 && column_index > 0 && mtx.get(row_index, column_index) > 0.0 {
+        if let Some(ref mut visited) = visited {
+            visited.insert(row_index, column_index);
+        }
+        match trc.get(row_index, column_index) {
+            Up => {
+                alignment1.push(seq[row_index - 1]);
+                alignment2.push(gap);
+                row_index -= 1;
+            }
+            Left => {
+                alignment1.push(gap);
+                alignment2.push(seq[column_index - 1]);
+                column_index -= 1;
+            }
+            Diag => {
+                alignment1.push(seq[row_index - 1]);
+                alignment2.push(seq[column_index - 1]);
+                row_index -= 1;
+                column_index -= 1;
+            }
+            Done => break,
+        }
+        if column_index == 0 && row_index > 0 {
+            column_index = length;
+        }
+    }
+
+    alignment1.reverse();
+    alignment2.reverse();
+    Alignment {
+        seq_one: seq.to_vec(),
+        seq_two: seq.to_vec(),
+        score: top_score,
+        start_x: row_index,
+        start_y: column_index % length.max(1),
+        end_x: topx,
+        end_y: topy,
+        seq_one_aligned: alignment1,
+        seq_two_aligned: alignment2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smith_waterman_no_diag::{smith_waterman_no_diag, MIN_CANDIDATES};
+    use test_support::{random_bases, test_scores};
+
+    /// repeats are drawn from A and C and the spacers between them from G and T
+    fn repeat_sequence(length: usize, seed: u64) -> Vec<char> {
+        random_bases(length, seed, &['A', 'C'])
+    }
+
+    /// a spacer that starts and ends with a run of cap; giving the spacers around each copy different caps
+    /// stops the copies extending past their ends
+    fn spacer_sequence(length: usize, cap: char, seed: u64) -> Vec<char> {
+        let mut spacer = vec![cap; 3];
+        spacer.extend(random_bases(length - 6, seed, &['G', 'T']));
+        spacer.extend(vec![cap; 3]);
+        spacer
+    }
+
+    #[test]
+    fn test_circular_band() {
+        assert!(in_circular_band(5, 5, 100, 3));
+        assert!(in_circular_band(5, 7, 100, 3));
+        assert!(!in_circular_band(5, 8, 100, 3));
+        assert!(in_circular_band(1, 99, 100, 3));
+        assert!(in_circular_band(99, 1, 100, 3));
+        assert!(!in_circular_band(97, 1, 100, 3));
+        assert!(!in_circular_band(5, 5, 100, 0));
+    }
+
+    #[test]
+    fn test_matches_doubled_reference() {
+        let repeat = repeat_sequence(40, 3);
+        let mut reference = spacer_sequence(30, 'G', 5);
+        reference.extend_from_slice(&repeat);
+        reference.extend(spacer_sequence(25, 'T', 7));
+        reference.extend_from_slice(&repeat);
+        reference.extend(spacer_sequence(60, 'G', 9));
+        let mut doubled = reference.clone();
+        doubled.extend_from_slice(&reference);
+
+        let circular = smith_waterman_circular(&reference, &test_scores(), 10);
+        let linear = smith_waterman_no_diag(&reference, &doubled, &test_scores(), 10);
+        assert_eq!(circular.score, 40.0);
+        assert_eq!(circular.score, linear.score);
+        assert_eq!((circular.start_x, circular.end_x, circular.start_y), (linear.start_x, linear.end_x, linear.start_y % reference.len()));
+        assert_eq!(circular.seq_two_aligned, linear.seq_two_aligned);
+    }
+
+    #[test]
+    fn test_copy_across_the_origin() {
+        // the second copy starts 15 bases before the end of the plasmid and finishes at its start
+        let repeat = repeat_sequence(40, 11);
+        let mut reference = repeat[15..].to_vec();
+        reference.extend(spacer_sequence(30, 'G', 13));
+        reference.extend_from_slice(&repeat);
+        reference.extend(spacer_sequence(60, 'T', 17));
+        reference.extend_from_slice(&repeat[..15]);
+
+        let alignment = smith_waterman_circular(&reference, &test_scores(), 10);
+        assert_eq!(alignment.score, 40.0);
+        assert_eq!((alignment.start_x, alignment.end_x), (55, 95));
+        assert_eq!((alignment.start_y, alignment.end_y), (reference.len() - 15, 25));
+        assert!(alignment.start_y > alignment.end_y);
+        assert_eq!(circular_span(&alignment), 40);
+        assert_eq!(alignment.seq_two_aligned, repeat);
+    }
+
+    #[test]
+    fn test_gap_across_the_origin() {
+        // the wrapped copy has an extra base right at the origin, so its gap runs from column n into column 1
+        let repeat = repeat_sequence(40, 19);
+        let extra = 'G';
+        let mut reference = vec![extra];
+        reference.extend_from_slice(&repeat[20..]);
+        reference.extend(spacer_sequence(30, 'G', 23));
+        reference.extend_from_slice(&repeat);
+        reference.extend(spacer_sequence(60, 'T', 29));
+        reference.extend_from_slice(&repeat[..20]);
+
+        let alignment = smith_waterman_circular(&reference, &test_scores(), 10);
+        assert_eq!(alignment.score, 35.0);
+        assert_eq!(alignment.seq_one_aligned.iter().filter(|&&base| base == '-').count(), 1);
+        assert_eq!(circular_span(&alignment), 41);
+        assert!(alignment.start_y > alignment.end_y);
+    }
+
+    #[test]
+    fn test_suboptimal_finds_each_repeat_once() {
+        let first = repeat_sequence(30, 31);
+        let second = repeat_sequence(25, 37);
+        let mut reference = Vec::new();
+        for (part, spacer, cap) in [(&first, 20, 'G'), (&second, 45, 'G'), (&first, 15, 'T'), (&second, 70, 'T')].iter() {
+            reference.extend_from_slice(part);
+            reference.extend(spacer_sequence(*spacer, *cap, reference.len() as u64));
+        }
+        let length = reference.len();
+        let copies = |alignment: &Alignment| [(alignment.start_x, alignment.end_x - alignment.start_x), (alignment.start_y, circular_span(alignment))];
+        let overlapping = |one: &Alignment, two: &Alignment| copies(one).iter().any(|a| copies(two).iter().any(|b| {
+            let offset = (b.0 + length - a.0) % length;
+            offset < a.1 || offset + b.1 > length
+        }));
+
+        let alignments = smith_waterman_circular_suboptimal(&reference, &test_scores(), 10, 20.0, MIN_CANDIDATES, overlapping);
+        let scores: Vec<f64> = alignments.iter().map(|alignment| alignment.score).collect();
+        assert_eq!(scores, vec![30.0, 25.0]);
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use affine_gap;
+use dedup::{alignment_identity, rotate_reference};
+use error::DupScoopError;
+use kmer_orientation::{ReadOrientation, ReferenceKmers};
+use needleman::Scores;
+use validate;
+use validate::InvalidBaseMode;
+
+/// indels at least this long are reported as structural differences rather than small indels
+pub const STRUCTURAL_MIN_LENGTH: usize = 50;
+/// the k-mer size for voting on the strand of the sample
+const ORIENTATION_KMER: usize = 15;
+/// the k-mer size for anchoring the sample's rotation to the reference
+const ANCHOR_KMER: usize = 25;
+
+/// The kind of row in a comparison table
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DifferenceKind {
+    Snv,
+    Insertion,
+    Deletion,
+    StructuralInsertion,
+    StructuralDeletion,
+    Summary,
+}
+
+/// One difference between a sample and the reference map, in 0-based half-open reference coordinates.
+/// Insertions fall between two reference bases, so they start and end at the same position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub kind: DifferenceKind,
+    pub reference_start: usize,
+    pub reference_end: usize,
+    pub reference_bases: String,
+    pub sample_bases: String,
+}
+
+/// The outcome of comparing one sample against its reference map
+pub struct Comparison {
+    /// whether the sample was reverse complemented to match the reference strand
+    pub reverse_complemented: bool,
+    /// the offset handed to rotate_reference to line the sample's origin up with the reference's
+    pub rotation: usize,
+    pub score: f64,
+    pub identity: f64,
+    pub differences: Vec<Difference>,
+}
+
+/// A row of the comparison table: one difference, or the summary for a sample. Fields that don't apply to
+/// a row are left empty (TSV) or null (JSON).
+#[derive(Serialize, Debug, Clone)]
+pub struct ComparisonRow {
+    pub sample: String,
+    pub reference: String,
+    pub kind: DifferenceKind,
+    pub reference_start: Option<usize>,
+    pub reference_end: Option<usize>,
+    pub reference_bases: Option<String>,
+    pub sample_bases: Option<String>,
+    /// forward or reverse, for the summary row
+    pub strand: Option<String>,
+    pub rotation: Option<usize>,
+    pub identity: Option<f64>,
+    pub differences: Option<usize>,
+}
+
+impl Comparison {
+    /// the table rows for this comparison: every difference, then a summary
+    pub fn rows(&self, sample: &str, reference: &str) -> Vec<ComparisonRow> {
+        let summary = ComparisonRow {
+            sample: sample.to_string(),
+            reference: reference.to_string(),
+            kind: DifferenceKind::Summary,
+            reference_start: None,
+            reference_end: None,
+            reference_bases: None,
+            sample_bases: None,
+            strand: Some(if self.reverse_complemented { "reverse" } else { "forward" }.to_string()),
+            rotation: Some(self.rotation),
+            identity: Some(self.identity),
+            differences: Some(self.differences.len()),
+        };
+        let mut rows: Vec<ComparisonRow> = self.differences.iter().map(|difference| ComparisonRow {
+            kind: difference.kind,
+            reference_start: Some(difference.reference_start),
+            reference_end: Some(difference.reference_end),
+            reference_bases: Some(difference.reference_bases.clone()),
+            sample_bases: Some(difference.sample_bases.clone()),
+            strand: None,
+            rotation: None,
+            identity: None,
+            differences: None,
+            ..summary.clone()
+        }).collect();
+        rows.push(summary);
+        rows
+    }
+}
+
+/// Compare a (deduplicated) plasmid against the map it was designed from: put it on the reference's strand
+/// by k-mer voting, rotate it so the origins line up, align the two globally (with affine gaps) and list the
+/// differences.
+pub fn compare_to_reference(record_id: &str, sample: &[char], reference: &[char], scores: &Scores, max_memory: usize) -> Result<Comparison, DupScoopError> {
+    // masking rather than rejecting, since a sample with a stray character is still worth comparing
+    let sample: &Vec<char> = &uppercase_bases(record_id, sample)?;
+    let reference: &Vec<char> = &uppercase_bases(record_id, reference)?;
+    if sample.is_empty() {
+        return Err(DupScoopError::EmptySequence(record_id.to_string()));
+    }
+    // the full matrices give up past about 12 kb at the default budget; longer constructs get the
+    // linear-space alignment, which only runs out for sequences far longer than any plasmid
+    let full_matrices = affine_gap::affine_matrix_bytes(reference.len(), sample.len()) <= max_memory;
+    let required = affine_gap::affine_linear_bytes(reference.len(), sample.len());
+    if !full_matrices && required > max_memory {
+        return Err(DupScoopError::SequenceTooLong { record: record_id.to_string(), required, max_memory });
+    }
+
+    let reference_string: String = reference.iter().collect();
+    let sample_string: String = sample.iter().collect();
+    let kmers = ReferenceKmers::generate_kmers(&reference_string, &ORIENTATION_KMER);
+    let orientation = kmers.vote_orientation(&sample_string, &0.5, &1);
+    if orientation == ReadOrientation::UNKNOWN {
+        eprintln!("Warning: couldn't tell which strand {} is on, comparing it as given", record_id);
+    }
+    let reverse_complemented = orientation == ReadOrientation::REV;
+    let oriented: Vec<char> = if reverse_complemented {
+        ReferenceKmers::reverse_complement_sequence(&sample_string).chars().collect()
+    } else {
+        sample.to_vec()
+    };
+
+    let rotation = rotation_offset(&oriented, reference).unwrap_or_else(|| {
+        eprintln!("Warning: no k-mer anchors {} to the reference, comparing it unrotated", record_id);
+        0
+    });
+    let rotated = rotate_reference(&oriented, rotation)?;
+
+    // with linear gaps one long deletion scores the same as the same gaps scattered between chance matches,
+    // so structural differences need the affine aligner to come out as a single event
+    let alignment = if full_matrices {
+        affine_gap::affine_align(reference, &rotated, scores)
+    } else {
+        affine_gap::affine_align_linear(reference, &rotated, scores)
+    };
+    Ok(Comparison {
+        reverse_complemented,
+        rotation,
+        score: alignment.score,
+        identity: alignment_identity(&alignment),
+        differences: aligned_differences(&alignment.seq_one_aligned, &alignment.seq_two_aligned),
+    })
+}
+
+fn uppercase_bases(record_id: &str, sequence: &[char]) -> Result<Vec<char>, DupScoopError> {
+    let (cleaned, _) = validate::normalize_sequence(record_id, sequence, InvalidBaseMode::Mask)?;
+    Ok(cleaned.iter().map(|base| base.to_ascii_uppercase()).collect())
+}
+
+/// The rotation that moves the sample's copy of the first reference k-mer found exactly once in both to the
+/// same position it has in the reference, or None if no k-mer is unique to both.
+fn rotation_offset(sample: &[char], reference: &[char]) -> Option<usize> {
+    let reference_kmers = kmer_positions(reference);
+    let sample_kmers = kmer_positions(sample);
+    reference.windows(ANCHOR_KMER).enumerate()
+        .filter(|(_, kmer)| reference_kmers.get(kmer).map(|positions| positions.len()) == Some(1))
+        .filter_map(|(position, kmer)| match sample_kmers.get(kmer) {
+            Some(positions) if positions.len() == 1 => Some((position, positions[0])),
+            _ => None,
+        })
+        .map(|(reference_position, sample_position)| (reference_position % sample.len() + sample.len() - sample_position) % sample.len())
+        .next()
+}
+
+fn kmer_positions(sequence: &[char]) -> HashMap<&[char], Vec<usize>> {
+    let mut positions: HashMap<&[char], Vec<usize>> = HashMap::new();
+    for (position, kmer) in sequence.windows(ANCHOR_KMER).enumerate() {
+        positions.entry(kmer).or_default().push(position);
+    }
+    positions
+}
+
+/// walk a global alignment of the reference (first) against the sample (second), collecting SNVs and runs
+/// of inserted or deleted bases
+fn aligned_differences(reference_aligned: &[char], sample_aligned: &[char]) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let mut position = 0;
+    let mut column = 0;
+    while column < reference_aligned.len() {
+        let (reference_base, sample_base) = (reference_aligned[column], sample_aligned[column]);
+        if reference_base == '-' || sample_base == '-' {
+            // take the whole run of gaps on the same side
+            let run_end = (column..reference_aligned.len())
+                .find(|&index| (reference_aligned[index] == '-') != (reference_base == '-') || (sample_aligned[index] == '-') != (sample_base == '-'))
+                .unwrap_or(reference_aligned.len());
+            let reference_bases: String = reference_aligned[column..run_end].iter().filter(|&&base| base != '-').collect();
+            let sample_bases: String = sample_aligned[column..run_end].iter().filter(|&&base| base != '-').collect();
+            let length = run_end - column;
+            let kind = match (reference_base == '-', length >= STRUCTURAL_MIN_LENGTH) {
+                (true, false) => DifferenceKind::Insertion,
+                (true, true) => DifferenceKind::StructuralInsertion,
+                (false, false) => DifferenceKind::Deletion,
+                (false, true) => DifferenceKind::StructuralDeletion,
+            };
+            differences.push(Difference {
+                kind,
+                reference_start: position,
+                reference_end: position + reference_bases.len(),
+                reference_bases: reference_bases.clone(),
+                sample_bases,
+            });
+            position += reference_bases.len();
+            column = run_end;
+        } else {
+            if !reference_base.eq_ignore_ascii_case(&sample_base) {
+                differences.push(Difference {
+                    kind: DifferenceKind::Snv,
+                    reference_start: position,
+                    reference_end: position + 1,
+                    reference_bases: reference_base.to_string(),
+                    sample_bases: sample_base.to_string(),
+                });
+            }
+            position += 1;
+            column += 1;
+        }
+    }
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::random_sequence;
+
+    fn test_scores() -> Scores {
+        Scores { match_score: 1.0, mismatch_score: -3.0, gap_open: -8.0, gap_ext: -1.0, gap_start: -8.0, gap_end: -8.0, substitution_matrix: None }
+    }
+
+    #[test]
+    fn test_aligned_differences() {
+        let reference: Vec<char> = "ACGTACGT--ACGTAC".chars().collect();
+        let sample: Vec<char> = "ACGAACGTTTAC---C".chars().collect();
+        let differences = aligned_differences(&reference, &sample);
+        assert_eq!(differences.iter().map(|difference| difference.kind).collect::<Vec<_>>(),
+                   vec![DifferenceKind::Snv, DifferenceKind::Insertion, DifferenceKind::Deletion]);
+        assert_eq!((differences[0].reference_start, differences[0].reference_end), (3, 4));
+        assert_eq!((differences[1].reference_start, differences[1].reference_end), (8, 8));
+        assert_eq!(differences[1].sample_bases, "TT");
+        assert_eq!((differences[2].reference_start, differences[2].reference_end), (10, 13));
+        assert_eq!(differences[2].reference_bases, "GTA");
+    }
+
+    #[test]
+    fn test_rotated_reverse_complement_sample() {
+        let reference = random_sequence(600, 3);
+        let mut sample = reference.clone();
+        sample[100] = if sample[100] == 'A' { 'C' } else { 'A' };
+        sample.drain(303..363);
+        let inserted = ['A', 'C', 'G', 'T'].iter().cloned().find(|&base| base != reference[199] && base != reference[200]).unwrap();
+        sample.splice(200..200, vec![inserted; 3]);
+        let sample = rotate_reference(&sample, 250).unwrap();
+        let sample: Vec<char> = ReferenceKmers::reverse_complement_sequence(&sample.iter().collect::<String>()).chars().collect();
+
+        let comparison = compare_to_reference("sample", &sample, &reference, &test_scores(), 1024 * 1024 * 1024).unwrap();
+        assert!(comparison.reverse_complemented);
+        let found: Vec<(DifferenceKind, usize, usize)> = comparison.differences.iter()
+            .map(|difference| (difference.kind, difference.reference_start, difference.reference_end)).collect();
+        assert_eq!(found, vec![(DifferenceKind::Snv, 100, 101),
+                               (DifferenceKind::Insertion, 200, 200),
+                               (DifferenceKind::StructuralDeletion, 303, 363)]);
+
+        let rows = comparison.rows("sample", "map");
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[3].kind, DifferenceKind::Summary);
+        assert_eq!(rows[3].strand.as_deref(), Some("reverse"));
+    }
+
+    #[test]
+    fn test_construct_too_long_for_the_full_matrices() {
+        let reference = random_sequence(15500, 21);
+        let mut sample = reference.clone();
+        sample[4000] = if sample[4000] == 'A' { 'C' } else { 'A' };
+        sample.drain(9000..9080);
+        let sample = rotate_reference(&sample, 6000).unwrap();
+
+        // the full matrices would need over 6 GB here
+        let max_memory = 4096 * 1024 * 1024;
+        assert!(affine_gap::affine_matrix_bytes(reference.len(), sample.len()) > max_memory);
+        let comparison = compare_to_reference("sample", &sample, &reference, &test_scores(), max_memory).unwrap();
+        assert!(!comparison.reverse_complemented);
+        let found: Vec<(DifferenceKind, usize, usize)> = comparison.differences.iter()
+            .map(|difference| (difference.kind, difference.reference_start, difference.reference_end)).collect();
+        assert_eq!(found, vec![(DifferenceKind::Snv, 4000, 4001), (DifferenceKind::StructuralDeletion, 9000, 9080)]);
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs;
+
+use toml;
+
+use dedup::{Deduplicator, InvertedMode};
+use engine::AlignmentEngine;
+use error::DupScoopError;
+use needleman::Scores;
+use score_matrix::PairedScores;
+use validate::InvalidBaseMode;
+
+/// The settings for a run, as read from a TOML file. Every value is optional: scores start from the
+/// preset (or the default scores), thresholds from the command line defaults, and anything set here
+/// overrides them. Command line flags are layered on top the same way.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RunConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub scores: ScoreConfig,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+}
+
+/// overrides for the fields of Scores, plus a substitution matrix (iupac, or the path of a matrix file)
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScoreConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mismatch_score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_open: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_ext: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_start: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_end: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<String>,
+}
+
+/// overrides for the Deduplicator thresholds
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DedupConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_identity: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagonal_distance: Option<i32>,
+    /// ignore, report, or collapse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inverted: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_memory_mb: Option<usize>,
+    /// the k-mer size of the seed prefilter; no prefilter if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefilter_kmer: Option<usize>,
+    /// linear or affine
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine: Option<String>,
+    /// align the plasmid as a circle instead of against its doubled sequence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circular: Option<bool>,
+    /// trim a suffix that repeats the prefix of a linear contig before deduplicating
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trim_overlap: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_overlap: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detect_only: Option<bool>,
+    /// reject or mask
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalid_bases: Option<String>,
+    /// keep lowercase bases lowercase in the output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_mask: Option<bool>,
+}
+
+impl RunConfig {
+    pub fn from_file(path: &str) -> Result<RunConfig, DupScoopError> {
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(|error| DupScoopError::InvalidConfig { path: path.to_string(), message: error.message().to_string() })
+    }
+
+    /// Every setting of a run spelled out, so the file reproduces it even if the defaults or presets change
+    pub fn resolved(deduplicator: &Deduplicator, matrix: Option<&str>, detect_only: bool) -> RunConfig {
+        let scores = &deduplicator.scores;
+        RunConfig {
+            preset: None,
+            scores: ScoreConfig {
+                match_score: Some(scores.match_score),
+                mismatch_score: Some(scores.mismatch_score),
+                gap_open: Some(scores.gap_open),
+                gap_ext: Some(scores.gap_ext),
+                gap_start: Some(scores.gap_start),
+                gap_end: Some(scores.gap_end),
+                matrix: matrix.map(|name| name.to_string()),
+            },
+            dedup: DedupConfig {
+                min_length: Some(deduplicator.min_length),
+                min_identity: Some(deduplicator.min_identity),
+                diagonal_distance: Some(deduplicator.diag_dist),
+                inverted: Some(deduplicator.inverted.name().to_string()),
+                max_memory_mb: Some(deduplicator.max_memory / 1024 / 1024),
+                prefilter_kmer: deduplicator.prefilter,
+                engine: Some(deduplicator.engine.name().to_string()),
+                circular: Some(deduplicator.circular),
+                trim_overlap: Some(deduplicator.trim_overlap),
+                min_overlap: Some(deduplicator.min_overlap),
+                detect_only: Some(detect_only),
+                invalid_bases: Some(deduplicator.invalid_bases.name().to_string()),
+                soft_mask: Some(deduplicator.soft_mask),
+            },
+        }
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), DupScoopError> {
+        let contents = toml::to_string(self).map_err(|error| DupScoopError::InvalidConfig { path: path.to_string(), message: error.to_string() })?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// the preset's scores with the configured overrides and substitution matrix applied
+    pub fn scores(&self) -> Result<Scores, DupScoopError> {
+        let preset = self.preset.as_deref().unwrap_or("default");
+        let base = Scores::preset(preset).ok_or_else(|| DupScoopError::InvalidArgument(format!("unknown scoring preset {} (expected one of {})", preset, Scores::PRESETS.join(", "))))?;
+        let overrides = &self.scores;
+        let scores = Scores {
+            match_score: overrides.match_score.unwrap_or(base.match_score),
+            mismatch_score: overrides.mismatch_score.unwrap_or(base.mismatch_score),
+            gap_open: overrides.gap_open.unwrap_or(base.gap_open),
+            gap_ext: overrides.gap_ext.unwrap_or(base.gap_ext),
+            gap_start: overrides.gap_start.unwrap_or(base.gap_start),
+            gap_end: overrides.gap_end.unwrap_or(base.gap_end),
+            substitution_matrix: None,
+        };
+        match overrides.matrix {
+            Some(ref name) => substitution_scores(scores, name),
+            None => Ok(scores),
+        }
+    }
+
+    /// a Deduplicator with these scores and thresholds
+    pub fn deduplicator(&self) -> Result<Deduplicator, DupScoopError> {
+        let settings = &self.dedup;
+        let mut deduplicator = Deduplicator::new(self.scores()?,
+                                                 settings.min_length.unwrap_or(1000),
+                                                 settings.min_identity.unwrap_or(0.9),
+                                                 settings.diagonal_distance.unwrap_or(10));
+        if let Some(ref name) = settings.inverted {
+            deduplicator.inverted = InvertedMode::from_name(name).ok_or_else(|| DupScoopError::InvalidArgument(format!("unknown inverted mode {}", name)))?;
+        }
+        deduplicator.max_memory = settings.max_memory_mb.unwrap_or(4096) * 1024 * 1024;
+        deduplicator.prefilter = settings.prefilter_kmer;
+        if let Some(ref name) = settings.engine {
+            deduplicator.engine = AlignmentEngine::from_name(name).ok_or_else(|| DupScoopError::InvalidArgument(format!("unknown alignment engine {}", name)))?;
+        }
+        deduplicator.circular = settings.circular.unwrap_or(false);
+        if deduplicator.circular && deduplicator.engine != AlignmentEngine::Linear {
+            return Err(DupScoopError::InvalidArgument(format!("--circular only works with the linear engine, not {}", deduplicator.engine.name())));
+        }
+        deduplicator.trim_overlap = settings.trim_overlap.unwrap_or(false);
+        deduplicator.min_overlap = settings.min_overlap.unwrap_or(100);
+        if let Some(ref name) = settings.invalid_bases {
+            deduplicator.invalid_bases = InvalidBaseMode::from_name(name).ok_or_else(|| DupScoopError::InvalidArgument(format!("unknown invalid base mode {}", name)))?;
+        }
+        deduplicator.soft_mask = settings.soft_mask.unwrap_or(false);
+        Ok(deduplicator)
+    }
+}
+
+/// Score base pairs from a named or file-based substitution matrix. A matrix file brings its own match and
+/// mismatch scores, so its best and worst entries replace them.
+pub fn substitution_scores(scores: Scores, name: &str) -> Result<Scores, DupScoopError> {
+    if name.eq_ignore_ascii_case("iupac") {
+        let matrix = PairedScores::default_scores(&scores.match_score, &scores.mismatch_score);
+        return Ok(scores.with_substitution_matrix(matrix));
+    }
+    let matrix = PairedScores::from_file(name)?;
+    let (match_score, mismatch_score) = matrix.score_range();
+    eprintln!("Loaded substitution matrix {} (scores {} to {})", matrix.name(), mismatch_score, match_score);
+    Ok(Scores { match_score, mismatch_score, ..scores }.with_substitution_matrix(matrix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_with_overrides() {
+        let config: RunConfig = toml::from_str("preset = \"plasmid\"\n[scores]\nmismatch_score = -4\n[dedup]\nmin_length = 500\ninverted = \"report\"\n").unwrap();
+        let deduplicator = config.deduplicator().unwrap();
+        assert_eq!(deduplicator.scores.match_score, 2.0);
+        assert_eq!(deduplicator.scores.mismatch_score, -4.0);
+        assert_eq!(deduplicator.scores.gap_ext, -0.5);
+        assert_eq!(deduplicator.min_length, 500);
+        assert_eq!(deduplicator.min_identity, 0.9);
+        assert_eq!(deduplicator.inverted, InvertedMode::Report);
+        assert_eq!(deduplicator.prefilter, None);
+    }
+
+    #[test]
+    fn test_resolved_round_trip() {
+        let mut deduplicator = RunConfig::default().deduplicator().unwrap();
+        deduplicator.prefilter = Some(15);
+        let resolved = RunConfig::resolved(&deduplicator, None, true);
+        let reread: RunConfig = toml::from_str(&toml::to_string(&resolved).unwrap()).unwrap();
+        assert_eq!(reread, resolved);
+
+        let rebuilt = reread.deduplicator().unwrap();
+        assert_eq!(rebuilt.scores.gap_ext, Scores::default_scores().gap_ext);
+        assert_eq!(rebuilt.prefilter, Some(15));
+        assert_eq!(rebuilt.max_memory, deduplicator.max_memory);
+    }
+
+    #[test]
+    fn test_bad_values() {
+        assert!(toml::from_str::<RunConfig>("[scores]\nmatch = 2\n").is_err());
+        let unknown_preset: RunConfig = toml::from_str("preset = \"pacbio\"\n").unwrap();
+        assert!(unknown_preset.deduplicator().is_err());
+        let unknown_engine: RunConfig = toml::from_str("[dedup]\nengine = \"gotoh\"\n").unwrap();
+        assert!(unknown_engine.deduplicator().is_err());
+        let circular_affine: RunConfig = toml::from_str("[dedup]\nengine = \"affine\"\ncircular = true\n").unwrap();
+        assert!(circular_affine.deduplicator().is_err());
+    }
+}
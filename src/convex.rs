@@ -3,19 +3,18 @@ use needleman::Scores;
 use needleman::Alignment;
 
 #[allow(dead_code)]
-pub fn convex(seq1: &Vec<char>, seq2: &Vec<char>, scores: &Scores) -> Alignment {
+pub fn convex(seq1: &[char], seq2: &[char], scores: &Scores) -> Alignment {
     let seq1_limit = seq1.len() + 1;
     let seq2_limit = seq2.len() + 1;
 
     let mut mtx = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0.0);
-    ////println!("made matrix of {} {}", mtx.rows(), mtx.cols());
     let mut trc: mymatrix::MyMatrix<i32> = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0);
     convex_alignment(seq1, seq2, &mut mtx, &mut trc, scores)
 }
 
 #[allow(dead_code)]
-pub fn convex_alignment(seq1: &Vec<char>,
-                        seq2: &Vec<char>,
+pub fn convex_alignment(seq1: &[char],
+                        seq2: &[char],
                         mtx: &mut mymatrix::MyMatrix<f64>,
                         trc: &mut mymatrix::MyMatrix<i32>,
                         scores: &Scores) -> Alignment {
@@ -43,15 +42,15 @@ pub fn convex_alignment(seq1: &Vec<char>,
     }
     for n in 1..seq2_limit {
         mtx.set(0, n, scoring_function(n));
-        trc.set(0, n, -1 * (n as i32));
+        trc.set(0, n, -(n as i32));
     }
 
     // fill in the matrix
     for ix in 1..seq1_limit {
         for iy in 1..seq2_limit {
             let score = Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
-            let up = mymatrix::maximize_over_column(&mtx, iy, ix, &scoring_function);
-            let left = mymatrix::maximize_over_row(&mtx, ix, iy, &scoring_function);
+            let up = mymatrix::maximize_over_column(mtx, iy, ix, &scoring_function);
+            let left = mymatrix::maximize_over_row(mtx, ix, iy, &scoring_function);
             let diag = mtx.get(ix - 1, iy - 1) + score;
 
             if up.1 > left.1 {
@@ -65,7 +64,7 @@ pub fn convex_alignment(seq1: &Vec<char>,
             } else {
                 if diag < left.1 {
                     mtx.set(ix, iy, left.1);
-                    trc.set(ix, iy, -1 * (iy as i32 - left.0 as i32));
+                    trc.set(ix, iy, -(iy as i32 - left.0 as i32));
                 } else {
                     mtx.set(ix, iy, diag);
                     trc.set(ix, iy, 0);
@@ -75,9 +74,8 @@ pub fn convex_alignment(seq1: &Vec<char>,
     }
 
     let no_cost = |_i: usize| -> f64 {0 as f64};
-    //println!("row_index={},rows={} -- {},{}",mtx.rows(),mtx.cols(),seq1_limit,seq2_limit);
-    let start_row = mymatrix::maximize_over_column(&mtx, seq2_limit - 1, seq1_limit - 1, &no_cost);
-    let start_column = mymatrix::maximize_over_row(&mtx, seq1_limit - 1, seq2_limit - 1, &no_cost);
+    let start_row = mymatrix::maximize_over_column(mtx, seq2_limit - 1, seq1_limit - 1, &no_cost);
+    let start_column = mymatrix::maximize_over_row(mtx, seq1_limit - 1, seq2_limit - 1, &no_cost);
     let lower_right = mtx.get(seq1_limit - 1, seq2_limit - 1);
 
     if lower_right > start_column.1 {
@@ -96,7 +94,7 @@ pub fn convex_alignment(seq1: &Vec<char>,
 }
 
 /// traceback a matrix into an alignment struct
-pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, start_row: usize, start_column: usize, trc: &mymatrix::MyMatrix<i32>, score: f64) -> Alignment {
+pub fn traceback(seq1: &[char], seq2: &[char], start_row: usize, start_column: usize, trc: &mymatrix::MyMatrix<i32>, score: f64) -> Alignment {
     assert_eq!(seq1.len(), trc.rows() - 1, "The matrix doesn't have the right number of rows; rows: {}, expected: {}", seq1.len(), trc.rows() - 1);
     assert_eq!(seq2.len(), trc.cols() - 1, "The matrix doesn't have the right number of columns; columns: {}, expected: {}", seq2.len(), trc.cols() - 1);
 
@@ -109,12 +107,12 @@ pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, start_row: usize, start_col
     // if we're off the final score
     if row_index + 1 < trc.rows() as u32 {
         alignment2.append(&mut gap_of_length((trc.rows() - 1) - row_index as usize));
-        let alignment1_reversed = &mut seq1[row_index as usize ..((trc.rows() - 1) as usize)].to_vec();
+        let alignment1_reversed = &mut seq1[row_index as usize ..(trc.rows() - 1)].to_vec();
         alignment1_reversed.reverse();
         alignment1.append(alignment1_reversed);
     }
     if column_index + 1 < trc.cols() as u32 {
-        let alignment2_reversed = &mut seq2[column_index as usize ..((trc.cols() - 1) as usize)].to_vec();
+        let alignment2_reversed = &mut seq2[column_index as usize ..(trc.cols() - 1)].to_vec();
         alignment2_reversed.reverse();
         alignment2.append(alignment2_reversed);
         alignment1.append(&mut gap_of_length((trc.cols() - 1) - column_index as usize));
@@ -136,10 +134,10 @@ pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, start_row: usize, start_col
                 column_index -= 1;
             }
             _x if _x < 0 => {
-                let offset = (-1 * _x) as usize;
+                let offset = -_x as usize;
                 let move_to_column = column_index as usize - offset;
 
-                assert!(((-1 * _x) as u32) <= column_index);
+                assert!((-_x as u32) <= column_index);
                 alignment1.append(&mut gap_of_length(offset));
                 let alignment2_reversed = &mut seq2[move_to_column..(column_index as usize)].to_vec();
                 alignment2_reversed.reverse();
@@ -155,7 +153,7 @@ pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, start_row: usize, start_col
                 alignment1_reversed.reverse();
                 alignment1.append(alignment1_reversed);
                 alignment2.append(&mut gap_of_length(_x as usize));
-                row_index = move_to_row as u32;
+                row_index = move_to_row;
             }
             _ => unreachable!()
         }
@@ -164,17 +162,17 @@ pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, start_row: usize, start_col
     alignment1.reverse();
     alignment2.reverse();
 
-    return Alignment {
+    Alignment {
         seq_one: seq1.to_vec(),
         seq_two: seq2.to_vec(),
-        score: score,
+        score,
         start_x: 0,
         start_y: 0,
         end_x: start_row,
         end_y: start_column,
         seq_one_aligned: alignment1,
         seq_two_aligned: alignment2,
-    };
+    }
 }
 
 fn gap_of_length(x: usize) -> Vec<char> {
@@ -191,8 +189,8 @@ mod tests {
         let mut mtx = mymatrix::MyMatrix::new(4, 4, 0.0);
         let mut trc: mymatrix::MyMatrix<i32> = mymatrix::MyMatrix::new(4, 4, 0);
 
-        let alignment = convex_alignment(&vec!['A', 'A', 'A'],
-                                         &vec!['A', 'A', 'A'],
+        let alignment = convex_alignment(&['A', 'A', 'A'],
+                                         &['A', 'A', 'A'],
                                          &mut mtx,
                                          &mut trc,
                                          &scores);
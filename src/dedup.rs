@@ -0,0 +1,858 @@
+use std::cmp::{min,max,Ordering};
+use std::iter::FromIterator;
+
+use circular;
+use engine::AlignmentEngine;
+use error::DupScoopError;
+use kmer_orientation::ReferenceKmers;
+use liftover::Liftover;
+use linear_space;
+use needleman::{Scores, Alignment};
+use overlap;
+use overlap::EndOverlap;
+use report::{EventKind, ReportEvent};
+use seed_chain;
+use seed_chain::SeedChain;
+use smith_waterman_no_diag;
+use smith_waterman_no_diag::{MatrixWindow, CANDIDATE_BYTES, MIN_CANDIDATES};
+use striped;
+use striped::IntegerScores;
+use validate;
+use validate::InvalidBaseMode;
+
+/// Finds and removes duplicated regions of circular plasmids. The thresholds are public fields; new()
+/// fills in the same defaults as the command line.
+#[derive(Clone)]
+pub struct Deduplicator {
+    pub scores: Scores,
+    /// copies have to be longer than this to count as a duplication
+    pub min_length: u64,
+    /// and share more than this proportion of their aligned columns
+    pub min_identity: f64,
+    /// alignments this close to the trivial diagonal are ignored
+    pub diag_dist: i32,
+    pub inverted: InvertedMode,
+    /// bytes we're allowed to spend on one alignment
+    pub max_memory: usize,
+    /// if set, only align around chains of shared k-mers of this size
+    pub prefilter: Option<usize>,
+    /// the local aligner used to search for duplications
+    pub engine: AlignmentEngine,
+    /// search with the circular n x n aligner rather than against the doubled reference
+    pub circular: bool,
+    /// treat the input as a linear contig and trim a suffix that repeats its prefix before the duplication search
+    pub trim_overlap: bool,
+    /// end overlaps have to be at least this long to be trimmed
+    pub min_overlap: usize,
+    /// whether characters that aren't IUPAC codes fail the record or are masked as N
+    pub invalid_bases: InvalidBaseMode,
+    /// keep lowercase (soft-masked) bases lowercase in the output
+    pub soft_mask: bool,
+}
+
+/// One duplicated segment, in coordinates of the sequence at the time it was found (earlier removals
+/// shift later duplications). The second copy is on the forward strand and ends before it starts when it
+/// wraps the origin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplication {
+    pub first_start: usize,
+    pub first_end: usize,
+    pub second_start: usize,
+    pub second_end: usize,
+    pub score: f64,
+    pub identity: f64,
+    /// whether the second copy is the reverse complement of the first
+    pub inverted: bool,
+}
+
+/// The outcome of deduplicating one sequence
+pub struct DedupResult {
+    pub sequence: Vec<u8>,
+    pub duplications: Vec<Duplication>,
+    /// the suffix-prefix overlap found with trim_overlap, in input coordinates
+    pub end_overlap: Option<EndOverlap>,
+    /// every step taken, ending with a summary row, for the --report output
+    pub events: Vec<ReportEvent>,
+    /// maps input coordinates to output coordinates
+    pub liftover: Liftover,
+}
+
+impl Deduplicator {
+    pub fn new(scores: Scores, min_length: u64, min_identity: f64, diag_dist: i32) -> Deduplicator {
+        Deduplicator {
+            scores,
+            min_length,
+            min_identity,
+            diag_dist,
+            inverted: InvertedMode::Ignore,
+            max_memory: 4096 * 1024 * 1024,
+            prefilter: None,
+            engine: AlignmentEngine::Linear,
+            circular: false,
+            trim_overlap: false,
+            min_overlap: 100,
+            invalid_bases: InvalidBaseMode::Reject,
+            soft_mask: false,
+        }
+    }
+
+    /// list the duplications in a sequence without changing it
+    pub fn detect(&self, sequence: &[u8]) -> Result<Vec<Duplication>, DupScoopError> {
+        Ok(self.detect_record("sequence", sequence)?.duplications)
+    }
+
+    /// remove duplications until none are left
+    pub fn deduplicate(&self, sequence: &[u8]) -> Result<DedupResult, DupScoopError> {
+        self.deduplicate_record("sequence", sequence)
+    }
+
+    /// like detect, but the report rows are labelled with the record name and the unchanged sequence is returned too
+    pub fn detect_record(&self, record_id: &str, sequence: &[u8]) -> Result<DedupResult, DupScoopError> {
+        deduplicate_reference(record_id, &sequence_to_chars(sequence), self, true)
+    }
+
+    /// like deduplicate, with the report rows labelled with the record name
+    pub fn deduplicate_record(&self, record_id: &str, sequence: &[u8]) -> Result<DedupResult, DupScoopError> {
+        deduplicate_reference(record_id, &sequence_to_chars(sequence), self, false)
+    }
+}
+
+impl Duplication {
+    /// a forward duplication from an alignment of the reference against its doubled copy
+    fn from_alignment(alignment: &Alignment, identity: f64, reference_length: usize) -> Duplication {
+        let second_start = alignment.start_y % reference_length;
+        Duplication {
+            first_start: alignment.start_x,
+            first_end: alignment.end_x,
+            second_start,
+            second_end: circular_end(second_start, alignment.end_y - alignment.start_y, reference_length),
+            score: alignment.score,
+            identity,
+            inverted: false,
+        }
+    }
+}
+
+fn sequence_to_chars(sequence: &[u8]) -> Vec<char> {
+    sequence.iter().map(|&base| base as char).collect()
+}
+
+/// repeatedly find and remove duplicated regions from a single plasmid until none remain, recording each
+/// removal in the report and tracking the coordinate changes in a liftover
+pub fn deduplicate_reference(record_id: &str, raw_reference: &[char], settings: &Deduplicator, detect_only: bool) -> Result<DedupResult, DupScoopError> {
+    let (cleaned_reference, normalization) = validate::normalize_sequence(record_id, raw_reference, settings.invalid_bases)?;
+    let reference_as_chars: &Vec<char> = &cleaned_reference.iter().map(|base| base.to_ascii_uppercase()).collect();
+    if reference_as_chars.is_empty() {
+        return Err(DupScoopError::EmptySequence(record_id.to_string()));
+    }
+    let required = linear_space::linear_space_bytes(reference_as_chars.len(), 2 * reference_as_chars.len());
+    if required > settings.max_memory {
+        return Err(DupScoopError::SequenceTooLong { record: record_id.to_string(), required, max_memory: settings.max_memory });
+    }
+    let scores = &settings.scores;
+    let mut events = Vec::new();
+    // the liftover starts from the record as it was read, so its input coordinates count any line endings
+    // and position numbers that normalize_sequence dropped
+    let mut liftover = Liftover::from_input_positions(validate::kept_positions(raw_reference), raw_reference.len());
+    if !normalization.is_empty() {
+        events.push(ReportEvent {
+            event: EventKind::Normalized,
+            iteration: None,
+            detail: Some(normalization.describe()),
+            ..ReportEvent::summary(record_id, 0, raw_reference.len(), reference_as_chars.len())
+        });
+    }
+
+    let mut current_reference = reference_as_chars.clone();
+    let end_overlap = if settings.trim_overlap {
+        overlap::find_end_overlap(reference_as_chars, scores, settings.min_overlap, settings.min_identity, settings.max_memory)
+    } else {
+        None
+    };
+    if let Some(ref overlap) = end_overlap {
+        let reference_length = reference_as_chars.len();
+        let mut event = ReportEvent {
+            record: record_id.to_string(),
+            event: EventKind::EndOverlap,
+            iteration: None,
+            start_x: Some(overlap.suffix_start),
+            end_x: Some(reference_length),
+            start_y: Some(0),
+            end_y: Some(overlap.prefix_end),
+            score: Some(overlap.score),
+            identity: Some(overlap.identity),
+            rotation: None,
+            removed_start: None,
+            removed_end: None,
+            length_before: reference_length,
+            length_after: reference_length,
+            detail: None,
+        };
+        // detect-only mode reports the overlap but leaves the contig linear
+        if !detect_only {
+            current_reference.truncate(overlap.suffix_start);
+            liftover.excise(overlap.suffix_start, reference_length);
+            event.removed_start = Some(overlap.suffix_start);
+            event.removed_end = Some(reference_length);
+            event.length_after = overlap.suffix_start;
+        }
+        events.push(event);
+    }
+
+    let mut iteration = 0;
+    let mut duplications = Vec::new();
+    if detect_only {
+        let duplicates = find_duplicate_regions(&current_reference, scores, settings);
+        if duplicates.is_empty() {
+            eprintln!("No duplications found in {}", record_id);
+        }
+        for (index, (alignment, identity)) in duplicates.iter().enumerate() {
+            events.push(duplication_event(record_id, index + 1, alignment, *identity, current_reference.len()));
+            duplications.push(Duplication::from_alignment(alignment, *identity, current_reference.len()));
+        }
+    } else {
+        // one alignment pass finds every duplicate that's currently visible; removing them can expose
+        // more, so keep going until a pass comes back empty
+        let mut duplicates = find_duplicate_regions(&current_reference, scores, settings);
+        if duplicates.is_empty() {
+            eprintln!("No duplications found in {}", record_id);
+        }
+        while !duplicates.is_empty() {
+            let reference_length = current_reference.len();
+            let deletions: Vec<(usize, usize)> = duplicates.iter().map(|(alignment, _)| duplicate_deletion(alignment, reference_length)).collect();
+
+            // rotate the best duplicate's deletion to the origin; the deletions don't overlap, so none of them wrap after that
+            let rotation = (reference_length - deletions[0].0) % reference_length;
+            let mut deduplicated_reference = rotate_reference(&current_reference, rotation)?;
+            liftover.rotate(rotation);
+            let mut rotated_deletions: Vec<(usize, usize)> = deletions.iter().map(|(start, length)| ((start + rotation) % reference_length, *length)).collect();
+
+            let mut length_before = reference_length;
+            for (index, ((alignment, identity), (start_del, length))) in duplicates.iter().zip(rotated_deletions.iter()).enumerate() {
+                iteration += 1;
+                // each row's removed interval is in the sequence its length_before describes: the rotated
+                // sequence with the pass's earlier rows already cut out
+                let shift: usize = rotated_deletions[..index].iter().filter(|(earlier_start, _)| earlier_start < start_del).map(|(_, earlier_length)| earlier_length).sum();
+                let mut event = duplication_event(record_id, iteration, alignment, *identity, length_before);
+                event.rotation = Some(rotation);
+                event.removed_start = Some(start_del - shift);
+                event.removed_end = Some(start_del - shift + length);
+                event.length_after = length_before - length;
+                events.push(event);
+                duplications.push(Duplication::from_alignment(alignment, *identity, reference_length));
+                length_before -= length;
+            }
+
+            // cut from the back so earlier coordinates stay valid
+            rotated_deletions.sort_by_key(|deletion| std::cmp::Reverse(deletion.0));
+            for (start_del, length) in rotated_deletions {
+                deduplicated_reference.drain(start_del..start_del + length);
+                liftover.excise(start_del, start_del + length);
+            }
+
+            eprintln!("Removed {} duplications from {} ({} bases left), looking for more", duplicates.len(), record_id, deduplicated_reference.len());
+            current_reference = deduplicated_reference;
+            duplicates = find_duplicate_regions(&current_reference, scores, settings);
+        }
+    }
+
+    // in detect-only mode we never touch the sequence, even for inverted repeats
+    let inverted = if detect_only && settings.inverted == InvertedMode::Collapse { InvertedMode::Report } else { settings.inverted };
+    if inverted != InvertedMode::Ignore {
+        // reported repeats are masked out of the searched copy so each search turns up the next one
+        let mut searched_reference = current_reference.clone();
+        let mut check_inverted = check_for_inverted_region(&searched_reference, scores, settings);
+        while check_inverted.0 {
+            let (_, alignment, identity, partner_start, partner_length) = check_inverted;
+            let reference_length = current_reference.len();
+            let mut event = ReportEvent {
+                record: record_id.to_string(),
+                event: EventKind::InvertedRepeat,
+                iteration: None,
+                start_x: Some(alignment.start_x),
+                end_x: Some(alignment.end_x),
+                start_y: Some(partner_start),
+                end_y: Some(circular_end(partner_start, partner_length, reference_length)),
+                score: Some(alignment.score),
+                identity: Some(identity),
+                rotation: None,
+                removed_start: None,
+                removed_end: None,
+                length_before: reference_length,
+                length_after: reference_length,
+                detail: None,
+            };
+            duplications.push(Duplication {
+                first_start: alignment.start_x,
+                first_end: alignment.end_x,
+                second_start: partner_start,
+                second_end: circular_end(partner_start, partner_length, reference_length),
+                score: alignment.score,
+                identity,
+                inverted: true,
+            });
+            if inverted == InvertedMode::Report {
+                events.push(event);
+                for offset in 0..partner_length {
+                    searched_reference[(partner_start + offset) % reference_length] = 'N';
+                }
+                check_inverted = check_for_inverted_region(&searched_reference, scores, settings);
+                continue;
+            }
+
+            // rotate the inverted copy to the start of the plasmid and cut it off
+            iteration += 1;
+            let rotation = (reference_length - partner_start) % reference_length;
+            let mut rotated_reference = rotate_reference(&current_reference, rotation)?;
+            rotated_reference.drain(0..partner_length);
+            liftover.rotate(rotation);
+            liftover.excise(0, partner_length);
+
+            event.iteration = Some(iteration);
+            event.rotation = Some(rotation);
+            event.removed_start = Some(0);
+            event.removed_end = Some(partner_length);
+            event.length_after = rotated_reference.len();
+            events.push(event);
+
+            eprintln!("Removed an inverted repeat from {} ({} bases left), looking for more", record_id, rotated_reference.len());
+            current_reference = rotated_reference;
+            searched_reference = current_reference.clone();
+            check_inverted = check_for_inverted_region(&searched_reference, scores, settings);
+        }
+    }
+    events.push(ReportEvent::summary(record_id, iteration, reference_as_chars.len(), current_reference.len()));
+    if settings.soft_mask {
+        // every base kept came from somewhere in the input, so it takes that base's case back
+        for (position, base) in current_reference.iter_mut().enumerate() {
+            if liftover.to_input(position).map(|input_position| raw_reference[input_position].is_ascii_lowercase()) == Some(true) {
+                *base = base.to_ascii_lowercase();
+            }
+        }
+    }
+    Ok(DedupResult {
+        sequence: current_reference.iter().map(|&base| base as u8).collect(),
+        duplications,
+        end_overlap,
+        events,
+        liftover,
+    })
+}
+
+/// a report row for a forward duplication that hasn't been removed (yet)
+fn duplication_event(record_id: &str, iteration: usize, alignment: &Alignment, identity: f64, reference_length: usize) -> ReportEvent {
+    // the same circular coordinates as the Duplication the library hands back
+    let duplication = Duplication::from_alignment(alignment, identity, reference_length);
+    ReportEvent {
+        record: record_id.to_string(),
+        event: EventKind::Duplication,
+        iteration: Some(iteration),
+        start_x: Some(duplication.first_start),
+        end_x: Some(duplication.first_end),
+        start_y: Some(duplication.second_start),
+        end_y: Some(duplication.second_end),
+        score: Some(alignment.score),
+        identity: Some(identity),
+        rotation: None,
+        removed_start: None,
+        removed_end: None,
+        length_before: reference_length,
+        length_after: reference_length,
+        detail: None,
+    }
+}
+
+/// seeds on the same diagonal further apart than this start a new chain
+const SEED_MAX_GAP: usize = 100;
+/// how far past the ends of a seed chain the alignment window reaches
+const SEED_FLANK: usize = 100;
+
+/// how inverted (reverse-complement) repeats are handled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvertedMode {
+    Ignore,
+    Report,
+    Collapse,
+}
+
+impl InvertedMode {
+    pub fn from_name(name: &str) -> Option<InvertedMode> {
+        match name.to_lowercase().as_str() {
+            "ignore" => Some(InvertedMode::Ignore),
+            "report" => Some(InvertedMode::Report),
+            "collapse" => Some(InvertedMode::Collapse),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InvertedMode::Ignore => "ignore",
+            InvertedMode::Report => "report",
+            InvertedMode::Collapse => "collapse",
+        }
+    }
+}
+
+/// align the reference against the reverse complement of its doubled copy. Returns whether the best hit
+/// passes the duplication thresholds, the alignment, its identity, and the start and length of the
+/// inverted partner copy on the forward strand
+fn check_for_inverted_region(reference: &[char], scores: &Scores, settings: &Deduplicator) -> (bool, Alignment, f64, usize, usize) {
+    let doubled_reference = String::from_iter(double_reference(reference));
+    let reverse_complement: Vec<char> = ReferenceKmers::reverse_complement_sequence(&doubled_reference).chars().collect();
+
+    // there's no trivial diagonal when aligning against the other strand
+    let alignment = best_local_alignment(reference, &reverse_complement, scores, 0, settings);
+    let length_one = alignment.end_x - alignment.start_x;
+    let length_two = alignment.end_y - alignment.start_y;
+    let matching_prop = alignment_identity(&alignment);
+
+    // position i of the reverse complement is base (2n - 1 - i) of the doubled forward sequence
+    let partner_start = (reverse_complement.len() - alignment.end_y) % reference.len();
+
+    // a palindromic stretch aligned onto itself isn't a repeat
+    let overlaps_self = circular_overlap(alignment.start_x, length_one, partner_start, length_two, reference.len());
+    let is_dup = min(length_one, length_two) > settings.min_length as usize && settings.min_identity < matching_prop && !overlaps_self;
+    (is_dup, alignment, matching_prop, partner_start, length_two)
+}
+
+/// the end coordinate of a circular interval; the end is less than the start when it wraps the origin
+fn circular_end(start: usize, length: usize, reference_length: usize) -> usize {
+    if start + length > reference_length { start + length - reference_length } else { start + length }
+}
+
+fn circular_overlap(start_one: usize, length_one: usize, start_two: usize, length_two: usize, reference_length: usize) -> bool {
+    let offset = (start_two + reference_length - start_one) % reference_length;
+    offset < length_one || offset + length_two > reference_length
+}
+
+/// concatenate the reference with itself, so alignments can run across the origin of the circular plasmid
+fn double_reference(reference: &[char]) -> Vec<char> {
+    let mut reference_duplicated = reference.to_vec();
+    reference_duplicated.extend_from_slice(reference);
+    reference_duplicated
+}
+
+/// the circular interval (start, length) to cut out for one duplicate. Separate copies lose the shorter
+/// one; copies that overlap (tandem repeats) lose only the part of the second copy past the first.
+fn duplicate_deletion(alignment: &Alignment, reference_length: usize) -> (usize, usize) {
+    let length_one = alignment.end_x - alignment.start_x;
+    let length_two = alignment.end_y - alignment.start_y;
+    let offset = (alignment.start_y % reference_length + reference_length - alignment.start_x) % reference_length;
+
+    // in coordinates relative to the start of the first copy
+    let (start_del, end_del) = if offset >= length_one && offset + length_two <= reference_length {
+        if length_two <= length_one { (offset, offset + length_two) } else { (0, length_one) }
+    } else if max(offset, length_one) < min(offset + length_two, reference_length) {
+        (max(offset, length_one), min(offset + length_two, reference_length))
+    } else {
+        (0, min(length_one, offset))
+    };
+    ((alignment.start_x + start_del) % reference_length, end_del - start_del)
+}
+
+/// the forward-strand intervals (start, length) covered by the two copies of a duplicate
+fn duplicate_copies(alignment: &Alignment, reference_length: usize) -> [(usize, usize); 2] {
+    [(alignment.start_x, alignment.end_x - alignment.start_x),
+        (alignment.start_y % reference_length, second_copy_length(alignment, reference_length))]
+}
+
+/// the length of the second copy, from either doubled-reference coordinates or circular ones (where the
+/// copy ends before it starts when it wraps the origin)
+fn second_copy_length(alignment: &Alignment, reference_length: usize) -> usize {
+    if alignment.end_y >= alignment.start_y { alignment.end_y - alignment.start_y } else { alignment.end_y + reference_length - alignment.start_y }
+}
+
+pub fn rotate_reference(reference: &[char],offset: usize) -> Result<Vec<char>, DupScoopError> {
+    if offset > reference.len() {
+        return Err(DupScoopError::AlignmentFailed(format!("unable to rotate the reference by {} bases as this is longer than its length {}", offset, reference.len())));
+    }
+    let mut new_ref = reference.to_vec();
+    new_ref.rotate_right(offset);
+    Ok(new_ref)
+}
+
+fn aligned_distance(alignment: &Alignment) -> u32 {
+    let it = alignment.seq_one_aligned.iter().zip(alignment.seq_two_aligned.iter());
+    let mut differences = 0;
+    for (x, y) in it {
+        if x.to_uppercase().to_string() != y.to_uppercase().to_string() {
+            differences += 1
+        }
+    }
+    differences
+}
+
+/// the proportion of aligned columns that match
+pub fn alignment_identity(alignment: &Alignment) -> f64 {
+    let differences = aligned_distance(alignment);
+    1.0 - (differences as f64 / alignment.seq_one_aligned.len() as f64)
+}
+
+/// align the reference against its doubled copy once and return every non-overlapping duplicate that
+/// passes the length and identity thresholds, best first, along with its identity
+fn find_duplicate_regions(reference: &[char], scores: &Scores, settings: &Deduplicator) -> Vec<(Alignment, f64)> {
+    let reference_length = reference.len();
+    let overlapping = |one: &Alignment, two: &Alignment| {
+        duplicate_copies(one, reference_length).iter().any(|first| {
+            duplicate_copies(two, reference_length).iter().any(|second| circular_overlap(first.0, first.1, second.0, second.1, reference_length))
+        })
+    };
+    let reference_dup = double_reference(reference);
+    let fits_in_memory = |window: &MatrixWindow| settings.engine.matrix_bytes(scores, window.x_end - window.x_start, window.y_end - window.y_start) <= settings.max_memory;
+    // the suboptimal searches keep a visited-cell bitmap next to the matrices, and as many candidate cells as
+    // the rest of the budget holds; they only run if that's a reasonable number
+    let whole_bytes = settings.engine.matrix_bytes(scores, reference_length, 2 * reference_length) + smith_waterman_no_diag::suboptimal_bytes(reference_length, 2 * reference_length, 0);
+    let circular_bytes = circular::circular_matrix_bytes(reference_length);
+    let candidate_budget = |bytes: usize| if bytes <= settings.max_memory { (settings.max_memory - bytes) / CANDIDATE_BYTES } else { 0 };
+    let circular_fits = || {
+        let fits = candidate_budget(circular_bytes) >= MIN_CANDIDATES;
+        if !fits {
+            eprintln!("Warning: the circular aligner doesn't fit in the memory budget, aligning against the doubled sequence instead");
+        }
+        fits
+    };
+    let seed_chains: Option<Vec<SeedChain>> = settings.prefilter.map(|kmer_size| {
+        let chains = seed_chain::find_seed_chains(reference, kmer_size, max(settings.diag_dist, 0) as usize, SEED_MAX_GAP, settings.min_length as usize / 2);
+        eprintln!("Found {} candidate seed chains", chains.len());
+        chains
+    });
+    let best_non_overlapping = |mut candidates: Vec<Alignment>| {
+        candidates.sort_by(|one, two| two.score.partial_cmp(&one.score).unwrap_or(Ordering::Equal));
+        let mut kept: Vec<Alignment> = Vec::new();
+        for candidate in candidates {
+            if !kept.iter().any(|existing| overlapping(existing, &candidate)) {
+                kept.push(candidate);
+            }
+        }
+        kept
+    };
+
+    let alignments = match seed_chains {
+        Some(ref chains) if chains.iter().all(|chain| fits_in_memory(&chain.window(reference_length, SEED_FLANK))) => {
+            best_non_overlapping(chains.iter()
+                .map(|chain| settings.engine.align_window(reference, &reference_dup, scores, settings.diag_dist, &chain.window(reference_length, SEED_FLANK)))
+                .collect())
+        }
+        // a chain too long for its window to fit can still be aligned along its diagonals
+        Some(ref chains) if settings.engine == AlignmentEngine::Linear &&
+            chains.iter().all(|chain| linear_space::banded_matrix_bytes(reference_length, &[chain.band(SEED_FLANK)]) <= settings.max_memory) => {
+            best_non_overlapping(chains.iter()
+                .map(|chain| smith_waterman_no_diag::smith_waterman_no_diag_banded(reference, &reference_dup, scores, settings.diag_dist, &[chain.band(SEED_FLANK)]))
+                .collect())
+        }
+        // the rest of the pipeline works in doubled-reference coordinates, so wrapped copies are unwrapped
+        _ if settings.circular && settings.engine == AlignmentEngine::Linear && circular_fits() => {
+            let min_score = minimum_duplicate_score(scores, settings);
+            circular::smith_waterman_circular_suboptimal(reference, scores, settings.diag_dist, min_score, candidate_budget(circular_bytes), overlapping).into_iter()
+                .map(|mut alignment| {
+                    alignment.end_y = alignment.start_y + second_copy_length(&alignment, reference_length);
+                    alignment
+                })
+                .collect()
+        }
+        // engines without a suboptimal mode only report their single best hit per pass
+        _ if !settings.engine.has_suboptimal() && fits_in_memory(&MatrixWindow::whole(reference, &reference_dup)) => {
+            vec![settings.engine.align_window(reference, &reference_dup, scores, settings.diag_dist, &MatrixWindow::whole(reference, &reference_dup))]
+        }
+        _ if candidate_budget(whole_bytes) >= MIN_CANDIDATES => {
+            let min_score = minimum_duplicate_score(scores, settings);
+            let max_candidates = candidate_budget(whole_bytes);
+            match IntegerScores::from_scores(scores) {
+                Some(integer_scores) => striped::smith_waterman_no_diag_integer_suboptimal(reference, &reference_dup, &integer_scores, settings.diag_dist, min_score, max_candidates, overlapping),
+                None => smith_waterman_no_diag::smith_waterman_no_diag_suboptimal(reference, &reference_dup, scores, settings.diag_dist, min_score, max_candidates, overlapping),
+            }
+        }
+        // too big for the full matrix, so we only get the single best hit per pass
+        _ => {
+            if settings.engine != AlignmentEngine::Linear {
+                eprintln!("Warning: the {:?} aligner doesn't fit in the memory budget, falling back to linear gaps", settings.engine);
+            }
+            vec![linear_space::smith_waterman_no_diag_linear(reference, &reference_dup, scores, settings.diag_dist)]
+        }
+    };
+
+    alignments.into_iter().filter_map(|alignment| {
+        let min_size = min(alignment.end_x - alignment.start_x, alignment.end_y - alignment.start_y);
+        let matching_prop = alignment_identity(&alignment);
+        if min_size > settings.min_length as usize && settings.min_identity < matching_prop {
+            Some((alignment, matching_prop))
+        } else {
+            None
+        }
+    }).collect()
+}
+
+/// the best local alignment, using the full matrix when it fits in the memory budget and the linear-space
+/// engine when it doesn't
+fn best_local_alignment(seq1: &[char], seq2: &[char], scores: &Scores, diag_dist: i32, settings: &Deduplicator) -> Alignment {
+    let max_memory = settings.max_memory;
+    if settings.engine.matrix_bytes(scores, seq1.len(), seq2.len()) <= max_memory {
+        settings.engine.align_window(seq1, seq2, scores, diag_dist, &MatrixWindow::whole(seq1, seq2))
+    } else if linear_space::full_matrix_bytes(seq1.len(), seq2.len()) <= max_memory {
+        smith_waterman_no_diag::smith_waterman_no_diag(seq1, seq2, scores, diag_dist)
+    } else {
+        linear_space::smith_waterman_no_diag_linear(seq1, seq2, scores, diag_dist)
+    }
+}
+
+/// the lowest score an alignment can have and still pass the thresholds: min_length columns at exactly the
+/// minimum identity, with every other column as costly as a column can be
+fn minimum_duplicate_score(scores: &Scores, settings: &Deduplicator) -> f64 {
+    // a substitution matrix can score some identical bases below match_score and some pairs below mismatch_score
+    let identical_column = ['A', 'C', 'G', 'T'].iter().map(|&base| Scores::scoring_function(base, base, scores)).fold(scores.match_score, f64::min);
+    let worst_substitution = scores.substitution_matrix.as_ref().map_or(scores.mismatch_score, |matrix| matrix.score_range().1.min(scores.mismatch_score));
+    let worst_column = worst_substitution.min(scores.gap_ext);
+    let score = settings.min_length as f64 * (settings.min_identity * identical_column + (1.0 - settings.min_identity) * worst_column);
+    score.max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use score_matrix::PairedScores;
+    use test_support::{random_bytes, test_scores};
+
+    fn plasmid_with_repeat() -> Vec<u8> {
+        let repeat = random_bytes(120, 3);
+        let mut plasmid = random_bytes(200, 5);
+        plasmid.extend_from_slice(&repeat);
+        plasmid.extend(random_bytes(150, 7));
+        plasmid.extend_from_slice(&repeat);
+        plasmid.extend(random_bytes(100, 9));
+        plasmid
+    }
+
+    fn deduplicator() -> Deduplicator {
+        let scores = test_scores();
+        Deduplicator::new(scores, 100, 0.9, 10)
+    }
+
+    #[test]
+    fn test_detect_leaves_sequence() {
+        let plasmid = plasmid_with_repeat();
+        let duplications = deduplicator().detect(&plasmid).unwrap();
+        assert_eq!(duplications, vec![Duplication {
+            first_start: 200,
+            first_end: 320,
+            second_start: 470,
+            second_end: 590,
+            score: 120.0,
+            identity: 1.0,
+            inverted: false,
+        }]);
+    }
+
+    #[test]
+    fn test_report_wraps_a_copy_across_the_origin() {
+        let mut plasmid = plasmid_with_repeat();
+        plasmid.rotate_left(520);
+        let result = deduplicator().detect_record("contig", &plasmid).unwrap();
+        let duplication = &result.duplications[0];
+        assert_eq!((duplication.first_start, duplication.first_end, duplication.second_start, duplication.second_end), (370, 490, 640, 70));
+
+        let event = result.events.iter().find(|event| event.event == EventKind::Duplication).unwrap();
+        assert_eq!((event.start_x, event.end_x, event.start_y, event.end_y), (Some(370), Some(490), Some(640), Some(70)));
+    }
+
+    #[test]
+    fn test_deduplicate_removes_one_copy() {
+        let plasmid = plasmid_with_repeat();
+        let result = deduplicator().deduplicate(&plasmid.to_ascii_lowercase()).unwrap();
+        assert_eq!(result.sequence.len(), plasmid.len() - 120);
+        assert_eq!(result.duplications.len(), 1);
+        assert_eq!(result.liftover.to_output(470), None);
+        assert_eq!(result.events.last().unwrap().event, EventKind::Summary);
+    }
+
+    #[test]
+    fn test_liftover_counts_from_the_raw_record() {
+        // Windows line endings every 60 bases, as a FASTA record comes in before normalize_sequence
+        let mut raw = Vec::new();
+        for line in plasmid_with_repeat().chunks(60) {
+            raw.extend(line.iter().map(|&base| base as char));
+            raw.extend(&['\r', '\n']);
+        }
+        let result = deduplicate_reference("contig", &raw, &deduplicator(), false).unwrap();
+        for (position, &base) in result.sequence.iter().enumerate() {
+            assert_eq!(raw[result.liftover.to_input(position).unwrap()], base as char);
+        }
+        // cleaned position 470 is the first base of the second copy, two characters further on per line
+        assert_eq!(result.liftover.to_output(470 + 2 * 7), None);
+        assert_eq!(result.liftover.to_output(60), None);
+        assert!(result.liftover.to_output(62).is_some());
+    }
+
+    #[test]
+    fn test_report_replays_several_removals_in_one_pass() {
+        let (first, second) = (random_bytes(120, 3), random_bytes(140, 13));
+        let mut plasmid = random_bytes(200, 5);
+        for (repeat, seed) in [(&first, 7), (&second, 9), (&first, 11), (&second, 15)].iter() {
+            plasmid.extend_from_slice(repeat);
+            plasmid.extend(random_bytes(150, *seed));
+        }
+        let result = deduplicator().deduplicate(&plasmid).unwrap();
+        let removals: Vec<&ReportEvent> = result.events.iter().filter(|event| event.event == EventKind::Duplication).collect();
+        assert_eq!(removals.len(), 2);
+        assert_eq!(removals[0].rotation, removals[1].rotation);
+
+        // cutting out each row's interval in turn, from the sequence its length_before describes, gives the output
+        let mut replayed: Vec<char> = plasmid.iter().map(|&base| base as char).collect();
+        replayed = rotate_reference(&replayed, removals[0].rotation.unwrap()).unwrap();
+        for removal in &removals {
+            assert_eq!(replayed.len(), removal.length_before);
+            let removed: Vec<u8> = replayed.drain(removal.removed_start.unwrap()..removal.removed_end.unwrap()).map(|base| base as u8).collect();
+            // a base next to a repeat can pair up by chance and go with it
+            assert!(removed.windows(first.len()).any(|window| window == &first[..]) || removed.windows(second.len()).any(|window| window == &second[..]));
+            assert_eq!(replayed.len(), removal.length_after);
+        }
+        assert_eq!(replayed.iter().map(|&base| base as u8).collect::<Vec<u8>>(), result.sequence);
+    }
+
+    #[test]
+    fn test_circular_matches_doubled() {
+        let mut circular = deduplicator();
+        circular.circular = true;
+        let plasmid = plasmid_with_repeat();
+        assert_eq!(circular.detect(&plasmid).unwrap(), deduplicator().detect(&plasmid).unwrap());
+
+        // rotate so the second copy runs across the origin
+        let mut rotated = plasmid.clone();
+        rotated.rotate_left(530);
+        let duplications = circular.detect(&rotated).unwrap();
+        assert_eq!(duplications, deduplicator().detect(&rotated).unwrap());
+        assert_eq!((duplications[0].second_start, duplications[0].second_end), (630, 60));
+    }
+
+    #[test]
+    fn test_trim_overlap_before_deduplicating() {
+        // the assembler ran 150 bases around the circle past the end of the plasmid
+        let plasmid = plasmid_with_repeat();
+        let mut contig = plasmid.clone();
+        contig.extend_from_slice(&plasmid[..150]);
+        let mut trimming = deduplicator();
+        trimming.trim_overlap = true;
+
+        let result = trimming.deduplicate(&contig).unwrap();
+        assert_eq!(result.end_overlap.as_ref().map(|overlap| overlap.suffix_start), Some(plasmid.len()));
+        assert_eq!(result.sequence.len(), plasmid.len() - 120);
+        assert_eq!(result.events[0].event, EventKind::EndOverlap);
+        assert_eq!(result.events[0].removed_start, Some(plasmid.len()));
+        assert_eq!(result.liftover.to_output(plasmid.len()), None);
+
+        let detected = trimming.detect_record("contig", &contig).unwrap();
+        assert_eq!(detected.sequence, contig);
+        assert_eq!(detected.events[0].removed_start, None);
+    }
+
+    #[test]
+    fn test_soft_masked_bases_keep_their_case() {
+        // lowercase the first 50 bases and wrap the sequence in CRLF lines
+        let mut plasmid = plasmid_with_repeat();
+        plasmid[..50].make_ascii_lowercase();
+        let mut input = Vec::new();
+        for line in plasmid.chunks(60) {
+            input.extend_from_slice(line);
+            input.extend_from_slice(b"\r\n");
+        }
+
+        let mut soft_masking = deduplicator();
+        soft_masking.soft_mask = true;
+        let result = soft_masking.deduplicate_record("contig", &input).unwrap();
+        assert_eq!(result.sequence.len(), plasmid.len() - 120);
+        assert_eq!(result.sequence.iter().filter(|base| base.is_ascii_lowercase()).count(), 50);
+        assert_eq!(result.events[0].event, EventKind::Normalized);
+        assert_eq!(result.events[0].detail.as_deref(), Some("dropped 24 whitespace characters; aligned 50 lowercase bases as uppercase"));
+        assert_eq!((result.events[0].length_before, result.events[0].length_after), (input.len(), plasmid.len()));
+
+        let result = deduplicator().deduplicate_record("contig", &input).unwrap();
+        assert!(result.sequence.iter().all(|base| base.is_ascii_uppercase()));
+    }
+
+    #[test]
+    fn test_empty_sequence_is_an_error() {
+        match deduplicator().deduplicate(b"") {
+            Err(DupScoopError::EmptySequence(_)) => {}
+            _ => panic!("expected an empty sequence error"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_bases_are_an_error() {
+        match deduplicator().deduplicate_record("contig_1", b"ACGT*ACG1XT") {
+            Err(DupScoopError::InvalidBases { record, positions }) => {
+                assert_eq!(record, "contig_1");
+                assert_eq!(positions, vec![(5, '*'), (9, 'X')]);
+            }
+            _ => panic!("expected an invalid bases error"),
+        }
+    }
+
+    #[test]
+    fn test_runs_of_n_are_not_duplications() {
+        // two scaffolding gaps of the same length look identical, but say nothing about the sequence
+        let mut plasmid = random_bytes(300, 5);
+        plasmid.extend(vec![b'N'; 150]);
+        plasmid.extend(random_bytes(200, 7));
+        plasmid.extend(vec![b'N'; 150]);
+        plasmid.extend(random_bytes(100, 9));
+
+        assert_eq!(deduplicator().detect(&plasmid).unwrap(), vec![]);
+        let iupac = deduplicator();
+        let iupac = Deduplicator { scores: iupac.scores.clone().with_substitution_matrix(PairedScores::default_scores(&1.0, &-3.0)), ..iupac };
+        assert_eq!(iupac.detect(&plasmid).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_rotating_past_the_end_is_an_error() {
+        let reference: Vec<char> = "ACGT".chars().collect();
+        assert_eq!(rotate_reference(&reference, 1).unwrap(), vec!['T', 'A', 'C', 'G']);
+        assert!(rotate_reference(&reference, 5).is_err());
+    }
+
+    fn reverse_complement(sequence: &[u8]) -> Vec<u8> {
+        ReferenceKmers::reverse_complement_sequence(&String::from_utf8_lossy(sequence)).into_bytes()
+    }
+
+    /// two unrelated repeats, each with a reverse-complemented copy further round the plasmid
+    fn plasmid_with_inverted_repeats() -> Vec<u8> {
+        let (first, second) = (random_bytes(120, 3), random_bytes(130, 13));
+        let mut plasmid = random_bytes(100, 5);
+        plasmid.extend_from_slice(&first);
+        plasmid.extend(random_bytes(150, 7));
+        plasmid.extend_from_slice(&second);
+        plasmid.extend(random_bytes(150, 9));
+        plasmid.extend(reverse_complement(&first));
+        plasmid.extend(random_bytes(150, 11));
+        plasmid.extend(reverse_complement(&second));
+        plasmid.extend(random_bytes(100, 15));
+        plasmid
+    }
+
+    #[test]
+    fn test_inverted_modes() {
+        let plasmid = plasmid_with_inverted_repeats();
+        let with_mode = |mode: InvertedMode| Deduplicator { inverted: mode, ..deduplicator() };
+        let inverted_events = |result: &DedupResult| result.events.iter().filter(|event| event.event == EventKind::InvertedRepeat).count();
+
+        let ignored = with_mode(InvertedMode::Ignore).deduplicate(&plasmid).unwrap();
+        assert_eq!((inverted_events(&ignored), ignored.sequence.len()), (0, plasmid.len()));
+
+        // reporting keeps looking after the first repeat, and leaves the sequence alone
+        let reported = with_mode(InvertedMode::Report).deduplicate(&plasmid).unwrap();
+        assert_eq!(inverted_events(&reported), 2);
+        assert_eq!(reported.sequence, plasmid);
+        let mut starts: Vec<(usize, usize)> = reported.duplications.iter().map(|duplication| {
+            let (first, second) = (duplication.first_start, duplication.second_start);
+            (first.min(second), first.max(second))
+        }).collect();
+        starts.sort();
+        // the first repeat picks up a base before it that happens to pair up
+        assert_eq!(starts, vec![(99, 650), (370, 920)]);
+
+        let collapsed = with_mode(InvertedMode::Collapse).deduplicate(&plasmid).unwrap();
+        assert_eq!(inverted_events(&collapsed), 2);
+        assert_eq!(collapsed.sequence.len(), plasmid.len() - 121 - 130);
+        assert_eq!(with_mode(InvertedMode::Collapse).detect(&plasmid).unwrap().len(), 2);
+    }
+}
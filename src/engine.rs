@@ -0,0 +1,88 @@
+use affine_gap;
+use linear_space;
+use needleman::{Alignment, Scores};
+use smith_waterman_no_diag;
+use smith_waterman_no_diag::MatrixWindow;
+use striped;
+use striped::IntegerScores;
+
+/// Which local aligner the duplicate search runs. Both exclude the same band around the self-alignment
+/// diagonal (see smith_waterman_no_diag::in_diagonal_band), so they can be swapped freely.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlignmentEngine {
+    /// every gap base costs gap_ext
+    Linear,
+    /// a gap costs gap_open once plus gap_ext per base
+    Affine,
+}
+
+impl AlignmentEngine {
+    pub fn from_name(name: &str) -> Option<AlignmentEngine> {
+        match name.to_lowercase().as_str() {
+            "linear" => Some(AlignmentEngine::Linear),
+            "affine" => Some(AlignmentEngine::Affine),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlignmentEngine::Linear => "linear",
+            AlignmentEngine::Affine => "affine",
+        }
+    }
+
+    /// the bytes a full-matrix alignment of a window this size needs with these scores
+    pub fn matrix_bytes(&self, scores: &Scores, seq1_length: usize, seq2_length: usize) -> usize {
+        match self {
+            AlignmentEngine::Linear if IntegerScores::from_scores(scores).is_some() => striped::integer_matrix_bytes(seq1_length, seq2_length),
+            AlignmentEngine::Linear => linear_space::full_matrix_bytes(seq1_length, seq2_length),
+            AlignmentEngine::Affine => affine_gap::affine_matrix_bytes(seq1_length, seq2_length),
+        }
+    }
+
+    /// the best local alignment inside a window of the full matrix
+    pub fn align_window(&self, seq1: &[char], seq2: &[char], scores: &Scores, min_diag_distance: i32, window: &MatrixWindow) -> Alignment {
+        match self {
+            // whole-number scores give the same alignment from the faster integer fill
+            AlignmentEngine::Linear => match IntegerScores::from_scores(scores) {
+                Some(integer_scores) => striped::smith_waterman_no_diag_integer(seq1, seq2, &integer_scores, min_diag_distance, window),
+                None => smith_waterman_no_diag::smith_waterman_no_diag_window(seq1, seq2, scores, min_diag_distance, window),
+            },
+            AlignmentEngine::Affine => affine_gap::affine_local(seq1, seq2, scores, min_diag_distance, window),
+        }
+    }
+
+    /// whether the engine can list several non-overlapping alignments from one matrix fill
+    pub fn has_suboptimal(&self) -> bool {
+        *self == AlignmentEngine::Linear
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::test_scores;
+
+    #[test]
+    fn test_engines_agree_on_ungapped_repeat() {
+        let scores = Scores { gap_ext: -2.0, ..test_scores() };
+        let reference: Vec<char> = "CCCCGATTACAGGTTAATTTTTTTTGATTACAGGTTAATCCCCC".chars().collect();
+        let mut doubled = reference.clone();
+        doubled.extend_from_slice(&reference);
+        let window = MatrixWindow::whole(&reference, &doubled);
+
+        let linear = AlignmentEngine::Linear.align_window(&reference, &doubled, &scores, 10, &window);
+        let affine = AlignmentEngine::Affine.align_window(&reference, &doubled, &scores, 10, &window);
+        assert_eq!(linear.score, 14.0);
+        assert_eq!(affine.score, linear.score);
+        assert_eq!((affine.start_x, affine.end_x, affine.start_y, affine.end_y), (linear.start_x, linear.end_x, linear.start_y, linear.end_y));
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(AlignmentEngine::from_name("Affine"), Some(AlignmentEngine::Affine));
+        assert_eq!(AlignmentEngine::from_name("linear"), Some(AlignmentEngine::Linear));
+        assert_eq!(AlignmentEngine::from_name("gotoh"), None);
+    }
+}
@@ -0,0 +1,119 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// the process exit status when duplications were found (or the command had nothing to find)
+pub const EXIT_SUCCESS: i32 = 0;
+/// the run worked, but no record had a duplication or inverted repeat
+pub const EXIT_NO_DUPLICATIONS: i32 = 1;
+/// bad command line values, configuration, or input files
+pub const EXIT_BAD_INPUT: i32 = 2;
+/// a record too long to align within the memory budget
+pub const EXIT_TOO_LONG: i32 = 3;
+/// reading or writing a file failed
+pub const EXIT_IO: i32 = 4;
+/// a bug: an alignment came back inconsistent, or something panicked
+pub const EXIT_INTERNAL: i32 = 5;
+/// some samples of a batch failed, but the others were processed
+pub const EXIT_SAMPLES_FAILED: i32 = 6;
+
+/// Everything that can go wrong while reading, deduplicating, or writing plasmids
+#[derive(Debug)]
+pub enum DupScoopError {
+    Io(io::Error),
+    /// an input file that isn't valid FASTA or GenBank, or has no records
+    Parse { path: String, message: String },
+    /// a record with no bases
+    EmptySequence(String),
+    /// characters in a record that aren't bases, with their 1-based positions
+    InvalidBases { record: String, positions: Vec<(usize, char)> },
+    /// even the linear-space aligner needs more memory than the budget allows
+    SequenceTooLong { record: String, required: usize, max_memory: usize },
+    /// a command line value we couldn't parse
+    InvalidArgument(String),
+    /// a substitution matrix file that isn't in the NCBI format
+    InvalidMatrix { name: String, message: String },
+    /// a run configuration file we couldn't read or that has a bad value
+    InvalidConfig { path: String, message: String },
+    /// an alignment result that doesn't fit the sequence it came from
+    AlignmentFailed(String),
+    /// some samples of a batch failed; the summary table says which and why
+    SamplesFailed { failed: usize, total: usize },
+}
+
+/// the most invalid positions listed in an error message
+const LISTED_POSITIONS: usize = 10;
+
+impl DupScoopError {
+    /// an error reading the input file at path, which is a parse error if the reader rejected the contents
+    pub fn reading(path: &str, error: io::Error) -> DupScoopError {
+        if error.kind() == io::ErrorKind::InvalidData {
+            DupScoopError::Parse { path: path.to_string(), message: error.to_string() }
+        } else {
+            DupScoopError::Io(error)
+        }
+    }
+
+    /// the process exit status for this class of error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DupScoopError::Io(_) => EXIT_IO,
+            DupScoopError::Parse { .. } | DupScoopError::EmptySequence(_) | DupScoopError::InvalidBases { .. } |
+            DupScoopError::InvalidArgument(_) | DupScoopError::InvalidMatrix { .. } | DupScoopError::InvalidConfig { .. } => EXIT_BAD_INPUT,
+            DupScoopError::SequenceTooLong { .. } => EXIT_TOO_LONG,
+            DupScoopError::AlignmentFailed(_) => EXIT_INTERNAL,
+            DupScoopError::SamplesFailed { .. } => EXIT_SAMPLES_FAILED,
+        }
+    }
+}
+
+impl fmt::Display for DupScoopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DupScoopError::Io(error) => write!(f, "{}", error),
+            DupScoopError::Parse { path, message } => write!(f, "couldn't read {}: {}", path, message),
+            DupScoopError::EmptySequence(record) => write!(f, "record {} has no sequence", record),
+            DupScoopError::InvalidBases { record, positions } => {
+                let listed: Vec<String> = positions.iter().take(LISTED_POSITIONS).map(|(position, character)| format!("{:?} at {}", character, position)).collect();
+                write!(f, "record {} has {} characters that aren't bases: {}{}", record, positions.len(), listed.join(", "),
+                       if positions.len() > LISTED_POSITIONS { ", ..." } else { "" })
+            }
+            DupScoopError::SequenceTooLong { record, required, max_memory } =>
+                write!(f, "record {} needs {} bytes to align, more than the {} byte memory budget", record, required, max_memory),
+            DupScoopError::InvalidArgument(message) => write!(f, "{}", message),
+            DupScoopError::InvalidMatrix { name, message } => write!(f, "substitution matrix {}: {}", name, message),
+            DupScoopError::InvalidConfig { path, message } => write!(f, "configuration {}: {}", path, message),
+            DupScoopError::AlignmentFailed(message) => write!(f, "alignment failed: {}", message),
+            DupScoopError::SamplesFailed { failed, total } => write!(f, "{} of {} samples failed", failed, total),
+        }
+    }
+}
+
+impl Error for DupScoopError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DupScoopError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DupScoopError {
+    fn from(error: io::Error) -> DupScoopError {
+        DupScoopError::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_bases_message() {
+        let error = DupScoopError::InvalidBases { record: "contig_1".to_string(), positions: (1..=12).map(|position| (position, '\r')).collect() };
+        let message = error.to_string();
+        assert!(message.starts_with("record contig_1 has 12 characters that aren't bases: '\\r' at 1, '\\r' at 2"));
+        assert!(message.ends_with("'\\r' at 10, ..."));
+        assert_eq!(error.exit_code(), EXIT_BAD_INPUT);
+    }
+}
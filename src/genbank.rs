@@ -0,0 +1,587 @@
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+
+use error::DupScoopError;
+use header::HeaderTags;
+use liftover::Liftover;
+use sequence_io;
+
+/// the keyword and indent that start a COMMENT line
+const COMMENT: &str = "COMMENT     ";
+/// the keyword of the per-base counts line, which has to be recomputed for the new sequence
+const BASE_COUNT: &str = "BASE COUNT";
+/// column where locations and qualifiers start in the FEATURES table
+const QUALIFIER_INDENT: usize = 21;
+
+/// A single GenBank record: the header lines are carried through untouched, while features and the
+/// sequence are parsed so they can be updated after deduplication
+pub struct GenBankRecord {
+    pub name: String,
+    pub locus_line: String,
+    pub header_lines: Vec<String>,
+    pub features: Vec<Feature>,
+    /// top-level keywords between the feature table and ORIGIN, such as BASE COUNT or CONTIG
+    pub trailer_lines: Vec<String>,
+    pub sequence: Vec<char>,
+}
+
+pub struct Feature {
+    pub key: String,
+    pub location: String,
+    /// qualifier lines with the leading indent removed, e.g. /label="ori"
+    pub qualifiers: Vec<String>,
+}
+
+/// A parsed feature location: a list of spans, optionally joined and/or on the complement strand
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub complement: bool,
+    pub operator: Option<String>,
+    pub spans: Vec<Span>,
+}
+
+/// One contiguous span of a location, in 0-based half-open coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub partial_start: bool,
+    pub partial_end: bool,
+}
+
+/// What happened to a feature when it was carried over to the deduplicated sequence
+#[derive(Debug, PartialEq)]
+pub enum LiftedFeature {
+    /// every base was kept, in order
+    Kept(Location),
+    /// some bases were removed or the feature now spans a cut point
+    Split(Location),
+    /// every base fell inside removed sequence
+    Dropped,
+    /// the location couldn't be parsed, so it was left as-is
+    Unsupported,
+}
+
+impl Feature {
+    /// a short description for reports, e.g. CDS 10..200 (bla)
+    pub fn describe(&self) -> String {
+        let name = self.qualifiers.iter()
+            .filter_map(|qualifier| {
+                ["/label=", "/gene=", "/product="].iter()
+                    .find(|prefix| qualifier.starts_with(*prefix))
+                    .map(|prefix| qualifier[prefix.len()..].trim_matches('"').to_string())
+            })
+            .next();
+        match name {
+            Some(name) => format!("{} {} ({})", self.key, self.location, name),
+            None => format!("{} {}", self.key, self.location),
+        }
+    }
+}
+
+impl Location {
+    /// parse a GenBank location string; remote references and between-base sites aren't supported
+    pub fn parse(location: &str) -> Option<Location> {
+        let location: String = location.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut parsed = Location { complement: false, operator: None, spans: Vec::new() };
+        let mut strands = Vec::new();
+        if !Location::parse_into(&location, false, &mut parsed, &mut strands) || parsed.spans.is_empty() {
+            return None;
+        }
+        // mixed-strand locations can't be described by a single complement flag
+        if strands.iter().any(|&strand| strand != strands[0]) {
+            return None;
+        }
+        parsed.complement = strands[0];
+        Some(parsed)
+    }
+
+    fn parse_into(location: &str, complement: bool, parsed: &mut Location, strands: &mut Vec<bool>) -> bool {
+        if let Some(inner) = strip_call(location, "complement") {
+            return Location::parse_into(inner, !complement, parsed, strands);
+        }
+        for operator in &["join", "order"] {
+            if let Some(inner) = strip_call(location, operator) {
+                parsed.operator = Some(operator.to_string());
+                let mut pieces: Vec<&str> = split_top_level(inner);
+                // join(complement(b),complement(a)) lists its pieces in reverse order on the forward strand
+                if !complement && pieces.iter().all(|piece| piece.starts_with("complement(")) {
+                    pieces.reverse();
+                }
+                return pieces.iter().all(|piece| Location::parse_into(piece, complement, parsed, strands));
+            }
+        }
+        match parse_span(location) {
+            Some(span) => {
+                parsed.spans.push(span);
+                strands.push(complement);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let spans: Vec<String> = self.spans.iter().map(|span| span.render()).collect();
+        let joined = if spans.len() > 1 {
+            format!("{}({})", self.operator.clone().unwrap_or_else(|| "join".to_string()), spans.join(","))
+        } else {
+            spans.join(",")
+        };
+        if self.complement {
+            format!("complement({})", joined)
+        } else {
+            joined
+        }
+    }
+
+    /// map every base of the location through the liftover, splitting it wherever consecutive bases
+    /// no longer sit next to each other in the output
+    pub fn lift(&self, liftover: &Liftover, output_length: usize) -> LiftedFeature {
+        let mut lifted_spans: Vec<Span> = Vec::new();
+        let mut lost_bases = false;
+        let mut cut = false;
+
+        for (span_index, span) in self.spans.iter().enumerate() {
+            let first_new_span = lifted_spans.len();
+            for input_position in span.start..span.end {
+                match liftover.to_output(input_position) {
+                    None => lost_bases = true,
+                    Some(output_position) => {
+                        let extends_last = lifted_spans.len() > first_new_span &&
+                            lifted_spans.last().is_some_and(|last| last.end == output_position);
+                        if extends_last {
+                            lifted_spans.last_mut().unwrap().end += 1;
+                        } else {
+                            if lifted_spans.len() > first_new_span {
+                                // continuing across the origin of the circular output isn't a cut
+                                let wraps = lifted_spans.last().unwrap().end == output_length && output_position == 0;
+                                cut |= !wraps;
+                            }
+                            lifted_spans.push(Span { start: output_position, end: output_position + 1, partial_start: false, partial_end: false });
+                        }
+                    }
+                }
+            }
+            if span_index == 0 && span.partial_start {
+                if let Some(first) = lifted_spans.first_mut() {
+                    first.partial_start = true;
+                }
+            }
+        }
+        if let (Some(last_span), Some(last)) = (self.spans.last(), lifted_spans.last_mut()) {
+            last.partial_end = last_span.partial_end;
+        }
+
+        if lifted_spans.is_empty() {
+            return LiftedFeature::Dropped;
+        }
+        let operator = if lifted_spans.len() > 1 { Some(self.operator.clone().unwrap_or_else(|| "join".to_string())) } else { None };
+        let location = Location { complement: self.complement, operator, spans: lifted_spans };
+        if lost_bases || cut {
+            LiftedFeature::Split(location)
+        } else {
+            LiftedFeature::Kept(location)
+        }
+    }
+}
+
+impl Span {
+    fn render(&self) -> String {
+        let start = format!("{}{}", if self.partial_start { "<" } else { "" }, self.start + 1);
+        if self.end == self.start + 1 && !self.partial_start && !self.partial_end {
+            start
+        } else {
+            format!("{}..{}{}", start, if self.partial_end { ">" } else { "" }, self.end)
+        }
+    }
+}
+
+fn strip_call<'a>(location: &'a str, name: &str) -> Option<&'a str> {
+    if location.starts_with(name) && location[name.len()..].starts_with('(') && location.ends_with(')') {
+        Some(&location[name.len() + 1..location.len() - 1])
+    } else {
+        None
+    }
+}
+
+fn split_top_level(location: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut depth = 0;
+    let mut piece_start = 0;
+    for (index, character) in location.char_indices() {
+        match character {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                pieces.push(&location[piece_start..index]);
+                piece_start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    pieces.push(&location[piece_start..]);
+    pieces
+}
+
+fn parse_span(span: &str) -> Option<Span> {
+    let (start, end) = match span.find("..") {
+        Some(split) => (&span[..split], &span[split + 2..]),
+        None => (span, span),
+    };
+    let partial_start = start.starts_with('<');
+    let partial_end = end.starts_with('>');
+    let start = start.trim_start_matches('<').parse::<usize>().ok()?;
+    let end = end.trim_start_matches('>').parse::<usize>().ok()?;
+    if start == 0 || end < start {
+        return None;
+    }
+    Some(Span { start: start - 1, end, partial_start, partial_end })
+}
+
+/// read every record from a GenBank file (or stdin, possibly compressed)
+pub fn read_genbank_records(genbank_file: &str) -> Result<Vec<GenBankRecord>, DupScoopError> {
+    read_genbank_input(sequence_io::open_input(genbank_file)?, genbank_file)
+}
+
+/// read every record from an opened GenBank input, failing if there aren't any
+pub fn read_genbank_input<R: Read>(reader: R, genbank_file: &str) -> Result<Vec<GenBankRecord>, DupScoopError> {
+    let records = read_genbank(reader).map_err(|error| DupScoopError::reading(genbank_file, error))?;
+    if records.is_empty() {
+        return Err(DupScoopError::Parse { path: genbank_file.to_string(), message: "there are no GenBank records in it".to_string() });
+    }
+    Ok(records)
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Header,
+    Features,
+    Trailer,
+    Origin,
+}
+
+/// parse GenBank records from any reader
+pub fn read_genbank<R: Read>(reader: R) -> Result<Vec<GenBankRecord>, io::Error> {
+    let mut records = Vec::new();
+    let mut current: Option<GenBankRecord> = None;
+    let mut section = Section::Header;
+    let mut in_qualifiers = false;
+
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let line = line.trim_end();
+        if line.starts_with("LOCUS") {
+            current = Some(GenBankRecord {
+                name: line.split_whitespace().nth(1).unwrap_or("").to_string(),
+                locus_line: line.to_string(),
+                header_lines: Vec::new(),
+                features: Vec::new(),
+                trailer_lines: Vec::new(),
+                sequence: Vec::new(),
+            });
+            section = Section::Header;
+            continue;
+        }
+        let record = match current.as_mut() {
+            Some(record) => record,
+            None => continue,
+        };
+        if line.starts_with("//") {
+            records.push(current.take().unwrap());
+            continue;
+        }
+        if line.starts_with("FEATURES") {
+            section = Section::Features;
+            continue;
+        }
+        if line.starts_with("ORIGIN") {
+            section = Section::Origin;
+            continue;
+        }
+        match section {
+            Section::Header => record.header_lines.push(line.to_string()),
+            Section::Trailer => record.trailer_lines.push(line.to_string()),
+            // only the position numbers and spacing go; everything else is left for normalize_sequence to check
+            Section::Origin => record.sequence.extend(line.chars().filter(|c| !c.is_whitespace() && !c.is_ascii_digit())),
+            Section::Features => {
+                if !line.starts_with(' ') {
+                    // another top-level keyword (e.g. CONTIG or BASE COUNT) ends the feature table
+                    record.trailer_lines.push(line.to_string());
+                    section = Section::Trailer;
+                } else if line.len() > 5 && !line[..QUALIFIER_INDENT.min(line.len())].trim().is_empty() {
+                    let mut fields = line.split_whitespace();
+                    record.features.push(Feature {
+                        key: fields.next().unwrap_or("").to_string(),
+                        location: fields.collect::<Vec<&str>>().join(""),
+                        qualifiers: Vec::new(),
+                    });
+                    in_qualifiers = false;
+                } else if let Some(feature) = record.features.last_mut() {
+                    let content = line.trim_start();
+                    if content.starts_with('/') {
+                        in_qualifiers = true;
+                        feature.qualifiers.push(content.to_string());
+                    } else if in_qualifiers {
+                        let qualifier = feature.qualifiers.last_mut().unwrap();
+                        qualifier.push('\n');
+                        qualifier.push_str(content);
+                    } else {
+                        feature.location.push_str(content);
+                    }
+                }
+            }
+        }
+    }
+    if let Some(record) = current {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// carry the features of a record over to its deduplicated sequence; features that now span a cut
+/// point are annotated with a note. Returns the new features along with the ones that were dropped
+/// and those that were split or couldn't be lifted.
+pub fn lift_features(features: &[Feature], liftover: &Liftover, output_length: usize) -> (Vec<Feature>, Vec<String>, Vec<String>) {
+    let mut lifted_features = Vec::new();
+    let mut dropped = Vec::new();
+    let mut flagged = Vec::new();
+    for feature in features {
+        let lifted = match Location::parse(&feature.location) {
+            Some(location) => location.lift(liftover, output_length),
+            None => LiftedFeature::Unsupported,
+        };
+        let (location, note) = match lifted {
+            LiftedFeature::Kept(location) => (location.render(), None),
+            LiftedFeature::Split(location) => (location.render(), Some("/note=\"dupscoop: feature spans a deduplication cut point\"")),
+            LiftedFeature::Unsupported => (feature.location.clone(), Some("/note=\"dupscoop: location could not be lifted over\"")),
+            LiftedFeature::Dropped => {
+                dropped.push(feature.describe());
+                continue;
+            }
+        };
+        let mut qualifiers = feature.qualifiers.clone();
+        if let Some(note) = note {
+            flagged.push(feature.describe());
+            qualifiers.push(note.to_string());
+        }
+        lifted_features.push(Feature { key: feature.key.clone(), location, qualifiers });
+    }
+    (lifted_features, dropped, flagged)
+}
+
+/// write a record in GenBank format, updating the sequence length on the LOCUS line and the BASE COUNT
+pub fn write_genbank_record<W: Write>(writer: &mut W, record: &GenBankRecord) -> Result<(), io::Error> {
+    writeln!(writer, "{}", update_locus_length(&record.locus_line, record.sequence.len()))?;
+    for line in &record.header_lines {
+        writeln!(writer, "{}", line)?;
+    }
+    writeln!(writer, "FEATURES             Location/Qualifiers")?;
+    let indent = " ".repeat(QUALIFIER_INDENT);
+    for feature in &record.features {
+        writeln!(writer, "     {:<16}{}", feature.key, wrap_location(&feature.location, &indent))?;
+        for qualifier in &feature.qualifiers {
+            for line in qualifier.lines() {
+                writeln!(writer, "{}{}", indent, line)?;
+            }
+        }
+    }
+    for line in &record.trailer_lines {
+        if line.starts_with(BASE_COUNT) {
+            writeln!(writer, "{}", base_count(&record.sequence))?;
+        } else {
+            writeln!(writer, "{}", line)?;
+        }
+    }
+    writeln!(writer, "ORIGIN")?;
+    for (line_index, line) in record.sequence.chunks(60).enumerate() {
+        let blocks: Vec<String> = line.chunks(10).map(|block| block.iter().collect::<String>().to_lowercase()).collect();
+        writeln!(writer, "{:>9} {}", line_index * 60 + 1, blocks.join(" "))?;
+    }
+    writeln!(writer, "//")
+}
+
+/// put a new record name in the LOCUS line in place of the old one, taking up the spaces after it so the
+/// length and later fields stay in their columns where possible
+pub fn rename_locus(locus_line: &str, old_name: &str, new_name: &str) -> String {
+    let name_start = match locus_line.strip_prefix("LOCUS").and_then(|rest| rest.find(old_name)) {
+        Some(offset) => "LOCUS".len() + offset,
+        None => return locus_line.to_string(),
+    };
+    let after_name = &locus_line[name_start + old_name.len()..];
+    let field_width = old_name.len() + after_name.len() - after_name.trim_start().len();
+    let padding = if after_name.trim_start().is_empty() { 0 } else { field_width.saturating_sub(new_name.len()).max(1) };
+    format!("{}{}{}{}", &locus_line[..name_start], new_name, " ".repeat(padding), after_name.trim_start())
+}
+
+/// Record the dedup tags in a COMMENT line at the end of the header, replacing the one an earlier run added
+pub fn annotate_header(header_lines: &[String], tags: &HeaderTags) -> Vec<String> {
+    let tag_comment = format!("{}dupscoop_", COMMENT);
+    let mut lines: Vec<String> = header_lines.iter().filter(|line| !line.starts_with(&tag_comment)).cloned().collect();
+    lines.push(format!("{}{}", COMMENT, tags.annotate(None)));
+    lines
+}
+
+/// a BASE COUNT line for the sequence, with any bases other than ACGT counted as others
+fn base_count(sequence: &[char]) -> String {
+    let count = |base: char| sequence.iter().filter(|character| character.eq_ignore_ascii_case(&base)).count();
+    let counts: Vec<usize> = "ACGT".chars().map(count).collect();
+    let mut line = format!("{}  {:>7} a {:>7} c {:>7} g {:>7} t", BASE_COUNT, counts[0], counts[1], counts[2], counts[3]);
+    let others = sequence.len() - counts.iter().sum::<usize>();
+    if others > 0 {
+        line.push_str(&format!(" {:>7} others", others));
+    }
+    line
+}
+
+fn update_locus_length(locus_line: &str, length: usize) -> String {
+    let tokens: Vec<&str> = locus_line.split_whitespace().collect();
+    match tokens.iter().position(|token| *token == "bp" || *token == "aa") {
+        Some(unit_index) if unit_index > 0 => {
+            let old_length = tokens[unit_index - 1];
+            let old_field = format!("{} {}", old_length, tokens[unit_index]);
+            let new_length = format!("{:>width$}", length, width = old_length.len());
+            locus_line.replacen(&old_field, &format!("{} {}", new_length, tokens[unit_index]), 1)
+        }
+        _ => locus_line.to_string(),
+    }
+}
+
+/// break long locations after commas so lines stay within the 79 column GenBank limit
+fn wrap_location(location: &str, indent: &str) -> String {
+    let width = 79 - QUALIFIER_INDENT;
+    let pieces: Vec<&str> = location.split(',').collect();
+    let mut lines = vec![String::new()];
+    for (index, piece) in pieces.iter().enumerate() {
+        let piece = if index + 1 < pieces.len() { format!("{},", piece) } else { piece.to_string() };
+        if !lines.last().unwrap().is_empty() && lines.last().unwrap().len() + piece.len() > width {
+            lines.push(String::new());
+        }
+        lines.last_mut().unwrap().push_str(&piece);
+    }
+    lines.join(&format!("\n{}", indent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "LOCUS       pTest                     40 bp    DNA     circular SYN 01-JAN-2020
+DEFINITION  a test plasmid.
+FEATURES             Location/Qualifiers
+     misc_feature    1..10
+                     /label=\"first\"
+     CDS             complement(21..30)
+                     /label=\"second\"
+     misc_feature    join(5..12,
+                     35..38)
+ORIGIN
+        1 aaaaaaaaaa cccccccccc gggggggggg tttttttttt
+//
+";
+
+    #[test]
+    fn test_parse_record() {
+        let records = read_genbank(EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.name, "pTest");
+        assert_eq!(record.header_lines, vec!["DEFINITION  a test plasmid."]);
+        assert_eq!(record.sequence.len(), 40);
+        assert_eq!(record.features.len(), 3);
+        assert_eq!(record.features[1].location, "complement(21..30)");
+        assert_eq!(record.features[1].qualifiers, vec!["/label=\"second\""]);
+        assert_eq!(record.features[2].location, "join(5..12,35..38)");
+    }
+
+    #[test]
+    fn test_parse_and_render_locations() {
+        for location in &["1..10", "5", "<1..>200", "complement(21..30)", "join(5..12,35..38)", "complement(join(5..12,35..38))", "order(1..2,5..6)"] {
+            assert_eq!(Location::parse(location).unwrap().render(), *location);
+        }
+        assert_eq!(Location::parse("join(complement(5..12),complement(1..3))").unwrap().render(), "complement(join(1..3,5..12))");
+        assert_eq!(Location::parse("J00194.1:100..202"), None);
+        assert_eq!(Location::parse("join(1..3,complement(5..12))"), None);
+    }
+
+    #[test]
+    fn test_lift_features() {
+        let records = read_genbank(EXAMPLE.as_bytes()).unwrap();
+        let mut liftover = Liftover::identity(40);
+        liftover.excise(8, 30);
+
+        let (features, dropped, flagged) = lift_features(&records[0].features, &liftover, 18);
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0].location, "1..8");
+        assert_eq!(features[1].location, "join(5..8,13..16)");
+        assert_eq!(dropped, vec!["CDS complement(21..30) (second)"]);
+        assert_eq!(flagged.len(), 2);
+        assert!(features[0].qualifiers.last().unwrap().contains("cut point"));
+    }
+
+    #[test]
+    fn test_lift_across_origin() {
+        let location = Location::parse("5..10").unwrap();
+        let mut liftover = Liftover::identity(20);
+        liftover.rotate(12);
+        // bases 5..10 (0-based 4..10) now sit at 16..20 and 0..2
+        let lifted = location.lift(&liftover, 20);
+        assert_eq!(lifted, LiftedFeature::Kept(Location {
+            complement: false,
+            operator: Some("join".to_string()),
+            spans: vec![Span { start: 16, end: 20, partial_start: false, partial_end: false },
+                        Span { start: 0, end: 2, partial_start: false, partial_end: false }],
+        }));
+    }
+
+    #[test]
+    fn test_write_record() {
+        let mut records = read_genbank(EXAMPLE.as_bytes()).unwrap();
+        records[0].sequence.truncate(25);
+        records[0].features.truncate(1);
+        let mut buffer = Vec::new();
+        write_genbank_record(&mut buffer, &records[0]).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        assert_eq!(output, "LOCUS       pTest                     25 bp    DNA     circular SYN 01-JAN-2020
+DEFINITION  a test plasmid.
+FEATURES             Location/Qualifiers
+     misc_feature    1..10
+                     /label=\"first\"
+ORIGIN
+        1 aaaaaaaaaa cccccccccc ggggg
+//
+");
+    }
+
+    #[test]
+    fn test_keywords_after_the_features() {
+        let example = EXAMPLE.replace("ORIGIN\n", "BASE COUNT       10 a      10 c      10 g      10 t\nCONTIG      join(U00096.3:1..40)\nORIGIN\n");
+        let mut records = read_genbank(example.as_bytes()).unwrap();
+        assert_eq!(records[0].header_lines, vec!["DEFINITION  a test plasmid."]);
+        assert_eq!(records[0].trailer_lines.len(), 2);
+        assert_eq!(records[0].features.len(), 3);
+
+        records[0].sequence.truncate(25);
+        records[0].features.truncate(1);
+        let mut buffer = Vec::new();
+        write_genbank_record(&mut buffer, &records[0]).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        assert!(output.contains("                     /label=\"first\"
+BASE COUNT       10 a      10 c       5 g       0 t
+CONTIG      join(U00096.3:1..40)
+ORIGIN
+"));
+    }
+
+    #[test]
+    fn test_rename_locus_and_tag_header() {
+        let locus = "LOCUS       p1                      4200 bp    DNA     circular SYN 01-JAN-2020";
+        assert_eq!(rename_locus(locus, "p1", "p1_dedup"), "LOCUS       p1_dedup                4200 bp    DNA     circular SYN 01-JAN-2020");
+        assert_eq!(rename_locus("LOCUS       p1", "p1", "p1_dedup"), "LOCUS       p1_dedup");
+
+        let tags = HeaderTags { original_length: 4200, final_length: 4000, iterations: 1, circularized: true };
+        let header = vec!["DEFINITION  test.".to_string(), "COMMENT     dupscoop_removed=5".to_string()];
+        assert_eq!(annotate_header(&header, &tags), vec!["DEFINITION  test.".to_string(),
+            "COMMENT     dupscoop_removed=200 dupscoop_iterations=1 dupscoop_orig_len=4200 circular=true".to_string()]);
+    }
+}
@@ -0,0 +1,123 @@
+use error::DupScoopError;
+use report::{EventKind, ReportEvent};
+
+/// the placeholders an output name template can use
+const PLACEHOLDERS: [&str; 5] = ["id", "index", "orig_len", "len", "removed"];
+
+/// What deduplication did to a record, written into its output header as key=value tags so the output
+/// can be traced back to its input
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderTags {
+    pub original_length: usize,
+    pub final_length: usize,
+    pub iterations: usize,
+    /// whether sequence was cut out, leaving a circular plasmid; otherwise the input's topology stands
+    pub circularized: bool,
+}
+
+impl HeaderTags {
+    /// the tags for a record, from the summary row that ends its report events
+    pub fn from_events(events: &[ReportEvent]) -> Option<HeaderTags> {
+        events.iter().rev().find(|event| event.event == EventKind::Summary).map(|summary| HeaderTags {
+            original_length: summary.length_before,
+            final_length: summary.length_after,
+            iterations: summary.iteration.unwrap_or(0),
+            circularized: summary.length_after < summary.length_before,
+        })
+    }
+
+    pub fn removed(&self) -> usize {
+        self.original_length - self.final_length
+    }
+
+    /// the key=value pairs, in the order they're written
+    pub fn pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = vec![("dupscoop_removed", self.removed().to_string()),
+                             ("dupscoop_iterations", self.iterations.to_string()),
+                             ("dupscoop_orig_len", self.original_length.to_string())];
+        // cutting out a duplication or a trimmed end overlap closes the circle; otherwise any circular tag
+        // the input had is left as it was
+        if self.circularized {
+            pairs.push(("circular", "true".to_string()));
+        }
+        pairs
+    }
+
+    /// Append the tags to a record's description, dropping any earlier values of the same tags (from a
+    /// previous run, or an assembler's circular=false) so each appears once.
+    pub fn annotate(&self, description: Option<&str>) -> String {
+        let pairs = self.pairs();
+        let mut words: Vec<String> = description.unwrap_or("").split_whitespace()
+            .filter(|word| !pairs.iter().any(|(key, _)| word.starts_with(&format!("{}=", key))))
+            .map(|word| word.to_string())
+            .collect();
+        words.extend(pairs.iter().map(|(key, value)| format!("{}={}", key, value)));
+        words.join(" ")
+    }
+}
+
+/// A pattern for naming output records, such as {id}_dedup. The placeholders are {id} (the input record
+/// name), {index} (its 1-based position in the file), {orig_len}, {len} and {removed}.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameTemplate {
+    template: String,
+}
+
+impl NameTemplate {
+    /// check the template only uses known placeholders and would give a usable record name
+    pub fn parse(template: &str) -> Result<NameTemplate, DupScoopError> {
+        let invalid = |message: String| DupScoopError::InvalidArgument(format!("output name template {}: {}", template, message));
+        if template.trim().is_empty() || template.chars().any(char::is_whitespace) {
+            return Err(invalid("record names can't be empty or contain whitespace".to_string()));
+        }
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            let close = rest[open..].find('}').ok_or_else(|| invalid("a { isn't closed".to_string()))? + open;
+            let placeholder = &rest[open + 1..close];
+            if !PLACEHOLDERS.contains(&placeholder) {
+                return Err(invalid(format!("unknown placeholder {{{}}} (expected one of {})", placeholder, PLACEHOLDERS.join(", "))));
+            }
+            rest = &rest[close + 1..];
+        }
+        Ok(NameTemplate { template: template.to_string() })
+    }
+
+    pub fn render(&self, record_id: &str, index: usize, tags: &HeaderTags) -> String {
+        self.template
+            .replace("{id}", record_id)
+            .replace("{index}", &index.to_string())
+            .replace("{orig_len}", &tags.original_length.to_string())
+            .replace("{len}", &tags.final_length.to_string())
+            .replace("{removed}", &tags.removed().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags() -> HeaderTags {
+        HeaderTags { original_length: 9876, final_length: 8642, iterations: 2, circularized: true }
+    }
+
+    #[test]
+    fn test_annotate_replaces_old_tags() {
+        assert_eq!(tags().annotate(None), "dupscoop_removed=1234 dupscoop_iterations=2 dupscoop_orig_len=9876 circular=true");
+        assert_eq!(tags().annotate(Some("len=9876 circular=false depth=1.2x dupscoop_removed=5")),
+                   "len=9876 depth=1.2x dupscoop_removed=1234 dupscoop_iterations=2 dupscoop_orig_len=9876 circular=true");
+
+        // nothing was cut out, so a linear contig stays linear
+        let untouched = HeaderTags { final_length: 9876, iterations: 0, circularized: false, ..tags() };
+        assert_eq!(untouched.annotate(Some("len=9876 circular=false")),
+                   "len=9876 circular=false dupscoop_removed=0 dupscoop_iterations=0 dupscoop_orig_len=9876");
+    }
+
+    #[test]
+    fn test_name_template() {
+        let template = NameTemplate::parse("{id}_{index}_dedup{removed}").unwrap();
+        assert_eq!(template.render("contig_1", 3, &tags()), "contig_1_3_dedup1234");
+        assert!(NameTemplate::parse("{id}_{sample}").is_err());
+        assert!(NameTemplate::parse("{id").is_err());
+        assert!(NameTemplate::parse("my plasmid").is_err());
+    }
+}
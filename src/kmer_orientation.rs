@@ -9,7 +9,7 @@ pub struct ReferenceKmers {
     kmer_size: usize,
 }
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum ReadOrientation {
     FWD,
@@ -38,7 +38,7 @@ impl ReferenceKmers {
     pub fn sequence_to_kmers(sequence: &str, kmer_size: &usize) -> HashSet<String> {
         let mut kmer_set = HashSet::new();
         for seq in sequence.chars().collect::<Vec<char>>().windows(*kmer_size) {
-            kmer_set.insert(seq.into_iter().collect());
+            kmer_set.insert(seq.iter().collect());
         }
         kmer_set
     }
@@ -65,7 +65,7 @@ impl ReferenceKmers {
         match ReferenceKmers::max_key_by_value(&counts).cloned() {
             Some(p) => {
                 let max_key_count = counts[&p];
-                let total: usize = counts.iter().map(|(_k, v)| v).sum();
+                let total: usize = counts.values().sum();
                 if max_key_count >= *min_count && (max_key_count as f32) / (total as f32) >= *min_ratio {
                     p
                 } else {
@@ -85,7 +85,7 @@ impl ReferenceKmers {
     {
         a_hash_map
             .iter()
-            .max_by(|a, b| a.1.cmp(&b.1))
+            .max_by(|a, b| a.1.cmp(b.1))
             .map(|(k, _v)| k)
     }
 }
@@ -105,14 +105,14 @@ mod tests {
 
     #[test]
     fn test_reverse_complement() {
-        assert_eq!(ReferenceKmers::reverse_complement_sequence(&"ACGGT"), "ACCGT");
-        assert_eq!(ReferenceKmers::reverse_complement_sequence(&"TTTTA"), "TAAAA");
-        assert_eq!(ReferenceKmers::reverse_complement_sequence(&"CCGAA"), "TTCGG");
+        assert_eq!(ReferenceKmers::reverse_complement_sequence("ACGGT"), "ACCGT");
+        assert_eq!(ReferenceKmers::reverse_complement_sequence("TTTTA"), "TAAAA");
+        assert_eq!(ReferenceKmers::reverse_complement_sequence("CCGAA"), "TTCGG");
     }
 
     #[test]
     fn test_sequence_to_kmers() {
-        let kmers = ReferenceKmers::sequence_to_kmers(&"ACGGT", &3);
+        let kmers = ReferenceKmers::sequence_to_kmers("ACGGT", &3);
         assert!(kmers.contains("ACG"));
         assert!(kmers.contains("CGG"));
         assert!(kmers.contains("GGT"));
@@ -120,28 +120,28 @@ mod tests {
 
     #[test]
     fn test_kmer_orientation_basic() {
-        let kmers = ReferenceKmers::generate_kmers(&"ACGGTAATTGGCC", &5);
+        let kmers = ReferenceKmers::generate_kmers("ACGGTAATTGGCC", &5);
         let orientation = kmers.vote_orientation("ACGGT", &0.5, &1);
         assert_eq!(orientation, ReadOrientation::FWD)
     }
 
     #[test]
     fn test_kmer_orientation_multi() {
-        let kmers = ReferenceKmers::generate_kmers(&"ACGGTCCGGTTTAATTAGAGATTTTT", &5);
+        let kmers = ReferenceKmers::generate_kmers("ACGGTCCGGTTTAATTAGAGATTTTT", &5);
         let orientation = kmers.vote_orientation("ACGGTCCGGTTTAATTAGAGATTTTT", &0.5, &20);
         assert_eq!(orientation, ReadOrientation::FWD)
     }
 
     #[test]
     fn test_kmer_orientation_below_threshold() {
-        let kmers = ReferenceKmers::generate_kmers(&"ACGGTCCGGTTTAATTAGAGATTTTT", &5);
+        let kmers = ReferenceKmers::generate_kmers("ACGGTCCGGTTTAATTAGAGATTTTT", &5);
         let orientation = kmers.vote_orientation("ACGGTCCGGTTTAATTAGAGATTTTT", &0.5, &23);
         assert_eq!(orientation, ReadOrientation::UNKNOWN)
     }
 
     #[test]
     fn test_kmer_orientation_full_reverse() {
-        let kmers = ReferenceKmers::generate_kmers(&"GGCGTCTGCTTGGGTGTTTAACCTTTTTTTTTTAATGTACTTCGTTCAGTTACGTATTGCTGTAGCGACCTATGCTATCTGCGTGACTCCAAGATNNNYRNNNYRNNNYRNNNACTCCAAGATCTACACGACGCTCTTCCGATCTNNNNNNNNNNNNNNNNCAGACATGATAAGATACATTGATGAGTTTGGACAAACCACAACTAGAATGCAGTGAAAAAAATGCTTTATTTGTGAAATTTGTGATGCTATTGCTTTATTTGTAACCATTATAAGCTGCAATAAACAAGTTTAGTTAACGCTCACCTATTAGCGGCTAAGGCTTAAGTACAGTTGATCAGAGTCGCGTAGAGTACAGTGCAAGCCTAGGGTCAGTACCGGTGCTGAGTTCGTTGACGGCAACTTGGACGCCTAAATCCTGTATACTCGCTTACGGCGGTCCGAAAGTCGTAGTGGTCCCGATCGCGTGTCTTAAATTCTGCAAGTTGGGCTGTGGCGCTCGTAAATGAGTCTCCCGGTTATCGAGTGCGTCCAAATGATGAGTTACCAAGCGGATTTCGATGAAATCTCGTAACGGTTGGAGAGTACCGGCTAAACGTCTGGTAGTCCTTACGGTGATCTGCTAGGATCTGAGTCCGGATCAGAAGAACTCGTCAAGAAGGCGATAGAAGGCGATGCGCTGCGAATCGGGAGCGGCGATACCGTAAAGCACGAGGAAGCGGTCAGCCCATTCGCCGCCAAGCTCTTCAGCAATATCACGGGTAGCCAACGCTATGTCCTGATAGCGGTCCGCCACACCCAGCCGGCCACAGTCGATGAATCCAGAAAAGCGGCCATTTTCCACCATGATATTCGGCAAGCAGGCATCGCCATGGGTCACGACGAGATCCTCGCCGTCGGGCATGCGCGCCTTGAGCCTGGCGAACAGTTCGGCTGGCGCGAGCCCCTGATGCTCTTCGTCCAGATCATCCTGATCGACAAGACCGGCTTCCATCCGAGTACGTGCTCGCTCGATGCGATGTTTCGCTTGGTGGTCGAATGGGCAGGTAGCCGGATCAAGCGTATGCAGCCGCCGCATTGCATCAGCCATGATGGATACTTTCTCGGCAGGAGCAAGGTGAGATGACAGGAGATCCTGCCCCGGCACTTCGCCCAATAGCAGCCAGTCCCTTCCCGCTTCAGTGACAACGTCGAGCACAGCTGCGCAAGGAACGCCCGTCGTGGCCAGCCACGATAGCCGCGCTGCCTCGTCCTGCAGTTCATTCAGGGCACCGGACAGGTCGGTCTTGACAAAAAGAACCGGGCGCCCCTGCGCTGACAGCCGGAACACGGCGGCATCAGAGCAGCCGATTGTCTGTTGTGCCCAGTCATAGCCGAATAGCCTCTCCACCCAAGCGGCCGGAGAACCTGCGTGCAATCCATCTTGTTCAATCATCGGTCCAGGATTCTCTTCGACATCTCCGGCTTGTTTCAGCAGAGAGAAGTTTGTTGCCTTGTACAGCTCGTCCATGCCGCCGGTGGAGTGGCGGCCCTCGGCGCGTTCGTACTGTTCCACGATGGTGTAGTCCTCGTTGTGGGAGGTGATGTCCAACTTGATGTTGACGTTGTAGGCGCCGGGCAGCTGCACGGGCTTCTTGGCCTTGTAGGTGGTCTTGACCTCAGCGTCGTAGTGGCCGCCGTCCTTCAGCTTCAGCCTCTGCTTGATCTCGCCCTTCAGGGCGCCGTCCTCGGGGTACATCCGCTCGGAGGAGGCCTCCCAGCCCATGGTTTTCTTCTGCATTACGGGGCCGTCGGAGGGGAAGTTGGTGCCGCGCAGCTTCACCTTGTAGATGAACTCGCCGTCCTGCAGGGAGGAGTCCTGGGTCACGGTCACCACGCCGCCGTCCTCGAAGTTCATCACGCGCTCCCACTTGAAGCCCTCGGGGAAGGACAGCTTCAAGTAGTCGGGGATGTCGGCGGGGTGCTTCACGTAGGCCTTGGAGCCGTACATGAACTGAGGGGACAGGATGTCCCAGGCGAAGGGCAGGGGGCCACCCTTGGTCACCTTCAGCTTGGCGGTCTGGGTGCCCTCGTAGGGGCGGCCCTCGCCCTCGCCCTCGATCTCGAACTCGTGGCCGTTCACGGAGCCCTCCATGTGCACCTTGAAGCGCATGAACTCCTTGATGATGGCCATGTTATCCTCCGGACCACACTNNNYRNNNYRNNNYRNNNGGACCACACTCTAGAGCGTATGTTACCGAGCCTGAGCAATACGTAACTGAACGAAGTACATTAAAAAAAAAAGGTTAAACACCCAAGCAGACGCC",
+        let kmers = ReferenceKmers::generate_kmers("GGCGTCTGCTTGGGTGTTTAACCTTTTTTTTTTAATGTACTTCGTTCAGTTACGTATTGCTGTAGCGACCTATGCTATCTGCGTGACTCCAAGATNNNYRNNNYRNNNYRNNNACTCCAAGATCTACACGACGCTCTTCCGATCTNNNNNNNNNNNNNNNNCAGACATGATAAGATACATTGATGAGTTTGGACAAACCACAACTAGAATGCAGTGAAAAAAATGCTTTATTTGTGAAATTTGTGATGCTATTGCTTTATTTGTAACCATTATAAGCTGCAATAAACAAGTTTAGTTAACGCTCACCTATTAGCGGCTAAGGCTTAAGTACAGTTGATCAGAGTCGCGTAGAGTACAGTGCAAGCCTAGGGTCAGTACCGGTGCTGAGTTCGTTGACGGCAACTTGGACGCCTAAATCCTGTATACTCGCTTACGGCGGTCCGAAAGTCGTAGTGGTCCCGATCGCGTGTCTTAAATTCTGCAAGTTGGGCTGTGGCGCTCGTAAATGAGTCTCCCGGTTATCGAGTGCGTCCAAATGATGAGTTACCAAGCGGATTTCGATGAAATCTCGTAACGGTTGGAGAGTACCGGCTAAACGTCTGGTAGTCCTTACGGTGATCTGCTAGGATCTGAGTCCGGATCAGAAGAACTCGTCAAGAAGGCGATAGAAGGCGATGCGCTGCGAATCGGGAGCGGCGATACCGTAAAGCACGAGGAAGCGGTCAGCCCATTCGCCGCCAAGCTCTTCAGCAATATCACGGGTAGCCAACGCTATGTCCTGATAGCGGTCCGCCACACCCAGCCGGCCACAGTCGATGAATCCAGAAAAGCGGCCATTTTCCACCATGATATTCGGCAAGCAGGCATCGCCATGGGTCACGACGAGATCCTCGCCGTCGGGCATGCGCGCCTTGAGCCTGGCGAACAGTTCGGCTGGCGCGAGCCCCTGATGCTCTTCGTCCAGATCATCCTGATCGACAAGACCGGCTTCCATCCGAGTACGTGCTCGCTCGATGCGATGTTTCGCTTGGTGGTCGAATGGGCAGGTAGCCGGATCAAGCGTATGCAGCCGCCGCATTGCATCAGCCATGATGGATACTTTCTCGGCAGGAGCAAGGTGAGATGACAGGAGATCCTGCCCCGGCACTTCGCCCAATAGCAGCCAGTCCCTTCCCGCTTCAGTGACAACGTCGAGCACAGCTGCGCAAGGAACGCCCGTCGTGGCCAGCCACGATAGCCGCGCTGCCTCGTCCTGCAGTTCATTCAGGGCACCGGACAGGTCGGTCTTGACAAAAAGAACCGGGCGCCCCTGCGCTGACAGCCGGAACACGGCGGCATCAGAGCAGCCGATTGTCTGTTGTGCCCAGTCATAGCCGAATAGCCTCTCCACCCAAGCGGCCGGAGAACCTGCGTGCAATCCATCTTGTTCAATCATCGGTCCAGGATTCTCTTCGACATCTCCGGCTTGTTTCAGCAGAGAGAAGTTTGTTGCCTTGTACAGCTCGTCCATGCCGCCGGTGGAGTGGCGGCCCTCGGCGCGTTCGTACTGTTCCACGATGGTGTAGTCCTCGTTGTGGGAGGTGATGTCCAACTTGATGTTGACGTTGTAGGCGCCGGGCAGCTGCACGGGCTTCTTGGCCTTGTAGGTGGTCTTGACCTCAGCGTCGTAGTGGCCGCCGTCCTTCAGCTTCAGCCTCTGCTTGATCTCGCCCTTCAGGGCGCCGTCCTCGGGGTACATCCGCTCGGAGGAGGCCTCCCAGCCCATGGTTTTCTTCTGCATTACGGGGCCGTCGGAGGGGAAGTTGGTGCCGCGCAGCTTCACCTTGTAGATGAACTCGCCGTCCTGCAGGGAGGAGTCCTGGGTCACGGTCACCACGCCGCCGTCCTCGAAGTTCATCACGCGCTCCCACTTGAAGCCCTCGGGGAAGGACAGCTTCAAGTAGTCGGGGATGTCGGCGGGGTGCTTCACGTAGGCCTTGGAGCCGTACATGAACTGAGGGGACAGGATGTCCCAGGCGAAGGGCAGGGGGCCACCCTTGGTCACCTTCAGCTTGGCGGTCTGGGTGCCCTCGTAGGGGCGGCCCTCGCCCTCGCCCTCGATCTCGAACTCGTGGCCGTTCACGGAGCCCTCCATGTGCACCTTGAAGCGCATGAACTCCTTGATGATGGCCATGTTATCCTCCGGACCACACTNNNYRNNNYRNNNYRNNNGGACCACACTCTAGAGCGTATGTTACCGAGCCTGAGCAATACGTAACTGAACGAAGTACATTAAAAAAAAAAGGTTAAACACCCAAGCAGACGCC",
                                                    &25);
         let orientation = kmers.vote_orientation("GGCGTCTGCTTGGGTGTTTAACCTTTTTTTTTTAATGTACTTCGTTCAGTTACGTATTGCTCAGGCTCGGTAACATACGCTCTAGAGTGTGGTCCNNNRYNNNRYNNNRYNNNAGTGTGGTCCGGAGGATAACATGGCCATCATCAAGGAGTTCATGCGCTTCAAGGTGCACATGGAGGGCTCCGTGAACGGCCACGAGTTCGAGATCGAGGGCGAGGGCGAGGGCCGCCCCTACGAGGGCACCCAGACCGCCAAGCTGAAGGTGACCAAGGGTGGCCCCCTGCCCTTCGCCTGGGACATCCTGTCCCCTCAGTTCATGTACGGCTCCAAGGCCTACGTGAAGCACCCCGCCGACATCCCCGACTACTTGAAGCTGTCCTTCCCCGAGGGCTTCAAGTGGGAGCGCGTGATGAACTTCGAGGACGGCGGCGTGGTGACCGTGACCCAGGACTCCTCCCTGCAGGACGGCGAGTTCATCTACAAGGTGAAGCTGCGCGGCACCAACTTCCCCTCCGACGGCCCCGTAATGCAGAAGAAAACCATGGGCTGGGAGGCCTCCTCCGAGCGGATGTACCCCGAGGACGGCGCCCTGAAGGGCGAGATCAAGCAGAGGCTGAAGCTGAAGGACGGCGGCCACTACGACGCTGAGGTCAAGACCACCTACAAGGCCAAGAAGCCCGTGCAGCTGCCCGGCGCCTACAACGTCAACATCAAGTTGGACATCACCTCCCACAACGAGGACTACACCATCGTGGAACAGTACGAACGCGCCGAGGGCCGCCACTCCACCGGCGGCATGGACGAGCTGTACAAGGCAACAAACTTCTCTCTGCTGAAACAAGCCGGAGATGTCGAAGAGAATCCTGGACCGATGATTGAACAAGATGGATTGCACGCAGGTTCTCCGGCCGCTTGGGTGGAGAGGCTATTCGGCTATGACTGGGCACAACAGACAATCGGCTGCTCTGATGCCGCCGTGTTCCGGCTGTCAGCGCAGGGGCGCCCGGTTCTTTTTGTCAAGACCGACCTGTCCGGTGCCCTGAATGAACTGCAGGACGAGGCAGCGCGGCTATCGTGGCTGGCCACGACGGGCGTTCCTTGCGCAGCTGTGCTCGACGTTGTCACTGAAGCGGGAAGGGACTGGCTGCTATTGGGCGAAGTGCCGGGGCAGGATCTCCTGTCATCTCACCTTGCTCCTGCCGAGAAAGTATCCATCATGGCTGATGCAATGCGGCGGCTGCATACGCTTGATCCGGCTACCTGCCCATTCGACCACCAAGCGAAACATCGCATCGAGCGAGCACGTACTCGGATGGAAGCCGGTCTTGTCGATCAGGATGATCTGGACGAAGAGCATCAGGGGCTCGCGCCAGCCGAACTGTTCGCCAGGCTCAAGGCGCGCATGCCCGACGGCGAGGATCTCGTCGTGACCCATGGCGATGCCTGCTTGCCGAATATCATGGTGGAAAATGGCCGCTTTTCTGGATTCATCGACTGTGGCCGGCTGGGTGTGGCGGACCGCTATCAGGACATAGCGTTGGCTACCCGTGATATTGCTGAAGAGCTTGGCGGCGAATGGGCTGACCGCTTCCTCGTGCTTTACGGTATCGCCGCTCCCGATTCGCAGCGCATCGCCTTCTATCGCCTTCTTGACGAGTTCTTCTGATCCGGACTCAGATCCTAGCAGATCACCGTAAGGACTACCAGACGTTTAGCCGGTACTCTCCAACCGTTACGAGATTTCATCGAAATCCGCTTGGTAACTCATCATTTGGACGCACTCGATAACCGGGAGACTCATTTACGAGCGCCACAGCCCAACTTGCAGAATTTAAGACACGCGATCGGGACCACTACGACTTTCGGACCGCCGTAAGCGAGTATACAGGATTTAGGCGTCCAAGTTGCCGTCAACGAACTCAGCACCGGTACTGACCCTAGGCTTGCACTGTACTCTACGCGACTCTGATCAACTGTACTTAAGCCTTAGCCGCTAATAGGTGAGCGTTAACTAAACTTGTTTATTGCAGCTTATAATGGTTACAAATAAAGCAATAGCATCACAAATTTCACAAATAAAGCATTTTTTTCACTGCATTCTAGTTGTGGTTTGTCCAAACTCATCAATGTATCTTATCATGTCTGNNNNNNNNNNNNNNNNAGATCGGAAGAGCGTCGTGTAGATCTTGGAGTNNNRYNNNRYNNNRYNNNATCTTGGAGTCACGCAGATAGCATAGGTCGCTACAGCAATACGTAACTGAACGAAGTACATTAAAAAAAAAAGGTTAAACACCCAAGCAGACGCC",
                                                  &0.5, &23);
@@ -150,7 +150,7 @@ mod tests {
 
     #[test]
     fn test_kmer_orientation_full_forward() {
-        let kmers = ReferenceKmers::generate_kmers(&"GGCGTCTGCTTGGGTGTTTAACCTTTTTTTTTTAATGTACTTCGTTCAGTTACGTATTGCTGTAGCGACCTATGCTATCTGCGTGACTCCAAGATNNNYRNNNYRNNNYRNNNACTCCAAGATCTACACGACGCTCTTCCGATCTNNNNNNNNNNNNNNNNCAGACATGATAAGATACATTGATGAGTTTGGACAAACCACAACTAGAATGCAGTGAAAAAAATGCTTTATTTGTGAAATTTGTGATGCTATTGCTTTATTTGTAACCATTATAAGCTGCAATAAACAAGTTTAGTTAACGCTCACCTATTAGCGGCTAAGGCTTAAGTACAGTTGATCAGAGTCGCGTAGAGTACAGTGCAAGCCTAGGGTCAGTACCGGTGCTGAGTTCGTTGACGGCAACTTGGACGCCTAAATCCTGTATACTCGCTTACGGCGGTCCGAAAGTCGTAGTGGTCCCGATCGCGTGTCTTAAATTCTGCAAGTTGGGCTGTGGCGCTCGTAAATGAGTCTCCCGGTTATCGAGTGCGTCCAAATGATGAGTTACCAAGCGGATTTCGATGAAATCTCGTAACGGTTGGAGAGTACCGGCTAAACGTCTGGTAGTCCTTACGGTGATCTGCTAGGATCTGAGTCCGGATCAGAAGAACTCGTCAAGAAGGCGATAGAAGGCGATGCGCTGCGAATCGGGAGCGGCGATACCGTAAAGCACGAGGAAGCGGTCAGCCCATTCGCCGCCAAGCTCTTCAGCAATATCACGGGTAGCCAACGCTATGTCCTGATAGCGGTCCGCCACACCCAGCCGGCCACAGTCGATGAATCCAGAAAAGCGGCCATTTTCCACCATGATATTCGGCAAGCAGGCATCGCCATGGGTCACGACGAGATCCTCGCCGTCGGGCATGCGCGCCTTGAGCCTGGCGAACAGTTCGGCTGGCGCGAGCCCCTGATGCTCTTCGTCCAGATCATCCTGATCGACAAGACCGGCTTCCATCCGAGTACGTGCTCGCTCGATGCGATGTTTCGCTTGGTGGTCGAATGGGCAGGTAGCCGGATCAAGCGTATGCAGCCGCCGCATTGCATCAGCCATGATGGATACTTTCTCGGCAGGAGCAAGGTGAGATGACAGGAGATCCTGCCCCGGCACTTCGCCCAATAGCAGCCAGTCCCTTCCCGCTTCAGTGACAACGTCGAGCACAGCTGCGCAAGGAACGCCCGTCGTGGCCAGCCACGATAGCCGCGCTGCCTCGTCCTGCAGTTCATTCAGGGCACCGGACAGGTCGGTCTTGACAAAAAGAACCGGGCGCCCCTGCGCTGACAGCCGGAACACGGCGGCATCAGAGCAGCCGATTGTCTGTTGTGCCCAGTCATAGCCGAATAGCCTCTCCACCCAAGCGGCCGGAGAACCTGCGTGCAATCCATCTTGTTCAATCATCGGTCCAGGATTCTCTTCGACATCTCCGGCTTGTTTCAGCAGAGAGAAGTTTGTTGCCTTGTACAGCTCGTCCATGCCGCCGGTGGAGTGGCGGCCCTCGGCGCGTTCGTACTGTTCCACGATGGTGTAGTCCTCGTTGTGGGAGGTGATGTCCAACTTGATGTTGACGTTGTAGGCGCCGGGCAGCTGCACGGGCTTCTTGGCCTTGTAGGTGGTCTTGACCTCAGCGTCGTAGTGGCCGCCGTCCTTCAGCTTCAGCCTCTGCTTGATCTCGCCCTTCAGGGCGCCGTCCTCGGGGTACATCCGCTCGGAGGAGGCCTCCCAGCCCATGGTTTTCTTCTGCATTACGGGGCCGTCGGAGGGGAAGTTGGTGCCGCGCAGCTTCACCTTGTAGATGAACTCGCCGTCCTGCAGGGAGGAGTCCTGGGTCACGGTCACCACGCCGCCGTCCTCGAAGTTCATCACGCGCTCCCACTTGAAGCCCTCGGGGAAGGACAGCTTCAAGTAGTCGGGGATGTCGGCGGGGTGCTTCACGTAGGCCTTGGAGCCGTACATGAACTGAGGGGACAGGATGTCCCAGGCGAAGGGCAGGGGGCCACCCTTGGTCACCTTCAGCTTGGCGGTCTGGGTGCCCTCGTAGGGGCGGCCCTCGCCCTCGCCCTCGATCTCGAACTCGTGGCCGTTCACGGAGCCCTCCATGTGCACCTTGAAGCGCATGAACTCCTTGATGATGGCCATGTTATCCTCCGGACCACACTNNNYRNNNYRNNNYRNNNGGACCACACTCTAGAGCGTATGTTACCGAGCCTGAGCAATACGTAACTGAACGAAGTACATTAAAAAAAAAAGGTTAAACACCCAAGCAGACGCC",
+        let kmers = ReferenceKmers::generate_kmers("GGCGTCTGCTTGGGTGTTTAACCTTTTTTTTTTAATGTACTTCGTTCAGTTACGTATTGCTGTAGCGACCTATGCTATCTGCGTGACTCCAAGATNNNYRNNNYRNNNYRNNNACTCCAAGATCTACACGACGCTCTTCCGATCTNNNNNNNNNNNNNNNNCAGACATGATAAGATACATTGATGAGTTTGGACAAACCACAACTAGAATGCAGTGAAAAAAATGCTTTATTTGTGAAATTTGTGATGCTATTGCTTTATTTGTAACCATTATAAGCTGCAATAAACAAGTTTAGTTAACGCTCACCTATTAGCGGCTAAGGCTTAAGTACAGTTGATCAGAGTCGCGTAGAGTACAGTGCAAGCCTAGGGTCAGTACCGGTGCTGAGTTCGTTGACGGCAACTTGGACGCCTAAATCCTGTATACTCGCTTACGGCGGTCCGAAAGTCGTAGTGGTCCCGATCGCGTGTCTTAAATTCTGCAAGTTGGGCTGTGGCGCTCGTAAATGAGTCTCCCGGTTATCGAGTGCGTCCAAATGATGAGTTACCAAGCGGATTTCGATGAAATCTCGTAACGGTTGGAGAGTACCGGCTAAACGTCTGGTAGTCCTTACGGTGATCTGCTAGGATCTGAGTCCGGATCAGAAGAACTCGTCAAGAAGGCGATAGAAGGCGATGCGCTGCGAATCGGGAGCGGCGATACCGTAAAGCACGAGGAAGCGGTCAGCCCATTCGCCGCCAAGCTCTTCAGCAATATCACGGGTAGCCAACGCTATGTCCTGATAGCGGTCCGCCACACCCAGCCGGCCACAGTCGATGAATCCAGAAAAGCGGCCATTTTCCACCATGATATTCGGCAAGCAGGCATCGCCATGGGTCACGACGAGATCCTCGCCGTCGGGCATGCGCGCCTTGAGCCTGGCGAACAGTTCGGCTGGCGCGAGCCCCTGATGCTCTTCGTCCAGATCATCCTGATCGACAAGACCGGCTTCCATCCGAGTACGTGCTCGCTCGATGCGATGTTTCGCTTGGTGGTCGAATGGGCAGGTAGCCGGATCAAGCGTATGCAGCCGCCGCATTGCATCAGCCATGATGGATACTTTCTCGGCAGGAGCAAGGTGAGATGACAGGAGATCCTGCCCCGGCACTTCGCCCAATAGCAGCCAGTCCCTTCCCGCTTCAGTGACAACGTCGAGCACAGCTGCGCAAGGAACGCCCGTCGTGGCCAGCCACGATAGCCGCGCTGCCTCGTCCTGCAGTTCATTCAGGGCACCGGACAGGTCGGTCTTGACAAAAAGAACCGGGCGCCCCTGCGCTGACAGCCGGAACACGGCGGCATCAGAGCAGCCGATTGTCTGTTGTGCCCAGTCATAGCCGAATAGCCTCTCCACCCAAGCGGCCGGAGAACCTGCGTGCAATCCATCTTGTTCAATCATCGGTCCAGGATTCTCTTCGACATCTCCGGCTTGTTTCAGCAGAGAGAAGTTTGTTGCCTTGTACAGCTCGTCCATGCCGCCGGTGGAGTGGCGGCCCTCGGCGCGTTCGTACTGTTCCACGATGGTGTAGTCCTCGTTGTGGGAGGTGATGTCCAACTTGATGTTGACGTTGTAGGCGCCGGGCAGCTGCACGGGCTTCTTGGCCTTGTAGGTGGTCTTGACCTCAGCGTCGTAGTGGCCGCCGTCCTTCAGCTTCAGCCTCTGCTTGATCTCGCCCTTCAGGGCGCCGTCCTCGGGGTACATCCGCTCGGAGGAGGCCTCCCAGCCCATGGTTTTCTTCTGCATTACGGGGCCGTCGGAGGGGAAGTTGGTGCCGCGCAGCTTCACCTTGTAGATGAACTCGCCGTCCTGCAGGGAGGAGTCCTGGGTCACGGTCACCACGCCGCCGTCCTCGAAGTTCATCACGCGCTCCCACTTGAAGCCCTCGGGGAAGGACAGCTTCAAGTAGTCGGGGATGTCGGCGGGGTGCTTCACGTAGGCCTTGGAGCCGTACATGAACTGAGGGGACAGGATGTCCCAGGCGAAGGGCAGGGGGCCACCCTTGGTCACCTTCAGCTTGGCGGTCTGGGTGCCCTCGTAGGGGCGGCCCTCGCCCTCGCCCTCGATCTCGAACTCGTGGCCGTTCACGGAGCCCTCCATGTGCACCTTGAAGCGCATGAACTCCTTGATGATGGCCATGTTATCCTCCGGACCACACTNNNYRNNNYRNNNYRNNNGGACCACACTCTAGAGCGTATGTTACCGAGCCTGAGCAATACGTAACTGAACGAAGTACATTAAAAAAAAAAGGTTAAACACCCAAGCAGACGCC",
                                                    &25);
         let orientation = kmers.vote_orientation("GGCGTCTGCTTGGGTGTTTAACCTTTTTTTTTTAATGTACTTCGTTCAGTTACGTATTGCTGTAGCGACCTATGCTATCTGCGTGACTCCAAGATNNNYRNNNYRNNNYRNNNACTCCAAGATCTACACGACGCTCTTCCGATCTNNNNNNNNNNNNNNNNCAGACATGATAAGATACATTGATGAGTTTGGACAAACCACAACTAGAATGCAGTGAAAAAAATGCTTTATTTGTGAAATTTGTGATGCTATTGCTTTATTTGTAACCATTATAAGCTGCAATAAACAAGTTTAGTTAACGCTCACCTATTAGCGGCTAAGGCTTAAGTACAGTTGATCAGAGTCGCGTAGAGTACAGTGCAAGCCTAGGGTCAGTACCGGTGCTGAGTTCGTTGACGGCAACTTGGACGCCTAAATCCTGTATACTCGCTTACGGCGGTCCGAAAGTCGTAGTGGTCCCGATCGCGTGTCTTAAATTCTGCAAGTTGGGCTGTGGCGCTCGTAAATGAGTCTCCCGGTTATCGAGTGCGTCCAAATGATGAGTTACCAAGCGGATTTCGATGAAATCTCGTAACGGTTGGAGAGTACCGGCTAAACGTCTGGTAGTCCTTACGGTGATCTGCTAGGATCTGAGTCCGGATCAGAAGAACTCGTCAAGAAGGCGATAGAAGGCGATGCGCTGCGAATCGGGAGCGGCGATACCGTAAAGCACGAGGAAGCGGTCAGCCCATTCGCCGCCAAGCTCTTCAGCAATATCACGGGTAGCCAACGCTATGTCCTGATAGCGGTCCGCCACACCCAGCCGGCCACAGTCGATGAATCCAGAAAAGCGGCCATTTTCCACCATGATATTCGGCAAGCAGGCATCGCCATGGGTCACGACGAGATCCTCGCCGTCGGGCATGCGCGCCTTGAGCCTGGCGAACAGTTCGGCTGGCGCGAGCCCCTGATGCTCTTCGTCCAGATCATCCTGATCGACAAGACCGGCTTCCATCCGAGTACGTGCTCGCTCGATGCGATGTTTCGCTTGGTGGTCGAATGGGCAGGTAGCCGGATCAAGCGTATGCAGCCGCCGCATTGCATCAGCCATGATGGATACTTTCTCGGCAGGAGCAAGGTGAGATGACAGGAGATCCTGCCCCGGCACTTCGCCCAATAGCAGCCAGTCCCTTCCCGCTTCAGTGACAACGTCGAGCACAGCTGCGCAAGGAACGCCCGTCGTGGCCAGCCACGATAGCCGCGCTGCCTCGTCCTGCAGTTCATTCAGGGCACCGGACAGGTCGGTCTTGACAAAAAGAACCGGGCGCCCCTGCGCTGACAGCCGGAACACGGCGGCATCAGAGCAGCCGATTGTCTGTTGTGCCCAGTCATAGCCGAATAGCCTCTCCACCCAAGCGGCCGGAGAACCTGCGTGCAATCCATCTTGTTCAATCATCGGTCCAGGATTCTCTTCGACATCTCCGGCTTGTTTCAGCAGAGAGAAGTTTGTTGCCTTGTACAGCTCGTCCATGCCGCCGGTGGAGTGGCGGCCCTCGGCGCGTTCGTACTGTTCCACGATGGTGTAGTCCTCGTTGTGGGAGGTGATGTCCAACTTGATGTTGACGTTGTAGGCGCCGGGCAGCTGCACGGGCTTCTTGGCCTTGTAGGTGGTCTTGACCTCAGCGTCGTAGTGGCCGCCGTCCTTCAGCTTCAGCCTCTGCTTGATCTCGCCCTTCAGGGCGCCGTCCTCGGGGTACATCCGCTCGGAGGAGGCCTCCCAGCCCATGGTTTTCTTCTGCATTACGGGGCCGTCGGAGGGGAAGTTGGTGCCGCGCAGCTTCACCTTGTAGATGAACTCGCCGTCCTGCAGGGAGGAGTCCTGGGTCACGGTCACCACGCCGCCGTCCTCGAAGTTCATCACGCGCTCCCACTTGAAGCCCTCGGGGAAGGACAGCTTCAAGTAGTCGGGGATGTCGGCGGGGTGCTTCACGTAGGCCTTGGAGCCGTACATGAACTGAGGGGACAGGATGTCCCAGGCGAAGGGCAGGGGGCCACCCTTGGTCACCTTCAGCTTGGCGGTCTGGGTGCCCTCGTAGGGGCGGCCCTCGCCCTCGCCCTCGATCTCGAACTCGTGGCCGTTCACGGAGCCCTCCATGTGCACCTTGAAGCGCATGAACTCCTTGATGATGGCCATGTTATCCTCCGGACCACACTNNNYRNNNYRNNNYRNNNGGACCACACTCTAGAGCGTATGTTACCGAGCCTGAGCAATACGTAACTGAACGAAGTACATTAAAAAAAAAAGGTTAAACACCCAAGCAGACGCC",
                                                  &0.5, &23);
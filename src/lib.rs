@@ -0,0 +1,50 @@
+extern crate bio;
+extern crate csv;
+extern crate flate2;
+extern crate matrix;
+extern crate string_builder;
+extern crate indicatif;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+
+pub mod affine_gap;
+pub mod batch;
+pub mod bgzf;
+pub mod circular;
+pub mod compare;
+pub mod config;
+mod convex;
+pub mod dedup;
+pub mod engine;
+pub mod error;
+pub mod genbank;
+pub mod header;
+pub mod kmer_orientation;
+pub mod linear_space;
+pub mod liftover;
+pub mod report;
+pub mod score_matrix;
+pub mod seed_chain;
+pub mod sequence_io;
+pub mod mymatrix;
+pub mod needleman;
+pub mod overlap;
+pub mod pipeline;
+
+pub mod smith_waterman_no_diag;
+pub mod striped;
+pub mod validate;
+
+/// fixtures shared by the unit tests
+#[cfg(test)]
+mod test_support;
+
+pub use dedup::{DedupResult, Deduplicator, Duplication, InvertedMode};
+pub use engine::AlignmentEngine;
+pub use error::DupScoopError;
+pub use needleman::{EndGaps, Scores};
+pub use overlap::EndOverlap;
+pub use validate::InvalidBaseMode;
@@ -0,0 +1,170 @@
+use std::fs::File;
+use std::io;
+
+use csv;
+
+/// Tracks where each base of a deduplicated sequence came from in the original input, by replaying
+/// the same rotations and deletions the dedup loop applies to the sequence itself
+pub struct Liftover {
+    /// for each output position, the input position it was copied from
+    origin: Vec<usize>,
+    /// for each input position, the output position it ended up at (if it survived)
+    destination: Vec<Option<usize>>,
+}
+
+/// A run of consecutive bases that were carried over unchanged, in 0-based half-open coordinates
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LiftoverBlock {
+    pub input_start: usize,
+    pub input_end: usize,
+    pub output_start: usize,
+    pub output_end: usize,
+}
+
+#[derive(Serialize)]
+struct LiftoverRow<'a> {
+    record: &'a str,
+    input_start: usize,
+    input_end: usize,
+    output_start: usize,
+    output_end: usize,
+}
+
+impl Liftover {
+    /// the mapping for a sequence of the given length that hasn't been changed yet
+    pub fn identity(length: usize) -> Liftover {
+        Liftover {
+            origin: (0..length).collect(),
+            destination: (0..length).map(Some).collect(),
+        }
+    }
+
+    /// the mapping for a sequence cleaned up from a longer input, given the input position of each base kept
+    pub fn from_input_positions(input_positions: Vec<usize>, input_length: usize) -> Liftover {
+        let mut liftover = Liftover { origin: input_positions, destination: vec![None; input_length] };
+        liftover.update_destination();
+        liftover
+    }
+
+    /// mirror a rotate_reference call with the same offset
+    pub fn rotate(&mut self, offset: usize) {
+        self.origin.rotate_right(offset);
+        self.update_destination();
+    }
+
+    /// mirror the removal of the output interval [start, end)
+    pub fn excise(&mut self, start: usize, end: usize) {
+        self.origin.drain(start..end);
+        self.update_destination();
+    }
+
+    /// map a position in the original input to the deduplicated output, if that base was kept
+    pub fn to_output(&self, input_position: usize) -> Option<usize> {
+        self.destination.get(input_position).cloned().unwrap_or(None)
+    }
+
+    /// map a position in the deduplicated output back to the original input
+    pub fn to_input(&self, output_position: usize) -> Option<usize> {
+        self.origin.get(output_position).cloned()
+    }
+
+    /// collapse the mapping into runs of consecutive bases, in output order
+    pub fn blocks(&self) -> Vec<LiftoverBlock> {
+        let mut blocks: Vec<LiftoverBlock> = Vec::new();
+        for (output_position, input_position) in self.origin.iter().enumerate() {
+            if let Some(last) = blocks.last_mut() {
+                if last.input_end == *input_position && last.output_end == output_position {
+                    last.input_end += 1;
+                    last.output_end += 1;
+                    continue;
+                }
+            }
+            blocks.push(LiftoverBlock {
+                input_start: *input_position,
+                input_end: input_position + 1,
+                output_start: output_position,
+                output_end: output_position + 1,
+            });
+        }
+        blocks
+    }
+
+    fn update_destination(&mut self) {
+        for position in self.destination.iter_mut() {
+            *position = None;
+        }
+        for (output_position, input_position) in self.origin.iter().enumerate() {
+            self.destination[*input_position] = Some(output_position);
+        }
+    }
+}
+
+/// write the liftover blocks for every record as a tab-separated interval map
+pub fn write_liftover(liftover_file: &str, liftovers: &[(String, Liftover)]) -> Result<(), io::Error> {
+    let mut tsv = csv::WriterBuilder::new().delimiter(b'\t').from_writer(File::create(liftover_file)?);
+    for (record, liftover) in liftovers {
+        for block in liftover.blocks() {
+            tsv.serialize(LiftoverRow {
+                record,
+                input_start: block.input_start,
+                input_end: block.input_end,
+                output_start: block.output_start,
+                output_end: block.output_end,
+            })?;
+        }
+    }
+    tsv.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity() {
+        let liftover = Liftover::identity(10);
+        assert_eq!(liftover.to_output(4), Some(4));
+        assert_eq!(liftover.to_input(9), Some(9));
+        assert_eq!(liftover.to_input(10), None);
+        assert_eq!(liftover.blocks(), vec![LiftoverBlock { input_start: 0, input_end: 10, output_start: 0, output_end: 10 }]);
+    }
+
+    #[test]
+    fn test_rotate_and_excise_follow_sequence() {
+        let mut sequence: Vec<char> = "ABCDEFGHIJ".chars().collect();
+        let mut liftover = Liftover::identity(sequence.len());
+
+        sequence.rotate_right(3);
+        liftover.rotate(3);
+        sequence.drain(2..5);
+        liftover.excise(2, 5);
+
+        // HIJABCDEFG -> HICDEFG
+        assert_eq!(sequence.iter().collect::<String>(), "HICDEFG");
+        for (output_position, base) in sequence.iter().enumerate() {
+            let input_position = liftover.to_input(output_position).unwrap();
+            assert_eq!((b'A' + input_position as u8) as char, *base);
+            assert_eq!(liftover.to_output(input_position), Some(output_position));
+        }
+        assert_eq!(liftover.to_output(9), None);
+        assert_eq!(liftover.to_output(0), None);
+        assert_eq!(liftover.blocks(), vec![
+            LiftoverBlock { input_start: 7, input_end: 9, output_start: 0, output_end: 2 },
+            LiftoverBlock { input_start: 2, input_end: 7, output_start: 2, output_end: 7 },
+        ]);
+    }
+
+    #[test]
+    fn test_from_input_positions() {
+        // "AC\r\nG1T" cleaned to "ACGT"
+        let liftover = Liftover::from_input_positions(vec![0, 1, 4, 6], 7);
+        assert_eq!(liftover.to_output(4), Some(2));
+        assert_eq!(liftover.to_output(2), None);
+        assert_eq!(liftover.to_input(3), Some(6));
+        assert_eq!(liftover.blocks(), vec![
+            LiftoverBlock { input_start: 0, input_end: 2, output_start: 0, output_end: 2 },
+            LiftoverBlock { input_start: 4, input_end: 5, output_start: 2, output_end: 3 },
+            LiftoverBlock { input_start: 6, input_end: 7, output_start: 3, output_end: 4 },
+        ]);
+    }
+}
@@ -0,0 +1,220 @@
+use std::f64;
+use std::mem::size_of;
+
+use mymatrix::DiagonalBand;
+use needleman::Alignment;
+use needleman::Direction;
+use needleman::Scores;
+use smith_waterman_no_diag::in_diagonal_band;
+
+/// the bytes the full-matrix smith_waterman_no_diag needs for a pair of sequences
+pub fn full_matrix_bytes(seq1_length: usize, seq2_length: usize) -> usize {
+    (seq1_length + 1) * (seq2_length + 1) * (size_of::<f64>() + size_of::<Direction>())
+}
+
+/// an upper bound on the bytes the banded smith_waterman_no_diag needs: every band is at most 2 * width + 1
+/// cells across in each row
+pub fn banded_matrix_bytes(seq1_length: usize, bands: &[DiagonalBand]) -> usize {
+    let band_cells: usize = bands.iter().map(|band| 2 * band.width + 1).sum();
+    (seq1_length + 1) * band_cells * (size_of::<f64>() + size_of::<Direction>())
+}
+
+/// the bytes the linear-space engine needs: a handful of score rows over the longer sequence, and two
+/// rows of traceback start cells
+pub fn linear_space_bytes(seq1_length: usize, seq2_length: usize) -> usize {
+    4 * (seq1_length.max(seq2_length) + 1) * size_of::<f64>() + 2 * (seq2_length + 1) * size_of::<(usize, usize)>()
+}
+
+/// The same local alignment smith_waterman_no_diag finds, in memory linear in the sequence lengths. A
+/// score-only forward pass finds the best end cell, along with the cell the full-matrix traceback from it
+/// would stop at, and Hirschberg's divide and conquer recovers the aligned bases in between.
+pub fn smith_waterman_no_diag_linear(seq1: &[char], seq2: &[char], scores: &Scores, min_diag_distance: i32) -> Alignment {
+    let (top_score, (end_x, end_y), (start_x, start_y)) = best_end_cell(seq1, seq2, scores, min_diag_distance);
+
+    let (seq_one_aligned, seq_two_aligned) = hirschberg(&seq1[start_x..end_x], &seq2[start_y..end_y], scores);
+    Alignment {
+        seq_one: seq1.to_vec(),
+        seq_two: seq2.to_vec(),
+        score: top_score,
+        start_x,
+        start_y,
+        end_x,
+        end_y,
+        seq_one_aligned,
+        seq_two_aligned,
+    }
+}
+
+/// Forward score-only pass, keeping one row; ties go the same way as the full-matrix fill. Alongside the
+/// scores each cell carries the cell its traceback would stop at: itself when its score is 0 (or it's in
+/// the excluded band), otherwise that of the neighbour the fill took its score from, preferring diagonal,
+/// then left, then up on ties just as the traceback matrix records them.
+fn best_end_cell(seq1: &[char], seq2: &[char], scores: &Scores, min_diag_distance: i32) -> (f64, (usize, usize), (usize, usize)) {
+    let mut previous = vec![0.0; seq2.len() + 1];
+    let mut current = vec![0.0; seq2.len() + 1];
+    let mut previous_start: Vec<(usize, usize)> = (0..seq2.len() + 1).map(|iy| (0, iy)).collect();
+    let mut current_start = previous_start.clone();
+    let mut top_score = 0.0;
+    let mut top = (0, 0);
+    let mut top_start = (0, 0);
+
+    let bar = indicatif::ProgressBar::new(seq1.len() as u64 + 1);
+    for ix in 1..seq1.len() + 1 {
+        current[0] = 0.0;
+        current_start[0] = (ix, 0);
+        for iy in 1..seq2.len() + 1 {
+            let score = Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
+            let up = previous[iy] + scores.gap_ext;
+            let left = current[iy - 1] + scores.gap_ext;
+            let diag = previous[iy - 1] + score;
+            let best = up.max(left).max(diag).max(0.0);
+            let stored = if in_diagonal_band(ix, iy, seq1.len(), seq2.len(), min_diag_distance) { 0.0 } else { best };
+            current_start[iy] = if stored <= 0.0 {
+                (ix, iy)
+            } else if diag >= up.max(left) {
+                previous_start[iy - 1]
+            } else if left >= up {
+                current_start[iy - 1]
+            } else {
+                previous_start[iy]
+            };
+            if best > top_score {
+                top_score = best;
+                top = (ix, iy);
+                top_start = current_start[iy];
+            }
+            current[iy] = stored;
+        }
+        std::mem::swap(&mut previous, &mut current);
+        std::mem::swap(&mut previous_start, &mut current_start);
+        bar.inc(1);
+    }
+    bar.finish();
+    (top_score, top, top_start)
+}
+
+/// the last row of a global (Needleman-Wunsch, linear gap) score matrix
+fn global_score_row(seq1: &[char], seq2: &[char], scores: &Scores) -> Vec<f64> {
+    let mut previous: Vec<f64> = (0..seq2.len() + 1).map(|iy| scores.gap_ext * iy as f64).collect();
+    let mut current = vec![0.0; seq2.len() + 1];
+    for ix in 1..seq1.len() + 1 {
+        current[0] = scores.gap_ext * ix as f64;
+        for iy in 1..seq2.len() + 1 {
+            let score = Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
+            current[iy] = (previous[iy - 1] + score)
+                .max(previous[iy] + scores.gap_ext)
+                .max(current[iy - 1] + scores.gap_ext);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous
+}
+
+/// Hirschberg's linear-space global alignment, returning the two gapped sequences
+pub fn hirschberg(seq1: &[char], seq2: &[char], scores: &Scores) -> (Vec<char>, Vec<char>) {
+    let gap = '-';
+    if seq1.is_empty() {
+        return (vec![gap; seq2.len()], seq2.to_vec());
+    }
+    if seq2.is_empty() {
+        return (seq1.to_vec(), vec![gap; seq1.len()]);
+    }
+    if seq1.len() == 1 {
+        // either the single base lines up with one position of seq2, or it's gapped out entirely
+        let all_gaps = scores.gap_ext * (seq2.len() + 1) as f64;
+        let (best_position, best_score) = seq2.iter().enumerate()
+            .map(|(position, base)| (position, Scores::scoring_function(seq1[0], *base, scores) + scores.gap_ext * (seq2.len() - 1) as f64))
+            .fold((0, f64::NEG_INFINITY), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+        if best_score >= all_gaps {
+            let mut aligned = vec![gap; seq2.len()];
+            aligned[best_position] = seq1[0];
+            return (aligned, seq2.to_vec());
+        }
+        let mut aligned_one = vec![seq1[0]];
+        aligned_one.extend(vec![gap; seq2.len()]);
+        let mut aligned_two = vec![gap];
+        aligned_two.extend_from_slice(seq2);
+        return (aligned_one, aligned_two);
+    }
+
+    let middle = seq1.len() / 2;
+    let upper = global_score_row(&seq1[..middle], seq2, scores);
+    let reversed_one: Vec<char> = seq1[middle..].iter().rev().cloned().collect();
+    let reversed_two: Vec<char> = seq2.iter().rev().cloned().collect();
+    let lower = global_score_row(&reversed_one, &reversed_two, scores);
+
+    let mut split = 0;
+    let mut best = f64::NEG_INFINITY;
+    for position in 0..seq2.len() + 1 {
+        let total = upper[position] + lower[seq2.len() - position];
+        if total > best {
+            best = total;
+            split = position;
+        }
+    }
+
+    let (mut aligned_one, mut aligned_two) = hirschberg(&seq1[..middle], &seq2[..split], scores);
+    let (right_one, right_two) = hirschberg(&seq1[middle..], &seq2[split..], scores);
+    aligned_one.extend(right_one);
+    aligned_two.extend(right_two);
+    (aligned_one, aligned_two)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smith_waterman_no_diag::smith_waterman_no_diag;
+    use test_support::{random_sequence, test_scores};
+
+    #[test]
+    fn test_matches_full_matrix() {
+        let scores = test_scores();
+        let repeat = random_sequence(60, 7);
+        let mut reference = random_sequence(80, 11);
+        reference.extend_from_slice(&repeat);
+        reference.extend(random_sequence(50, 13));
+        let mut mutated = repeat.clone();
+        mutated[30] = if mutated[30] == 'A' { 'C' } else { 'A' };
+        mutated.remove(45);
+        reference.extend(mutated);
+        reference.extend(random_sequence(40, 17));
+        let mut doubled = reference.clone();
+        doubled.extend_from_slice(&reference);
+
+        let full = smith_waterman_no_diag(&reference, &doubled, &scores, 10);
+        let linear = smith_waterman_no_diag_linear(&reference, &doubled, &scores, 10);
+        assert_eq!(linear.score, full.score);
+        assert_eq!((linear.start_x, linear.end_x, linear.start_y, linear.end_y), (full.start_x, full.end_x, full.start_y, full.end_y));
+        assert_eq!(linear.seq_one_aligned.iter().filter(|&&base| base != '-').count(), full.end_x - full.start_x);
+        assert_eq!(linear.seq_two_aligned.iter().filter(|&&base| base != '-').count(), full.end_y - full.start_y);
+    }
+
+    #[test]
+    fn test_ties_start_where_the_full_traceback_stops() {
+        // with every score +-1 many paths tie, and several cells on them score 0, so the start cell depends
+        // on which way each cell's traceback pointer went
+        let scores = Scores { match_score: 1.0, mismatch_score: -1.0, gap_open: -1.0, gap_ext: -1.0, gap_start: -1.0, gap_end: -1.0, substitution_matrix: None };
+        for seed in 0..40 {
+            let reference = random_sequence(60, seed);
+            let mut doubled = reference.clone();
+            doubled.extend_from_slice(&reference);
+
+            let full = smith_waterman_no_diag(&reference, &doubled, &scores, 5);
+            let linear = smith_waterman_no_diag_linear(&reference, &doubled, &scores, 5);
+            assert_eq!(linear.score, full.score);
+            assert_eq!((linear.start_x, linear.end_x, linear.start_y, linear.end_y), (full.start_x, full.end_x, full.start_y, full.end_y), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_hirschberg_global() {
+        let scores = Scores::default_scores();
+        let seq1: Vec<char> = "ACGTTACGT".chars().collect();
+        let seq2: Vec<char> = "ACGTACGT".chars().collect();
+        let (aligned_one, aligned_two) = hirschberg(&seq1, &seq2, &scores);
+
+        assert_eq!(aligned_one.iter().collect::<String>(), "ACGTTACGT");
+        assert_eq!(aligned_two.iter().filter(|&&base| base != '-').collect::<String>(), "ACGTACGT");
+        assert_eq!(aligned_two.iter().filter(|&&base| base == '-').count(), 1);
+    }
+}
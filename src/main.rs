@@ -1,27 +1,35 @@
-mod convex;
-mod kmer_orientation;
-mod score_matrix;
-pub mod mymatrix;
-pub mod needleman;
-
-pub mod smith_waterman_no_diag;
-
 extern crate bio;
 extern crate clap;
-extern crate csv;
-extern crate matrix;
-extern crate string_builder;
-extern crate indicatif;
-
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::{Write};
-use needleman::{Scores, Alignment};
-use clap::{Arg, App};
-use std::cmp::{min,max};
-use std::iter::FromIterator;
-
-fn main() -> std::io::Result<()> {
+extern crate dupscoop;
+
+use std::fs;
+use std::panic;
+use std::process;
+use std::str::FromStr;
+use std::thread;
+use clap::{Arg, ArgGroup, App, ArgMatches, SubCommand};
+use dupscoop::batch::SampleStatus;
+use dupscoop::{batch, compare, liftover, pipeline, report, sequence_io};
+use dupscoop::config::RunConfig;
+use dupscoop::header::NameTemplate;
+use dupscoop::{DupScoopError, Scores};
+use dupscoop::error::{EXIT_BAD_INPUT, EXIT_INTERNAL, EXIT_NO_DUPLICATIONS, EXIT_SUCCESS};
+
+fn main() {
+    // a panic is a bug rather than a problem with the input; the default hook has already printed it
+    let code = match panic::catch_unwind(run) {
+        Ok(Ok(code)) => code,
+        Ok(Err(error)) => {
+            eprintln!("Error: {}", error);
+            error.exit_code()
+        }
+        Err(_) => EXIT_INTERNAL,
+    };
+    process::exit(code);
+}
+
+/// run the command, returning the exit status
+fn run() -> Result<i32, DupScoopError> {
     let matches = App::new("DupScoop")
         .version("1.0")
         .author("Aaron M. <aaronatwpi@gmail.com>")
@@ -30,199 +38,414 @@ fn main() -> std::io::Result<()> {
             .short("r")
             .long("ref")
             .value_name("FILE")
-            .help("The reference we will align to")
+            .help("The reference we will align to (FASTA or GenBank, optionally gzip or BGZF compressed, or - for stdin); every record is deduplicated separately")
             .takes_value(true))
         .arg(Arg::with_name("output")
              .short("o")
              .long("output")
              .value_name("FILE")
-             .help("the alignment output file")
+             .help("the alignment output file, written in the same format as the reference (compressed if named .gz or .bgz, - for stdout)")
              .takes_value(true))
-        .arg(Arg::with_name("minLength")
-            .short("m")
-            .long("min")
-            .value_name("INT")
-            .help("the minimum length for a segment to be considered a duplication")
+        .arg(Arg::with_name("outputNameTemplate")
+            .long("output-name-template")
+            .value_name("TEMPLATE")
+            .help("name output records from a template such as {id}_dedup, using {id}, {index}, {orig_len}, {len} and {removed} (not with --detect-only)")
             .takes_value(true))
-        .arg(Arg::with_name("minScoreProportion")
-            .short("s")
-            .long("score")
-            .value_name("FLOAT")
-            .help("the score ")
+        .args(&dedup_args())
+        .arg(Arg::with_name("report")
+            .long("report")
+            .value_name("FILE")
+            .help("write a report of every duplication removed; JSON if the file ends in .json, otherwise TSV")
             .takes_value(true))
-        .arg(Arg::with_name("minDiagDistance")
-            .short("d")
-            .long("diagonaldist")
-            .value_name("INT")
-            .help("the score ")
+        .arg(Arg::with_name("liftover")
+            .long("liftover")
+            .value_name("FILE")
+            .help("write a TSV interval map from input to deduplicated output coordinates; input positions count any spaces or numbers dropped from the sequence")
+            .takes_value(true))
+        .arg(Arg::with_name("detectOnly")
+            .long("detect-only")
+            .help("report every non-overlapping duplicated segment but write the input sequences unchanged"))
+        .arg(Arg::with_name("preset")
+            .long("preset")
+            .value_name("NAME")
+            .possible_values(&Scores::PRESETS)
+            .help("the starting set of alignment scores (default: default)")
             .takes_value(true))
-        .get_matches();
+        .args(&score_args())
+        .arg(Arg::with_name("config")
+            .long("config")
+            .value_name("FILE")
+            .help("read scores and thresholds from a TOML file; command line flags override it")
+            .takes_value(true))
+        .arg(Arg::with_name("saveConfig")
+            .long("save-config")
+            .value_name("FILE")
+            .help("write every score and threshold this run used to a TOML file that --config can replay")
+            .takes_value(true))
+        .subcommand(SubCommand::with_name("compare")
+            .about("compare deduplicated plasmids against the reference maps they were designed from")
+            .arg(Arg::with_name("input")
+                .short("i")
+                .long("input")
+                .value_name("FILE")
+                .help("the DupScoop output to check (FASTA, or GenBank if named .gb/.gbk)")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("expected")
+                .short("e")
+                .long("expected")
+                .value_name("FILE")
+                .help("the expected reference maps; records are paired by name, or every input is compared to the only record")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("where to write the differences; JSON if the file ends in .json, otherwise tab-separated (default comparison.tsv)")
+                .takes_value(true))
+            .arg(Arg::with_name("preset")
+                .long("preset")
+                .value_name("NAME")
+                .possible_values(&Scores::PRESETS)
+                .help("the alignment scores to compare with (default: default)")
+                .takes_value(true))
+            .args(&score_args())
+            .arg(Arg::with_name("config")
+                .long("config")
+                .value_name("FILE")
+                .help("read scores and the memory budget from a TOML file; command line flags override it")
+                .takes_value(true))
+            .arg(Arg::with_name("maxMemory")
+                .long("max-memory")
+                .value_name("MB")
+                .help("the memory budget for one alignment (default 4096)")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("batch")
+            .about("deduplicate many plasmid assemblies in parallel, writing a summary table of every sample")
+            .arg(Arg::with_name("sampleSheet")
+                .long("sample-sheet")
+                .value_name("CSV")
+                .help("a CSV file with sample_id, input and output columns")
+                .takes_value(true))
+            .arg(Arg::with_name("inputGlob")
+                .long("input-glob")
+                .value_name("GLOB")
+                .help("process every file in a directory matching a pattern such as assemblies/*.fa")
+                .takes_value(true))
+            .group(ArgGroup::with_name("samples")
+                .args(&["sampleSheet", "inputGlob"])
+                .required(true))
+            .arg(Arg::with_name("outputDir")
+                .long("output-dir")
+                .value_name("DIR")
+                .help("where files matched by --input-glob are written, under their input names")
+                .takes_value(true))
+            .arg(Arg::with_name("outputNameTemplate")
+                .long("output-name-template")
+                .value_name("TEMPLATE")
+                .help("name output records from a template such as {id}_dedup, using {id}, {index}, {orig_len}, {len} and {removed} (not with --detect-only)")
+                .takes_value(true))
+            .arg(Arg::with_name("threads")
+                .short("t")
+                .long("threads")
+                .value_name("INT")
+                .help("how many samples to process at once (default: the number of CPUs)")
+                .takes_value(true))
+            .arg(Arg::with_name("summary")
+                .long("summary")
+                .value_name("FILE")
+                .help("where to write the per-sample summary; JSON if the file ends in .json, otherwise tab-separated (default batch_summary.tsv)")
+                .takes_value(true))
+            .arg(Arg::with_name("config")
+                .long("config")
+                .value_name("FILE")
+                .help("read scores and thresholds for every sample from a TOML file; command line flags override it")
+                .takes_value(true))
+            .arg(Arg::with_name("preset")
+                .long("preset")
+                .value_name("NAME")
+                .possible_values(&Scores::PRESETS)
+                .help("the alignment scores to start from, overriding the configuration's preset")
+                .takes_value(true))
+            .arg(Arg::with_name("detectOnly")
+                .long("detect-only")
+                .help("only report duplications, leaving every sample as it is"))
+            .args(&dedup_args())
+            .args(&score_args()))
+        .after_help("EXIT STATUS:\n    0  duplications were found (or the subcommand succeeded)\n    1  no record had a duplication or inverted repeat\n    \
+                     2  bad arguments, configuration, or input sequences\n    3  a record is too long for the memory budget\n    4  a file couldn't be read or written\n    \
+                     5  internal failure\n    6  some samples of a batch failed")
+        .get_matches_safe();
+    let matches = match matches {
+        Ok(matches) => matches,
+        Err(error) if error.use_stderr() => {
+            eprintln!("{}", error.message);
+            return Ok(EXIT_BAD_INPUT);
+        }
+        // --help and --version
+        Err(error) => error.exit(),
+    };
+
+    if let Some(compare_matches) = matches.subcommand_matches("compare") {
+        return run_compare(compare_matches);
+    }
+    if let Some(batch_matches) = matches.subcommand_matches("batch") {
+        return run_batch(batch_matches);
+    }
 
     let output_file = matches.value_of("output").unwrap_or("output.fa");
     let reference_file = matches.value_of("reference").unwrap_or("reference.fa");
 
-    let min_score_prop: f64 = matches.value_of("minScoreProportion").unwrap_or("0.9").parse::<f64>().unwrap();
-    let min_length: u64 = matches.value_of("minLength").unwrap_or("1000").parse::<u64>().unwrap();
-    let diag_dist: i32 = matches.value_of("minDiagDistance").unwrap_or("10").parse::<i32>().unwrap();
-
-    let reference_as_chars = reference_to_sequence(reference_file).unwrap();
-    let mut reference_as_chars_duplicated = reference_to_sequence(&reference_file).unwrap();
-    let mut refClone = reference_as_chars_duplicated.clone();
-    reference_as_chars_duplicated.append(&mut refClone);
-
-    let scores = Scores::default_scores();
-
-    let mut output = File::create(output_file).unwrap();
-
-    if reference_as_chars.len() > 30000 {
-        println!("Warning: we don't try to deduplicate plasmids longer than 30kb...exiting with existing plasmid");
-        writeln!(output, ">reference\n{}\n", String::from_iter(reference_as_chars));
-    } else {
-        // do an initial check of duplication before diving into subsequent alignments
-        let mut check_dups = check_for_duplicate_region(&reference_as_chars, &reference_as_chars_duplicated, min_score_prop, min_length, &scores, diag_dist);
-        let mut current_reference = reference_as_chars.clone();
-
-        // if we saw a candidate dup
-        let mut still_dup = check_dups.0;
-        if still_dup {
-            while still_dup {
-                let rotated_reference = rotate_reference(&current_reference, check_dups.1);
-                current_reference = align_and_remove_dup(&rotated_reference, min_score_prop, min_length, &scores, diag_dist);
-                let mut reference_as_chars_duplicated = current_reference.clone();
-                let mut refClone = reference_as_chars_duplicated.clone();
-                reference_as_chars_duplicated.append(&mut refClone);
-                check_dups = check_for_duplicate_region(&current_reference, &reference_as_chars_duplicated, min_score_prop, min_length, &scores, diag_dist);
-                still_dup = check_dups.0;
-                println!("Dup loop!");
-            }
-            let resulting_reference = String::from_iter(current_reference);
-            writeln!(output, ">reference\n{}\n", resulting_reference);
-        } else {
-            println!("No dups found!");
-            writeln!(output, ">reference\n{}\n", String::from_iter(reference_as_chars));
-        }
+    let config = layered_config(&matches)?;
+    let deduplicator = config.deduplicator()?;
+    let detect_only = config.dedup.detect_only.unwrap_or(false);
+    if let Some(config_file) = matches.value_of("saveConfig") {
+        RunConfig::resolved(&deduplicator, config.scores.matrix.as_deref(), detect_only).write(config_file)?;
     }
-    Ok(())
-}
-
-fn align_and_remove_dup(reference: &Vec<char>, min_score_prop: f64, min_length: u64, scores: &Scores, diag_dist: i32) -> Vec<char> {
-    let alignment = smith_waterman_no_diag::smith_waterman_no_diag(&reference, &reference, &scores, diag_dist);
-    let seq_one_aligned= String::from_iter(alignment.seq_one_aligned.clone().into_iter().filter(|&x| x != '-'));
-    let seq_two_aligned= String::from_iter(alignment.seq_two_aligned.clone().into_iter().filter(|&x| x != '-'));
-    let min_size = min(seq_one_aligned.len(), seq_two_aligned.len());
-
-    let mut start_del = alignment.start_x;
-    let mut end_del = alignment.end_x;
-    /*println!("Alignment starts and stops {},{} with score {}, and {},{} with lengths {} and {}, sequences {} and {} from {} and {}",alignment.start_x,
-             alignment.end_x,
-             alignment.score,
-             alignment.start_y,
-             alignment.end_y,
-             seq_one_aligned.len(),
-             seq_two_aligned.len(),
-             seq_one_aligned,
-             seq_two_aligned,
-             String::from_iter(alignment.seq_one_aligned),
-             String::from_iter(alignment.seq_two_aligned));
-*/
-    // delete the smallest chunk possible
-    if alignment.start_y > alignment.start_x {
-        if seq_two_aligned.len() == min_size {
-            start_del = max(alignment.start_y,alignment.end_x);
-            end_del = alignment.end_y;
-        } else {
-            start_del = alignment.start_x;
-            end_del = min(alignment.end_x, alignment.start_y);
-        }
 
-    } else {
-        if seq_two_aligned.len() == min_size {
-            start_del = alignment.start_y;
-            end_del = min(alignment.end_y,alignment.start_x);
-        } else {
-            start_del = max(alignment.start_x,alignment.end_y);
-            end_del = alignment.end_y;
-        }
+    let name_template = matches.value_of("outputNameTemplate").map(NameTemplate::parse).transpose()?;
+    let result = pipeline::deduplicate_file(&deduplicator, reference_file, output_file, detect_only, name_template.as_ref())?;
+    if let Some(report_file) = matches.value_of("report") {
+        report::write_report(report_file, &result.events)?;
     }
-
-    let split_at_start = reference.split_at(start_del);
-    let mut first_half: Vec<char> = split_at_start.0.to_vec();
-    println!("First half length {} and cut point {}",first_half.len(), start_del);
-    let mut second_half: Vec<char> = split_at_start.1[(end_del-start_del)..].to_vec();
-    println!("Second half length {} and cut point {} from fragment {}",second_half.len(), end_del-start_del,split_at_start.1.len());
-    first_half.append(&mut second_half);
-    first_half
-
+    if let Some(liftover_file) = matches.value_of("liftover") {
+        liftover::write_liftover(liftover_file, &result.liftovers)?;
+    }
+    Ok(if result.found_duplications() { EXIT_SUCCESS } else { EXIT_NO_DUPLICATIONS })
 }
 
-fn rotate_reference(reference: &Vec<char>,offset: usize) -> Vec<char> {
-    let mut new_ref = reference.clone();
-    assert!(offset <= new_ref.len(),"Unable to rotate reference by {} bases as this is longer then the length {}",offset,new_ref.len());
-    new_ref.rotate_right(offset);
-    new_ref
+/// the compare subcommand: align each input record against its expected map and write out the differences
+fn run_compare(matches: &ArgMatches) -> Result<i32, DupScoopError> {
+    let samples = sequence_io::read_sequence_records(matches.value_of("input").unwrap())?;
+    let expected = sequence_io::read_sequence_records(matches.value_of("expected").unwrap())?;
+    let config = layered_config(matches)?;
+    let scores = config.scores()?;
+    let max_memory = config.dedup.max_memory_mb.unwrap_or(4096) * 1024 * 1024;
+
+    let mut rows = Vec::new();
+    for sample in &samples {
+        let reference = match expected.iter().find(|record| record.id == sample.id) {
+            Some(record) => record,
+            None if expected.len() == 1 => &expected[0],
+            None => return Err(DupScoopError::InvalidArgument(format!("there's no expected record named {} to compare it with", sample.id))),
+        };
+        eprintln!("Comparing {} ({} bases) with {} ({} bases)", sample.id, sample.sequence.len(), reference.id, reference.sequence.len());
+        let comparison = compare::compare_to_reference(&sample.id, &sample.sequence, &reference.sequence, &scores, max_memory)?;
+        eprintln!("{} differences at identity {:.4} ({} strand, rotated by {})", comparison.differences.len(), comparison.identity,
+                 if comparison.reverse_complemented { "reverse" } else { "forward" }, comparison.rotation);
+        rows.append(&mut comparison.rows(&sample.id, &reference.id));
+    }
+    report::write_report(matches.value_of("output").unwrap_or("comparison.tsv"), &rows)?;
+    Ok(EXIT_SUCCESS)
 }
 
-fn aligned_distance(alignment: &Alignment) -> u32 {
-    let it = alignment.seq_one_aligned.iter().zip(alignment.seq_two_aligned.iter());
-    let mut differences = 0;
-    for (i, (x, y)) in it.enumerate() {
-        if x.to_uppercase().to_string() != y.to_uppercase().to_string() {
-            differences += 1
+/// the batch subcommand: deduplicate every sample of a sample sheet or directory glob on a pool of threads
+fn run_batch(matches: &ArgMatches) -> Result<i32, DupScoopError> {
+    let samples = match matches.value_of("sampleSheet") {
+        Some(sheet) => batch::read_sample_sheet(sheet)?,
+        None => {
+            let output_dir = matches.value_of("outputDir").ok_or_else(|| DupScoopError::InvalidArgument("--input-glob needs an --output-dir to write to".to_string()))?;
+            fs::create_dir_all(output_dir)?;
+            batch::samples_from_glob(matches.value_of("inputGlob").unwrap(), output_dir)?
         }
+    };
+    let config = layered_config(matches)?;
+    let deduplicator = config.deduplicator()?;
+    let detect_only = config.dedup.detect_only.unwrap_or(false);
+    let threads = match parse_optional::<usize>(matches, "threads")? {
+        Some(threads) => threads,
+        None => thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1),
+    };
+
+    eprintln!("Processing {} samples on {} threads", samples.len(), threads);
+    let name_template = matches.value_of("outputNameTemplate").map(NameTemplate::parse).transpose()?;
+    let summaries = batch::run_batch(&samples, &deduplicator, detect_only, name_template.as_ref(), threads);
+    report::write_report(matches.value_of("summary").unwrap_or("batch_summary.tsv"), &summaries)?;
+    let failed = summaries.iter().filter(|summary| summary.status == SampleStatus::Failed).count();
+    if failed > 0 {
+        return Err(DupScoopError::SamplesFailed { failed, total: summaries.len() });
     }
-    differences
+    Ok(EXIT_SUCCESS)
 }
 
-fn check_for_duplicate_region(reference: &Vec<char>, reference_dup: &Vec<char>, min_score_prop: f64, min_length: u64, scores: &Scores, diag_dist: i32) -> (bool, usize) {
-    let alignment = smith_waterman_no_diag::smith_waterman_no_diag(&reference, &reference_dup, &scores, diag_dist);
-    let length_one = alignment.end_x - alignment.start_x;
-    let length_two = alignment.end_y - alignment.start_y;
-    let min_size = min(length_one, length_two);
-    let seq_one_aligned= String::from_iter(alignment.seq_one_aligned.clone().into_iter().filter(|&x| x != '-'));
-    let seq_two_aligned= String::from_iter(alignment.seq_two_aligned.clone().into_iter().filter(|&x| x != '-'));
-
-    let seq1_aligned_len = alignment.seq_one_aligned.clone().len() as f64;
-    let seq1_aligned = alignment.seq_one_aligned.clone();
-    let seq2_aligned = alignment.seq_two_aligned.clone();
-    let start_y = alignment.start_y;
-    let differences = aligned_distance(&alignment);
-    let matching_prop = 1.0 - (differences as f64/ seq1_aligned_len);
-
-/*
-    println!("PRE starts and stops {},{} with score {}, and {},{} with lengths {} and {}, sequences {} and {} from {} and {}",alignment.start_x,
-             alignment.end_x,
-             alignment.score,
-             alignment.start_y,
-             alignment.end_y,
-             seq_one_aligned.len(),
-             seq_two_aligned.len(),
-             seq_one_aligned,
-             seq_two_aligned,
-             String::from_iter(seq1_aligned),
-             String::from_iter(seq2_aligned));
-*/
-    (min_size > min_length as usize && min_score_prop < matching_prop,start_y)
+/// the duplication thresholds and search options, shared by the default command and batch
+fn dedup_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("minLength")
+            .short("m")
+            .long("min")
+            .value_name("INT")
+            .help("the minimum length for a segment to be considered a duplication (default 1000)")
+            .takes_value(true),
+        Arg::with_name("minScoreProportion")
+            .short("s")
+            .long("score")
+            .value_name("FLOAT")
+            .help("the proportion of aligned columns that have to match for two segments to count as copies (default 0.9)")
+            .takes_value(true),
+        Arg::with_name("minDiagDistance")
+            .short("d")
+            .long("diagonaldist")
+            .value_name("INT")
+            .help("ignore alignments closer than this to the main diagonal, where the plasmid trivially matches itself (default 10)")
+            .takes_value(true),
+        Arg::with_name("inverted")
+            .long("inverted")
+            .help("also search for inverted (reverse-complement) duplications and report them"),
+        Arg::with_name("collapseInverted")
+            .long("collapse-inverted")
+            .help("remove inverted duplications the same way forward duplications are removed (implies --inverted)"),
+        Arg::with_name("invalidBases")
+            .long("invalid-bases")
+            .value_name("MODE")
+            .possible_values(&["reject", "mask"])
+            .help("what to do with characters that aren't IUPAC nucleotide codes: fail the record, or replace them with N (default reject)")
+            .takes_value(true),
+        Arg::with_name("softMask")
+            .long("soft-mask")
+            .help("keep lowercase (soft-masked) bases lowercase in the output"),
+        Arg::with_name("maxMemory")
+            .long("max-memory")
+            .value_name("MB")
+            .help("the memory budget for an alignment; larger plasmids switch to a slower linear-space aligner (default 4096)")
+            .takes_value(true),
+        Arg::with_name("prefilter")
+            .long("prefilter")
+            .help("only align the regions around repeated k-mers instead of the whole plasmid against itself"),
+        Arg::with_name("kmer")
+            .long("kmer")
+            .value_name("INT")
+            .help("the k-mer size used by --prefilter (default 15)")
+            .takes_value(true),
+        Arg::with_name("engine")
+            .long("engine")
+            .value_name("NAME")
+            .possible_values(&["linear", "affine"])
+            .help("the aligner used to search for duplications: linear gaps, or affine gaps (open plus extension) so one long indel isn't over-penalized (default linear)")
+            .takes_value(true),
+        Arg::with_name("affine")
+            .long("affine")
+            .help("shorthand for --engine affine"),
+        Arg::with_name("trimOverlap")
+            .long("trim-overlap")
+            .help("treat each record as a linear contig: trim a suffix that repeats its start (the assembler running around the circle) before searching for duplications"),
+        Arg::with_name("minOverlap")
+            .long("min-overlap")
+            .value_name("INT")
+            .help("the shortest end overlap --trim-overlap removes (default 100); its identity has to pass --score too")
+            .takes_value(true),
+        Arg::with_name("circular")
+            .long("circular")
+            .help("align each plasmid against itself as a circle (an n x n matrix) instead of against its doubled sequence; needs the linear engine"),
+    ]
 }
 
-fn reference_to_sequence(reference_file: &str) -> Result< Vec<char>, std::io::Error> {
-    let mut file = File::open(reference_file)?;
-    let mut reference = String::new();
-    file.read_to_string(&mut reference)?;
+/// the per-field score flags, shared by every command that aligns
+fn score_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("matchScore")
+            .long("match")
+            .value_name("FLOAT")
+            .help("the score for a matching base")
+            .allow_hyphen_values(true)
+            .takes_value(true),
+        Arg::with_name("mismatchScore")
+            .long("mismatch")
+            .value_name("FLOAT")
+            .help("the score for a mismatched base")
+            .allow_hyphen_values(true)
+            .takes_value(true),
+        Arg::with_name("gapOpen")
+            .long("gap-open")
+            .value_name("FLOAT")
+            .help("the score for opening a gap (affine engine)")
+            .allow_hyphen_values(true)
+            .takes_value(true),
+        Arg::with_name("gapExt")
+            .long("gap-ext")
+            .value_name("FLOAT")
+            .help("the score for each gapped base")
+            .allow_hyphen_values(true)
+            .takes_value(true),
+        Arg::with_name("gapStart")
+            .long("gap-start")
+            .value_name("FLOAT")
+            .help("the score for a gap at the start of an alignment")
+            .allow_hyphen_values(true)
+            .takes_value(true),
+        Arg::with_name("gapEnd")
+            .long("gap-end")
+            .value_name("FLOAT")
+            .help("the score for a gap at the end of an alignment")
+            .allow_hyphen_values(true)
+            .takes_value(true),
+        Arg::with_name("matrix")
+            .long("matrix")
+            .value_name("NAME|FILE")
+            .help("score base pairs with a substitution matrix: iupac (ambiguity codes match any base they could stand for, and N scores 0), or an NCBI-format matrix file such as NUC.4.4")
+            .takes_value(true),
+    ]
+}
 
-    // slice off the first line (name), and combine the rest into a single string of characters
-    let first_line_marker = reference.find("\n");
-    println!("first endline: {}", first_line_marker.unwrap());
+/// the --config file (or the defaults) with --preset, the score flags and the duplication thresholds laid
+/// over it; flags a command doesn't take are left as configured
+fn layered_config(matches: &ArgMatches) -> Result<RunConfig, DupScoopError> {
+    let mut config = match matches.value_of("config") {
+        Some(config_file) => RunConfig::from_file(config_file)?,
+        None => RunConfig::default(),
+    };
+    config.preset = matches.value_of("preset").map(|name| name.to_string()).or(config.preset);
+    let scores = &mut config.scores;
+    scores.match_score = parse_optional(matches, "matchScore")?.or(scores.match_score);
+    scores.mismatch_score = parse_optional(matches, "mismatchScore")?.or(scores.mismatch_score);
+    scores.gap_open = parse_optional(matches, "gapOpen")?.or(scores.gap_open);
+    scores.gap_ext = parse_optional(matches, "gapExt")?.or(scores.gap_ext);
+    scores.gap_start = parse_optional(matches, "gapStart")?.or(scores.gap_start);
+    scores.gap_end = parse_optional(matches, "gapEnd")?.or(scores.gap_end);
+    scores.matrix = matches.value_of("matrix").map(|name| name.to_string()).or(scores.matrix.take());
 
-    match first_line_marker {
-        // The division was valid
-        Some(x) => {
-            reference = reference.split_off(x).replace("\n", "");
-        }
-        // The division was invalid
-        None => panic!("We couldn't find the reference name in your input file {}", reference_file),
+    let dedup = &mut config.dedup;
+    dedup.min_length = parse_optional(matches, "minLength")?.or(dedup.min_length);
+    dedup.min_identity = parse_optional(matches, "minScoreProportion")?.or(dedup.min_identity);
+    dedup.diagonal_distance = parse_optional(matches, "minDiagDistance")?.or(dedup.diagonal_distance);
+    dedup.max_memory_mb = parse_optional(matches, "maxMemory")?.or(dedup.max_memory_mb);
+    if matches.is_present("collapseInverted") {
+        dedup.inverted = Some("collapse".to_string());
+    } else if matches.is_present("inverted") {
+        dedup.inverted = Some("report".to_string());
     }
-
-    let reference_as_chars: Vec<char> = reference.to_string().to_uppercase().chars().collect();
-    Ok(reference_as_chars)
+    if matches.is_present("prefilter") {
+        dedup.prefilter_kmer = Some(parse_optional(matches, "kmer")?.or(dedup.prefilter_kmer).unwrap_or(15));
+    }
+    if matches.is_present("affine") {
+        dedup.engine = Some("affine".to_string());
+    } else if let Some(name) = matches.value_of("engine") {
+        dedup.engine = Some(name.to_string());
+    }
+    if matches.is_present("trimOverlap") {
+        dedup.trim_overlap = Some(true);
+    }
+    dedup.min_overlap = parse_optional(matches, "minOverlap")?.or(dedup.min_overlap);
+    if matches.is_present("circular") {
+        dedup.circular = Some(true);
+    }
+    if matches.is_present("detectOnly") {
+        dedup.detect_only = Some(true);
+    }
+    if let Some(mode) = matches.value_of("invalidBases") {
+        dedup.invalid_bases = Some(mode.to_string());
+    }
+    if matches.is_present("softMask") {
+        dedup.soft_mask = Some(true);
+    }
+    Ok(config)
 }
 
+/// parse a command line value if it was given
+fn parse_optional<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>, DupScoopError> {
+    match matches.value_of(name) {
+        Some(value) => value.parse::<T>().map(Some).map_err(|_| DupScoopError::InvalidArgument(format!("couldn't parse the value {} given for {}", value, name))),
+        None => Ok(None),
+    }
+}
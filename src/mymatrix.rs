@@ -21,7 +21,7 @@ impl<T> MyMatrix<T> where T: Clone + Debug + Sized + Display {
     pub fn value_count(&self) -> usize {self.values.len()}
 
     #[inline]
-    pub fn get<'a>(&self, row: usize, col: usize) -> T {
+    pub fn get(&self, row: usize, col: usize) -> T {
         self.values[(col * self.row_length) + row].clone()
     }
     
@@ -45,11 +45,10 @@ impl<T> MyMatrix<T> where T: Clone + Debug + Sized + Display {
     }
 
     pub fn new(row: usize, col: usize, initialize: T) -> MyMatrix<T> {
-        let mat = MyMatrix::<T>{
+        MyMatrix::<T>{
             values: vec![initialize; row * col],
             row_length: row,
-        };
-        mat
+        }
     }
 
     pub fn row(&self, row: usize) -> Vec<T> {
@@ -72,12 +71,138 @@ impl<T> MyMatrix<T> where T: Clone + Debug + Sized + Display {
                 print!("{number:>width$}",number=self.get(ix,iy), width = width);
                 print!("{:width$}",",", width = 2);
             }
-            print!("{}","\n");
+            println!();
         }
     }
 }
 
 
+/// read access shared by the dense and banded matrices, so a traceback can walk either
+pub trait MatrixStorage<T> {
+    fn rows(&self) -> usize;
+    fn cols(&self) -> usize;
+    fn get(&self, row: usize, col: usize) -> T;
+}
+
+impl<T> MatrixStorage<T> for MyMatrix<T> where T: Clone + Debug + Sized + Display {
+    fn rows(&self) -> usize { MyMatrix::rows(self) }
+    fn cols(&self) -> usize { MyMatrix::cols(self) }
+    fn get(&self, row: usize, col: usize) -> T { MyMatrix::get(self, row, col) }
+}
+
+/// The cells within `width` diagonals either side of `diagonal`, where a cell's diagonal is col - row
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagonalBand {
+    pub diagonal: isize,
+    pub width: usize,
+}
+
+impl DiagonalBand {
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        (col as isize - row as isize - self.diagonal).abs() <= self.width as isize
+    }
+}
+
+/// A matrix that only stores the cells inside a set of diagonal bands, using memory proportional to
+/// rows * band width. Cells outside every band read back as the `outside` value and can't be set.
+pub struct BandedMatrix<T: Clone> {
+    values: Vec<T>,
+    // for each row, the stored column ranges [start, end) and where each range starts in values
+    segments: Vec<Vec<(usize, usize, usize)>>,
+    col_count: usize,
+    outside: T,
+}
+
+#[allow(dead_code)]
+impl<T> BandedMatrix<T> where T: Clone + Debug + Sized + Display {
+    pub fn new(row: usize, col: usize, bands: &[DiagonalBand], initialize: T, outside: T) -> BandedMatrix<T> {
+        let mut segments = Vec::with_capacity(row);
+        let mut value_count = 0;
+        for row_index in 0..row {
+            let mut ranges: Vec<(usize, usize)> = bands.iter().filter_map(|band| {
+                let start = (row_index as isize + band.diagonal - band.width as isize).max(0);
+                let end = (row_index as isize + band.diagonal + band.width as isize + 1).min(col as isize);
+                if start < end { Some((start as usize, end as usize)) } else { None }
+            }).collect();
+            ranges.sort_unstable();
+
+            // overlapping bands share their cells
+            let mut row_segments: Vec<(usize, usize, usize)> = Vec::new();
+            for (start, end) in ranges {
+                match row_segments.last_mut() {
+                    Some(last) if start <= last.1 => {
+                        if end > last.1 {
+                            value_count += end - last.1;
+                            last.1 = end;
+                        }
+                    }
+                    _ => {
+                        row_segments.push((start, end, value_count));
+                        value_count += end - start;
+                    }
+                }
+            }
+            segments.push(row_segments);
+        }
+        BandedMatrix::<T> {
+            values: vec![initialize; value_count],
+            segments,
+            col_count: col,
+            outside,
+        }
+    }
+
+    #[inline]
+    pub fn rows(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.col_count
+    }
+
+    pub fn value_count(&self) -> usize {self.values.len()}
+
+    /// the stored column ranges [start, end) of a row, in increasing order
+    pub fn row_ranges(&self, row: usize) -> Vec<(usize, usize)> {
+        self.segments[row].iter().map(|segment| (segment.0, segment.1)).collect()
+    }
+
+    #[inline]
+    fn index(&self, row: usize, col: usize) -> Option<usize> {
+        self.segments[row].iter()
+            .find(|segment| segment.0 <= col && col < segment.1)
+            .map(|segment| segment.2 + col - segment.0)
+    }
+
+    pub fn in_band(&self, row: usize, col: usize) -> bool {
+        self.index(row, col).is_some()
+    }
+
+    #[inline]
+    pub fn get(&self, row: usize, col: usize) -> T {
+        match self.index(row, col) {
+            Some(index) => self.values[index].clone(),
+            None => self.outside.clone(),
+        }
+    }
+
+    #[inline]
+    pub fn set(&mut self, row: usize, col: usize, value: T) {
+        match self.index(row, col) {
+            Some(index) => self.values[index] = value,
+            None => panic!("Cell {},{} is outside the stored bands", row, col),
+        }
+    }
+}
+
+impl<T> MatrixStorage<T> for BandedMatrix<T> where T: Clone + Debug + Sized + Display {
+    fn rows(&self) -> usize { BandedMatrix::rows(self) }
+    fn cols(&self) -> usize { BandedMatrix::cols(self) }
+    fn get(&self, row: usize, col: usize) -> T { BandedMatrix::get(self, row, col) }
+}
+
+
 pub fn maximize_over_column(mtx: &MyMatrix<f64>, col: usize, cur_row: usize, scoring_function: &dyn Fn(usize) -> f64) -> (usize, f64) {
     let mut max_index = cur_row;
     let mut max_score = f64::MIN;
@@ -135,7 +260,7 @@ mod tests {
     fn minimize_over_x_test() {
 
         let mut mtx = MyMatrix::new(10, 5, 0.0);
-        let convert_function = |distance: usize| (-10.0 - ((distance as f64) * 0.1));
+        let convert_function = |distance: usize| -10.0 - ((distance as f64) * 0.1);
 
         mtx.set(3,4, 20.0);
         let (_, score) = maximize_over_column(&mtx, 4,4, &convert_function);
@@ -144,4 +269,22 @@ mod tests {
         assert_eq!(score,-10.1);
 
     }
+
+    #[test]
+    fn banded_storage() {
+        let mut mtx = BandedMatrix::new(10, 12, &[DiagonalBand { diagonal: 0, width: 1 }, DiagonalBand { diagonal: 6, width: 0 }], 0.0, -1.0);
+
+        assert_eq!(mtx.rows(), 10);
+        assert_eq!(mtx.cols(), 12);
+        assert_eq!(mtx.row_ranges(0), vec![(0, 2), (6, 7)]);
+        assert_eq!(mtx.row_ranges(9), vec![(8, 11)]);
+        assert_eq!(mtx.value_count(), 2 + 3 * 9 + 6);
+
+        mtx.set(4, 10, 3.0);
+        mtx.set(4, 5, 2.0);
+        assert_eq!(mtx.get(4, 10), 3.0);
+        assert_eq!(mtx.get(4, 5), 2.0);
+        assert_eq!(mtx.get(4, 8), -1.0);
+        assert!(!mtx.in_band(4, 8));
+    }
 }
@@ -1,9 +1,13 @@
 use mymatrix;
+use score_matrix::PairedScores;
+use std::sync::Arc;
+use mymatrix::{BandedMatrix, DiagonalBand, MatrixStorage};
 use needleman::Direction::{Left, Up, Diag, Done};
 use std::f64;
 use std::fmt;
 
 #[allow(dead_code)]
+#[derive(Clone)]
 pub struct Scores {
     pub match_score: f64,
     pub mismatch_score: f64,
@@ -11,6 +15,8 @@ pub struct Scores {
     pub gap_ext: f64,
     pub gap_start: f64,
     pub gap_end: f64,
+    /// if set, base pairs are scored from this table, falling back to mismatch_score for pairs it doesn't cover
+    pub substitution_matrix: Option<Arc<PairedScores>>,
 }
 
 impl Scores {
@@ -22,6 +28,7 @@ impl Scores {
             gap_ext: -6.0,
             gap_start: -10.0,
             gap_end: -10.0,
+            substitution_matrix: None,
         }
     }
 
@@ -33,17 +40,69 @@ impl Scores {
             gap_ext: -0.5,
             gap_start: -10.0,
             gap_end: -10.0,
+            substitution_matrix: None,
         }
     }
 
+    /// long-read assemblies: indels are the common error, so gaps are cheaper than mismatches
+    pub fn nanopore_assembly_scores() -> Scores {
+        Scores {
+            match_score: 2.0,
+            mismatch_score: -4.0,
+            gap_open: -4.0,
+            gap_ext: -2.0,
+            gap_start: -4.0,
+            gap_end: -4.0,
+            substitution_matrix: None,
+        }
+    }
+
+    /// short-read assemblies: few errors, and indels are rarer than substitutions
+    pub fn illumina_assembly_scores() -> Scores {
+        Scores {
+            match_score: 2.0,
+            mismatch_score: -4.0,
+            gap_open: -8.0,
+            gap_ext: -6.0,
+            gap_start: -8.0,
+            gap_end: -8.0,
+            substitution_matrix: None,
+        }
+    }
+
+    /// the names preset() accepts
+    pub const PRESETS: [&'static str; 4] = ["default", "plasmid", "nanopore-assembly", "illumina-assembly"];
+
+    /// look up a named set of scores
+    pub fn preset(name: &str) -> Option<Scores> {
+        match name {
+            "default" => Some(Scores::default_scores()),
+            "plasmid" => Some(Scores::plasmid_aligmment_scores()),
+            "nanopore-assembly" => Some(Scores::nanopore_assembly_scores()),
+            "illumina-assembly" => Some(Scores::illumina_assembly_scores()),
+            _ => None,
+        }
+    }
+
+    /// the same scores, with base pairs looked up in a substitution matrix
+    pub fn with_substitution_matrix(self, matrix: PairedScores) -> Scores {
+        Scores { substitution_matrix: Some(Arc::new(matrix)), ..self }
+    }
+
     #[inline]
     pub fn scoring_function(base1: char, base2: char, scores: &Scores) -> f64 {
-        if base1 == base2 {
-            return scores.match_score;
-        } else if base1 == 'N' || base2 == 'N' || base1 == 'Y' || base2 == 'Y' {
-            return 0.0;
+        if let Some(ref matrix) = scores.substitution_matrix {
+            return matrix.score(base1, base2).unwrap_or(scores.mismatch_score);
+        }
+        if base1 == 'N' || base2 == 'N' {
+            // an unknown base is no evidence either way, even against another N
+            0.0
+        } else if base1 == base2 {
+            scores.match_score
+        } else if base1 == 'Y' || base2 == 'Y' {
+            0.0
         } else {
-            return scores.mismatch_score;
+            scores.mismatch_score
         }
     }
 }
@@ -78,18 +137,17 @@ pub struct Alignment {
 }
 
 /// Aligns two sequences using the Needleman Wunsch global alignment with simple gap scoring 
-pub fn needleman_wunsch(seq1: &Vec<char>, seq2: &Vec<char>, scores: &Scores) -> Alignment {
+pub fn needleman_wunsch(seq1: &[char], seq2: &[char], scores: &Scores) -> Alignment {
     let seq1_limit = seq1.len() + 1;
     let seq2_limit = seq2.len() + 1;
 
     let mut mtx = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0.0);
-    println!("made matrix of {} {}", mtx.rows(), mtx.cols());
     let mut trc: mymatrix::MyMatrix<Direction> = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, Done);
     needleman_wunsch_borrow(seq1, seq2, &mut mtx, &mut trc, scores)
 }
 
-pub fn needleman_wunsch_borrow(seq1: &Vec<char>,
-                               seq2: &Vec<char>,
+pub fn needleman_wunsch_borrow(seq1: &[char],
+                               seq2: &[char],
                                mtx: &mut mymatrix::MyMatrix<f64>,
                                trc: &mut mymatrix::MyMatrix<Direction>,
                                scores: &Scores) -> Alignment {
@@ -114,9 +172,6 @@ pub fn needleman_wunsch_borrow(seq1: &Vec<char>,
     for ix in 1..seq1_limit {
         for iy in 1..seq2_limit {
             let score = Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
-            if (ix == iy || ((iy % seq1.len()) == ix) || ((ix % seq2.len()) == iy)) && ix % 100 == 0 {
-                println!("NOGO is from {},{}", ix, iy);
-            }
             let up_t = (mtx.get(ix - 1, iy) + scores.gap_ext, Up);
             let left_t = (mtx.get(ix, iy - 1) + scores.gap_ext, Left);
             let diag_t = (mtx.get(ix - 1, iy - 1) + score, Diag);
@@ -124,18 +179,157 @@ pub fn needleman_wunsch_borrow(seq1: &Vec<char>,
             //let max = max2(max2(max2(upT, leftT), diagT), (0.0,Diag));
             let max = max2(max2(up_t, left_t), diag_t);
 
+            mtx.set(ix, iy, max.0);
+            trc.set(ix, iy, max.1);
 
-            if ix % 1000 == 0 && iy % 1000 == 0 {
-                println!("top is now {} from {},{}", max.0, ix, iy);
+        }
+    }
+    traceback(seq1, seq2, trc, mtx.get(seq1_limit - 1, seq2_limit - 1), seq1_limit - 1, seq2_limit - 1)
+}
+
+/// Needleman Wunsch restricted to a set of diagonal bands, in memory proportional to the band width. The
+/// bands have to cover both corners of the matrix, the path can't leave them.
+pub fn needleman_wunsch_banded(seq1: &[char], seq2: &[char], scores: &Scores, bands: &[DiagonalBand]) -> Alignment {
+    let mut mtx = BandedMatrix::new(seq1.len() + 1, seq2.len() + 1, bands, 0.0, f64::NEG_INFINITY);
+    let mut trc = BandedMatrix::new(seq1.len() + 1, seq2.len() + 1, bands, Done, Done);
+    needleman_wunsch_banded_borrow(seq1, seq2, &mut mtx, &mut trc, scores)
+}
+
+pub fn needleman_wunsch_banded_borrow(seq1: &[char],
+                                      seq2: &[char],
+                                      mtx: &mut BandedMatrix<f64>,
+                                      trc: &mut BandedMatrix<Direction>,
+                                      scores: &Scores) -> Alignment {
+    let seq1_limit = seq1.len() + 1;
+    let seq2_limit = seq2.len() + 1;
+    assert!(mtx.in_band(0, 0) && mtx.in_band(seq1_limit - 1, seq2_limit - 1), "The bands don't cover both ends of the alignment");
+
+    for ix in 0..seq1_limit {
+        for (col_start, col_end) in mtx.row_ranges(ix) {
+            for iy in col_start..col_end {
+                let cell = match (ix, iy) {
+                    (0, 0) => (0.0, Done),
+                    (0, _) => (scores.gap_ext * (iy as f64), Left),
+                    (_, 0) => (scores.gap_ext * (ix as f64), Up),
+                    _ => {
+                        let score = Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
+                        let up_t = (mtx.get(ix - 1, iy) + scores.gap_ext, Up);
+                        let left_t = (mtx.get(ix, iy - 1) + scores.gap_ext, Left);
+                        let diag_t = (mtx.get(ix - 1, iy - 1) + score, Diag);
+                        max2(max2(up_t, left_t), diag_t)
+                    }
+                };
+                mtx.set(ix, iy, cell.0);
+                trc.set(ix, iy, cell.1);
             }
+        }
+    }
+    traceback(seq1, seq2, trc, mtx.get(seq1_limit - 1, seq2_limit - 1), seq1_limit - 1, seq2_limit - 1)
+}
+
+/// Which unaligned ends of the two sequences are free in a semi-global alignment. An overhanging end that
+/// isn't free costs gap_start per base at the start of a sequence and gap_end per base at its end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EndGaps {
+    pub seq1_start: bool,
+    pub seq1_end: bool,
+    pub seq2_start: bool,
+    pub seq2_end: bool,
+}
+
+impl EndGaps {
+    /// every end penalized: a global alignment, with end gaps scored by gap_start and gap_end
+    pub fn global() -> EndGaps {
+        EndGaps { seq1_start: false, seq1_end: false, seq2_start: false, seq2_end: false }
+    }
+
+    /// a suffix of seq1 against a prefix of seq2
+    pub fn overlap() -> EndGaps {
+        EndGaps { seq1_start: true, seq1_end: false, seq2_start: false, seq2_end: true }
+    }
+
+    /// all of seq1 somewhere inside seq2 (glocal)
+    pub fn contained() -> EndGaps {
+        EndGaps { seq1_start: false, seq1_end: false, seq2_start: true, seq2_end: true }
+    }
+}
+
+/// Semi-global alignment: global in the middle, with each overhanging end free or penalized as end_gaps
+/// says. Free overhangs are left out of the aligned sequences, so start_x..end_x and start_y..end_y are the
+/// aligned stretches; penalized ones are aligned against gaps and included.
+pub fn semi_global(seq1: &[char], seq2: &[char], scores: &Scores, end_gaps: &EndGaps) -> Alignment {
+    let seq1_limit = seq1.len() + 1;
+    let seq2_limit = seq2.len() + 1;
+
+    let mut mtx = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0.0);
+    let mut trc: mymatrix::MyMatrix<Direction> = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, Done);
+
+    // a free start leaves the first column (or row) at zero and Done, so the path can begin anywhere on it
+    if !end_gaps.seq1_start {
+        for n in 1..seq1_limit {
+            mtx.set(n, 0, scores.gap_start * (n as f64));
+            trc.set(n, 0, Up);
+        }
+    }
+    if !end_gaps.seq2_start {
+        for n in 1..seq2_limit {
+            mtx.set(0, n, scores.gap_start * (n as f64));
+            trc.set(0, n, Left);
+        }
+    }
 
+    // Moving along the last row (or down the last column) is a gap after the other sequence has ended, so
+    // a penalized end pays gap_end for it rather than gap_ext, the same as the overhang scan below
+    let last_row = seq1_limit - 1;
+    let last_column = seq2_limit - 1;
+    let trailing_left = if end_gaps.seq2_end { scores.gap_ext } else { scores.gap_end };
+    let trailing_up = if end_gaps.seq1_end { scores.gap_ext } else { scores.gap_end };
+    for ix in 1..seq1_limit {
+        for iy in 1..seq2_limit {
+            let score = Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
+            let up_t = (mtx.get(ix - 1, iy) + if iy == last_column { trailing_up } else { scores.gap_ext }, Up);
+            let left_t = (mtx.get(ix, iy - 1) + if ix == last_row { trailing_left } else { scores.gap_ext }, Left);
+            let diag_t = (mtx.get(ix - 1, iy - 1) + score, Diag);
+            let max = max2(max2(up_t, left_t), diag_t);
             mtx.set(ix, iy, max.0);
             trc.set(ix, iy, max.1);
+        }
+    }
 
+    // the path can finish anywhere on the last row or column, paying for whatever hangs over the end
+    let end_penalty = |free: bool, overhang: usize| if free { 0.0 } else { scores.gap_end * (overhang as f64) };
+    let mut best = (mtx.get(last_row, last_column), last_row, last_column);
+    for iy in 0..last_column {
+        let score = mtx.get(last_row, iy) + end_penalty(end_gaps.seq2_end, last_column - iy);
+        if score > best.0 {
+            best = (score, last_row, iy);
         }
     }
-    //trc.print_matrix(8);
-    traceback(seq1, seq2, trc, mtx.get(seq1_limit - 1, seq2_limit - 1), seq1_limit - 1, seq2_limit - 1)
+    for ix in 0..last_row {
+        let score = mtx.get(ix, last_column) + end_penalty(end_gaps.seq1_end, last_row - ix);
+        if score > best.0 {
+            best = (score, ix, last_column);
+        }
+    }
+
+    let (score, topx, topy) = best;
+    let mut alignment = traceback(seq1, seq2, &trc, score, topx, topy);
+    if !end_gaps.seq1_end && topx < last_row {
+        alignment.seq_one_aligned.extend_from_slice(&seq1[topx..]);
+        alignment.seq_two_aligned.extend(vec!['-'; last_row - topx]);
+        alignment.end_x = last_row;
+    }
+    if !end_gaps.seq2_end && topy < last_column {
+        alignment.seq_one_aligned.extend(vec!['-'; last_column - topy]);
+        alignment.seq_two_aligned.extend_from_slice(&seq2[topy..]);
+        alignment.end_y = last_column;
+    }
+    alignment
+}
+
+/// Overlap (suffix-prefix) alignment: a suffix of seq1 against a prefix of seq2
+pub fn overlap_alignment(seq1: &[char], seq2: &[char], scores: &Scores) -> Alignment {
+    semi_global(seq1, seq2, scores, &EndGaps::overlap())
 }
 
 #[inline]
@@ -152,15 +346,15 @@ struct TruthSet {
 }
 
 /// traceback a matrix into an alignment struct
-pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, trc: &mymatrix::MyMatrix<Direction>, top_score: f64, topx: usize, topy: usize) -> Alignment {
+pub fn traceback<M: MatrixStorage<Direction>>(seq1: &[char], seq2: &[char], trc: &M, top_score: f64, topx: usize, topy: usize) -> Alignment {
     assert_eq!(seq1.len(), trc.rows() - 1, "The matrix doesn't have the right number of rows; rows: {}, expected: {}", seq1.len(), trc.rows() - 1);
     assert_eq!(seq2.len(), trc.cols() - 1, "The matrix doesn't have the right number of columns; columns: {}, expected: {}", seq2.len(), trc.cols() - 1);
 
     let mut alignment1 = Vec::new();
     let mut alignment2 = Vec::new();
 
-    let mut row_index = seq1.len(); // topx;
-    let mut column_index = seq2.len(); // topx;
+    let mut row_index = topx;
+    let mut column_index = topy;
 
     let gap = '-';
 
@@ -171,24 +365,20 @@ pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, trc: &mymatrix::MyMatrix<Di
             Up => {
                 alignment1.push(seq1[row_index - 1]);
                 alignment2.push(gap);
-                // println!("LEFT: Moving from {},{} to {},{}", row_index, column_index, row_index - 1, column_index);
                 row_index -= 1;
             }
             Left => {
                 alignment1.push(gap);
                 alignment2.push(seq2[column_index - 1]);
-                // println!("LEFT: Moving from {},{} to {},{}", row_index, column_index, row_index, column_index - 1);
                 column_index -= 1;
             }
             Diag => {
                 alignment1.push(seq1[row_index - 1]);
                 alignment2.push(seq2[column_index - 1]);
-                // println!("DIAG: Moving from {},{} to {},{}", row_index, column_index, row_index - 1, column_index - 1);
                 row_index -= 1;
                 column_index -= 1;
             }
             Done => {
-                //println!("{},{}",row_index,column_index);
                 break;
             }
         }
@@ -197,8 +387,7 @@ pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, trc: &mymatrix::MyMatrix<Di
 
     alignment1.reverse();
     alignment2.reverse();
-    // println!("{},{}",alignment1.len(),alignment2.len());
-    return Alignment {
+    Alignment {
         seq_one: seq1.to_vec(),
         seq_two: seq2.to_vec(),
         score: top_score,
@@ -208,7 +397,7 @@ pub fn traceback(seq1: &Vec<char>, seq2: &Vec<char>, trc: &mymatrix::MyMatrix<Di
         end_y: topy,
         seq_one_aligned: alignment1,
         seq_two_aligned: alignment2,
-    };
+    }
 }
 
 #[cfg(test)]
@@ -245,7 +434,7 @@ mod tests {
     #[test]
     fn test_basic_alignment() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'A', 'A'], &vec!['A', 'A', 'A'], &scores);
+        let alignment = needleman_wunsch(&['A', 'A', 'A'], &['A', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -262,7 +451,7 @@ mod tests {
     #[test]
     fn test_basic_alignment_ns() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'N', 'A'], &vec!['A', 'T', 'A'], &scores);
+        let alignment = needleman_wunsch(&['A', 'N', 'A'], &['A', 'T', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -279,7 +468,7 @@ mod tests {
     #[test]
     fn test_basic_alignment_unequal() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A'], &vec!['A', 'A', 'A'], &scores);
+        let alignment = needleman_wunsch(&['A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A'], &['A', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -295,7 +484,7 @@ mod tests {
     #[test]
     fn test_basic_alignment_unequal_second() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'A', 'A'], &vec!['A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A'], &scores);
+        let alignment = needleman_wunsch(&['A', 'A', 'A'], &['A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -311,7 +500,7 @@ mod tests {
     #[test]
     fn test_mismatch_alignment() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'T', 'A'], &vec!['A', 'A', 'A'], &scores);
+        let alignment = needleman_wunsch(&['A', 'T', 'A'], &['A', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -329,7 +518,7 @@ mod tests {
     #[test]
     fn test_mismatch_length() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['A', 'T', 'T', 'A', 'A'], &vec!['T', 'A', 'A'], &scores);
+        let alignment = needleman_wunsch(&['A', 'T', 'T', 'A', 'A'], &['T', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -348,7 +537,7 @@ mod tests {
     #[test]
     fn test_mismatch_length2() {
         let scores = Scores::default_scores();
-        let alignment = needleman_wunsch(&vec!['G', 'G', 'G', 'A', 'T', 'T', 'A', 'A'], &vec!['G', 'G', 'T', 'A', 'A'], &scores);
+        let alignment = needleman_wunsch(&['G', 'G', 'G', 'A', 'T', 'T', 'A', 'A'], &['G', 'G', 'T', 'A', 'A'], &scores);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -367,6 +556,15 @@ mod tests {
 
 
 
+    #[test]
+    fn test_presets() {
+        for name in Scores::PRESETS.iter() {
+            assert!(Scores::preset(name).is_some(), "missing preset {}", name);
+        }
+        assert_eq!(Scores::preset("plasmid").unwrap().gap_ext, -0.5);
+        assert!(Scores::preset("pacbio").is_none());
+    }
+
     #[test]
     fn test_large_alignment() {
         let scores = Scores::default_scores();
@@ -381,4 +579,98 @@ mod tests {
 
         // assert_eq!(alignment.score, (size as f64)  * scores.match_score);
     }
+
+    #[test]
+    fn test_banded_alignment() {
+        let scores = Scores::default_scores();
+        let seq1: Vec<char> = "ACGTTACGTAACCGGT".chars().collect();
+        let seq2: Vec<char> = "ACGTACGTAACCGGTT".chars().collect();
+        let full = needleman_wunsch(&seq1, &seq2, &scores);
+        let banded = needleman_wunsch_banded(&seq1, &seq2, &scores, &[DiagonalBand { diagonal: 0, width: 2 }]);
+
+        assert_eq!(banded.score, full.score);
+        assert_eq!(banded.seq_one_aligned, full.seq_one_aligned);
+        assert_eq!(banded.seq_two_aligned, full.seq_two_aligned);
+    }
+
+    #[test]
+    fn test_overlap_alignment() {
+        let scores = Scores::default_scores();
+        let seq1: Vec<char> = "TTTTTGGCCAACGTAC".chars().collect();
+        let seq2: Vec<char> = "CCAAGGTACAAAAAAA".chars().collect();
+        let alignment = overlap_alignment(&seq1, &seq2, &scores);
+
+        assert_eq!((alignment.start_x, alignment.end_x), (7, 16));
+        assert_eq!((alignment.start_y, alignment.end_y), (0, 9));
+        assert_eq!(alignment.seq_one_aligned.iter().collect::<String>(), "CCAACGTAC");
+        assert_eq!(alignment.seq_two_aligned.iter().collect::<String>(), "CCAAGGTAC");
+        assert_eq!(alignment.score, 8.0 * scores.match_score + scores.mismatch_score);
+    }
+
+    #[test]
+    fn test_semi_global_global_matches_needleman() {
+        let scores = Scores { gap_start: -6.0, gap_end: -6.0, ..Scores::default_scores() };
+        let seq1: Vec<char> = "ACGTTACGTAACCGGT".chars().collect();
+        let seq2: Vec<char> = "GTACGTAACCGG".chars().collect();
+        let full = needleman_wunsch(&seq1, &seq2, &scores);
+        let semi = semi_global(&seq1, &seq2, &scores, &EndGaps::global());
+
+        assert_eq!(semi.score, full.score);
+        assert_eq!(semi.seq_one_aligned.len(), semi.seq_two_aligned.len());
+        assert_eq!((semi.start_x, semi.end_x, semi.start_y, semi.end_y), (0, 16, 0, 12));
+    }
+
+    #[test]
+    fn test_semi_global_contained() {
+        let scores = Scores::default_scores();
+        let seq1: Vec<char> = "CCAAGGTA".chars().collect();
+        let seq2: Vec<char> = "TTTTCCAAGGTATTTT".chars().collect();
+        let alignment = semi_global(&seq1, &seq2, &scores, &EndGaps::contained());
+
+        assert_eq!((alignment.start_x, alignment.end_x), (0, 8));
+        assert_eq!((alignment.start_y, alignment.end_y), (4, 12));
+        assert_eq!(alignment.seq_two_aligned.iter().collect::<String>(), "CCAAGGTA");
+        assert_eq!(alignment.score, 8.0 * scores.match_score);
+    }
+
+    #[test]
+    fn test_semi_global_end_gap_scores() {
+        // cheap end gaps: the overhangs are aligned against gaps at gap_start and gap_end a base
+        let scores = Scores { gap_start: -1.0, gap_end: -2.0, ..Scores::default_scores() };
+        let seq1: Vec<char> = "GGCCAAGGTAT".chars().collect();
+        let seq2: Vec<char> = "CCAAGGTA".chars().collect();
+        let alignment = semi_global(&seq1, &seq2, &scores, &EndGaps::global());
+
+        assert_eq!(alignment.seq_one_aligned.iter().collect::<String>(), "GGCCAAGGTAT");
+        assert_eq!(alignment.seq_two_aligned.iter().collect::<String>(), "--CCAAGGTA-");
+        assert_eq!((alignment.start_x, alignment.end_x, alignment.start_y, alignment.end_y), (0, 11, 0, 8));
+        assert_eq!(alignment.score, 8.0 * scores.match_score + 2.0 * scores.gap_start + scores.gap_end);
+
+        // with free ends on seq1 the overhangs drop out of the alignment
+        let free = EndGaps { seq1_start: true, seq1_end: true, ..EndGaps::global() };
+        let alignment = semi_global(&seq1, &seq2, &scores, &free);
+        assert_eq!(alignment.seq_one_aligned.iter().collect::<String>(), "CCAAGGTA");
+        assert_eq!((alignment.start_x, alignment.end_x), (2, 10));
+        assert_eq!(alignment.score, 8.0 * scores.match_score);
+    }
+
+    #[test]
+    fn test_semi_global_end_gaps_dearer_than_interior_gaps() {
+        // the trailing overhang can't sneak into the last column as interior gaps at gap_ext (a dear mismatch
+        // keeps the last A from being pushed onto the final T instead)
+        let scores = Scores { mismatch_score: -20.0, ..Scores::default_scores() };
+        assert!(scores.gap_end < scores.gap_ext);
+        let seq1: Vec<char> = "CCAAGGTATTTT".chars().collect();
+        let seq2: Vec<char> = "CCAAGGTA".chars().collect();
+        let alignment = semi_global(&seq1, &seq2, &scores, &EndGaps::global());
+
+        assert_eq!(alignment.score, 8.0 * scores.match_score + 4.0 * scores.gap_end);
+        assert_eq!(alignment.seq_one_aligned.iter().collect::<String>(), "CCAAGGTATTTT");
+        assert_eq!(alignment.seq_two_aligned.iter().collect::<String>(), "CCAAGGTA----");
+        assert_eq!((alignment.end_x, alignment.end_y), (12, 8));
+
+        let swapped = semi_global(&seq2, &seq1, &scores, &EndGaps::global());
+        assert_eq!(swapped.score, alignment.score);
+        assert_eq!(swapped.seq_two_aligned.iter().collect::<String>(), "CCAAGGTATTTT");
+    }
 }
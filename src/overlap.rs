@@ -0,0 +1,112 @@
+use std::cmp::min;
+use std::mem::size_of;
+
+use dedup::alignment_identity;
+use needleman;
+use needleman::{Direction, Scores};
+
+/// A suffix of a linear contig that repeats its prefix, left over from the assembler running past the
+/// origin of the circle. Trimming the suffix circularizes the contig.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndOverlap {
+    /// the overlapping suffix runs from here to the end of the contig
+    pub suffix_start: usize,
+    /// the matching prefix runs from 0 to here
+    pub prefix_end: usize,
+    pub score: f64,
+    pub identity: f64,
+}
+
+impl EndOverlap {
+    /// the number of bases trimming the suffix removes
+    pub fn trimmed_length(&self, reference_length: usize) -> usize {
+        reference_length - self.suffix_start
+    }
+}
+
+/// the longest stretch at each end we can compare in max_memory bytes: the overlap can't be longer than
+/// half the contig, and the overlap alignment fills a full window x window matrix
+pub fn overlap_window(reference_length: usize, max_memory: usize) -> usize {
+    let cells = max_memory / (size_of::<f64>() + size_of::<Direction>());
+    min(reference_length / 2, ((cells as f64).sqrt() as usize).saturating_sub(1))
+}
+
+/// Align the end of the contig against its start and return the suffix-prefix overlap if it's at least
+/// min_length bases long (on the suffix side) with an identity above min_identity.
+pub fn find_end_overlap(reference: &[char], scores: &Scores, min_length: usize, min_identity: f64, max_memory: usize) -> Option<EndOverlap> {
+    let window = overlap_window(reference.len(), max_memory);
+    if window < reference.len() / 2 {
+        eprintln!("Warning: only looking for end overlaps up to {} bases long to stay in the memory budget", window);
+    }
+    if window < min_length {
+        return None;
+    }
+    let suffix: Vec<char> = reference[reference.len() - window..].to_vec();
+    let prefix: Vec<char> = reference[..window].to_vec();
+    let alignment = needleman::overlap_alignment(&suffix, &prefix, scores);
+
+    let overlap = EndOverlap {
+        suffix_start: reference.len() - window + alignment.start_x,
+        prefix_end: alignment.end_y,
+        score: alignment.score,
+        identity: alignment_identity(&alignment),
+    };
+    let length = window - alignment.start_x;
+    eprintln!("Best end overlap is {} bases at identity {:.3} (score {})", length, overlap.identity, overlap.score);
+    if length >= min_length && alignment.score > 0.0 && overlap.identity > min_identity {
+        Some(overlap)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::{random_sequence, test_scores};
+
+    #[test]
+    fn test_finds_overlap_with_an_error() {
+        // the assembler ran 150 bases past the origin, and got one of them wrong
+        let circle = random_sequence(600, 3);
+        let mut contig = circle.clone();
+        contig.extend_from_slice(&circle[..150]);
+        contig[700] = if contig[700] == 'A' { 'C' } else { 'A' };
+
+        let overlap = find_end_overlap(&contig, &test_scores(), 100, 0.9, 1024 * 1024 * 1024).unwrap();
+        assert_eq!(overlap.suffix_start, 600);
+        assert_eq!(overlap.prefix_end, 150);
+        assert_eq!(overlap.trimmed_length(contig.len()), 150);
+        assert_eq!(overlap.score, 146.0);
+        assert!((overlap.identity - 149.0 / 150.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_overhanging_suffix_pays_end_gaps() {
+        // the end of the suffix hangs past the whole prefix, which has to cost gap_end a base even when
+        // interior gaps are cheaper
+        let scores = Scores { gap_ext: -4.0, ..test_scores() };
+        let prefix = random_sequence(40, 7);
+        let mut suffix: Vec<char> = "GGGG".chars().collect();
+        suffix.extend_from_slice(&prefix);
+        suffix.extend_from_slice(&['T', 'T', 'T', 'T']);
+        let alignment = needleman::overlap_alignment(&suffix, &prefix, &scores);
+
+        assert_eq!((alignment.start_x, alignment.end_x), (4, 48));
+        assert_eq!((alignment.start_y, alignment.end_y), (0, 40));
+        assert_eq!(alignment.score, 40.0 * scores.match_score + 4.0 * scores.gap_end);
+    }
+
+    #[test]
+    fn test_no_overlap() {
+        let contig = random_sequence(800, 5);
+        assert_eq!(find_end_overlap(&contig, &test_scores(), 100, 0.9, 1024 * 1024 * 1024), None);
+    }
+
+    #[test]
+    fn test_window_fits_the_budget() {
+        assert_eq!(overlap_window(1000, 1024 * 1024 * 1024), 500);
+        let window = overlap_window(1_000_000, 9 * 1024 * 1024);
+        assert_eq!(window, 1023);
+    }
+}
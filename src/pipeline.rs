@@ -0,0 +1,198 @@
+use std::io::{Read, Write};
+
+use bio::io::fasta;
+
+use dedup;
+use dedup::Deduplicator;
+use error::DupScoopError;
+use genbank;
+use genbank::GenBankRecord;
+use header::{HeaderTags, NameTemplate};
+use liftover::Liftover;
+use report::{EventKind, ReportEvent};
+use sequence_io;
+use sequence_io::SequenceFormat;
+
+/// What deduplicating one input file produced: the report rows and the liftover of every record
+pub struct FileResult {
+    pub events: Vec<ReportEvent>,
+    pub liftovers: Vec<(String, Liftover)>,
+}
+
+impl FileResult {
+    /// whether any record had a duplication or inverted repeat
+    pub fn found_duplications(&self) -> bool {
+        self.events.iter().any(|event| event.event == EventKind::Duplication || event.event == EventKind::InvertedRepeat)
+    }
+}
+
+/// Deduplicate (or just scan) every record of a FASTA or GenBank file and write the results to the output
+/// in the same format, lifting GenBank features over to the new coordinates. Each output record is tagged
+/// with what was removed, and renamed if there's a name template. A detect-only scan copies the input to
+/// the output byte for byte.
+pub fn deduplicate_file(deduplicator: &Deduplicator, input_file: &str, output_file: &str, detect_only: bool, name_template: Option<&NameTemplate>) -> Result<FileResult, DupScoopError> {
+    let mut report_events = Vec::new();
+    let mut liftovers = Vec::new();
+    let mut input = sequence_io::open_input(input_file)?;
+    let format = SequenceFormat::detect(input_file, &mut input)?;
+    let mut contents = Vec::new();
+    input.read_to_end(&mut contents).map_err(|error| DupScoopError::reading(input_file, error))?;
+
+    // only create the output once the input has parsed, so a bad input doesn't leave an empty file behind
+    let mut output = match format {
+        SequenceFormat::Fasta => {
+            let records = sequence_io::read_fasta_input(&contents[..], input_file)?;
+            let mut output = sequence_io::create_output(output_file)?;
+            let mut writer = fasta::Writer::new(&mut output);
+            for (index, record) in records.iter().enumerate() {
+                eprintln!("Processing record {} ({} bases)", record.id, record.sequence.len());
+                let (resulting_reference, mut events, liftover) = process_record(deduplicator, &record.id, &record.sequence, detect_only)?;
+                if !detect_only {
+                    let tags = record_tags(&record.id, &events)?;
+                    let name = output_name(name_template, &record.id, index, &tags);
+                    sequence_io::write_fasta_record(&mut writer, &name, Some(&tags.annotate(record.description.as_deref())), &resulting_reference)?;
+                }
+                report_events.append(&mut events);
+                liftovers.push((record.id.clone(), liftover));
+            }
+            writer.flush()?;
+            drop(writer);
+            output
+        }
+        SequenceFormat::GenBank => {
+            let records = genbank::read_genbank_input(&contents[..], input_file)?;
+            let mut output = sequence_io::create_output(output_file)?;
+            for (index, record) in records.iter().enumerate() {
+                eprintln!("Processing record {} ({} bases)", record.name, record.sequence.len());
+                let (resulting_reference, mut events, liftover) = process_record(deduplicator, &record.name, &record.sequence, detect_only)?;
+                if !detect_only {
+                    let tags = record_tags(&record.name, &events)?;
+                    let name = output_name(name_template, &record.name, index, &tags);
+                    let (features, dropped, flagged) = genbank::lift_features(&record.features, &liftover, resulting_reference.len());
+
+                    // list the feature changes ahead of the record's summary row
+                    let summary = events.pop();
+                    for feature in &dropped {
+                        events.push(ReportEvent::feature(&record.name, EventKind::FeatureDropped, feature, record.sequence.len(), resulting_reference.len()));
+                    }
+                    for feature in &flagged {
+                        events.push(ReportEvent::feature(&record.name, EventKind::FeatureFlagged, feature, record.sequence.len(), resulting_reference.len()));
+                    }
+                    events.extend(summary);
+
+                    genbank::write_genbank_record(&mut output, &GenBankRecord {
+                        locus_line: genbank::rename_locus(&record.locus_line, &record.name, &name),
+                        name,
+                        header_lines: genbank::annotate_header(&record.header_lines, &tags),
+                        features,
+                        trailer_lines: record.trailer_lines.clone(),
+                        sequence: resulting_reference,
+                    })?;
+                }
+                report_events.append(&mut events);
+                liftovers.push((record.name.clone(), liftover));
+            }
+            output
+        }
+    };
+    if detect_only {
+        output.write_all(&contents)?;
+    }
+    output.finish()?;
+    Ok(FileResult { events: report_events, liftovers })
+}
+
+fn record_tags(record_id: &str, events: &[ReportEvent]) -> Result<HeaderTags, DupScoopError> {
+    HeaderTags::from_events(events).ok_or_else(|| DupScoopError::AlignmentFailed(format!("there's no summary of record {}", record_id)))
+}
+
+/// the record's name in the output: from the template if there is one, otherwise its input name
+fn output_name(name_template: Option<&NameTemplate>, record_id: &str, index: usize, tags: &HeaderTags) -> String {
+    match name_template {
+        Some(template) => template.render(record_id, index + 1, tags),
+        None => record_id.to_string(),
+    }
+}
+
+/// Deduplicate (or just scan) one record; a record too long for the memory budget is an error. The
+/// characters are validated as they were read, so anything outside ASCII is an invalid base rather than
+/// being narrowed into one.
+pub fn process_record(deduplicator: &Deduplicator, record_id: &str, sequence: &[char], detect_only: bool) -> Result<(Vec<char>, Vec<ReportEvent>, Liftover), DupScoopError> {
+    let result = dedup::deduplicate_reference(record_id, sequence, deduplicator, detect_only)?;
+    Ok((result.sequence.iter().map(|&base| base as char).collect(), result.events, result.liftover))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use needleman::Scores;
+    use error::EXIT_TOO_LONG;
+    use validate::InvalidBaseMode;
+
+    #[test]
+    fn test_detect_only_copies_the_input() {
+        let directory = std::env::temp_dir().join(format!("dupscoop_pipeline_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = |name: &str| directory.join(name).to_string_lossy().to_string();
+        // soft-masked, an odd line width, a masked base and a linear tag: all of it has to come through as-is
+        let input = ">contig_1 len=40 circular=false\r\nacgtACGGTCAGTTCA\r\nTTGACCGTAXCA\r\nggtaccTTGA\r\n>contig_2\nACGTTGCA\n";
+        fs::write(path("input.fa"), input).unwrap();
+
+        let deduplicator = Deduplicator { invalid_bases: InvalidBaseMode::Mask, ..Deduplicator::new(Scores::default_scores(), 1000, 0.9, 10) };
+        let template = NameTemplate::parse("{id}_dedup").unwrap();
+        let result = deduplicate_file(&deduplicator, &path("input.fa"), &path("output.fa"), true, Some(&template)).unwrap();
+        assert_eq!(result.liftovers.len(), 2);
+        assert_eq!(fs::read(path("output.fa")).unwrap(), input.as_bytes());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_non_ascii_characters_are_invalid_bases() {
+        // U+0141 would narrow to 0x41, an A
+        let sequence: Vec<char> = "ACGTŁACGT".chars().collect();
+        match process_record(&Deduplicator::new(Scores::default_scores(), 1000, 0.9, 10), "contig_1", &sequence, false) {
+            Err(DupScoopError::InvalidBases { positions, .. }) => assert_eq!(positions, vec![(5, 'Ł')]),
+            other => panic!("expected an invalid base error, got {:?}", other.map(|result| result.0)),
+        }
+
+        let masking = Deduplicator { invalid_bases: InvalidBaseMode::Mask, ..Deduplicator::new(Scores::default_scores(), 1000, 0.9, 10) };
+        let (masked, _, _) = process_record(&masking, "contig_1", &sequence, false).unwrap();
+        assert_eq!(masked.iter().collect::<String>(), "ACGTNACGT");
+    }
+
+    #[test]
+    fn test_genbank_origin_is_validated_like_fasta() {
+        let genbank = "LOCUS       pTest                     16 bp    DNA     circular SYN 01-JAN-2020
+ORIGIN
+        1 acgtAC*Gtt ca-g
+//
+";
+        let records = ::genbank::read_genbank(genbank.as_bytes()).unwrap();
+        assert_eq!(records[0].sequence.iter().collect::<String>(), "acgtAC*Gttca-g");
+        match process_record(&Deduplicator::new(Scores::default_scores(), 1000, 0.9, 10), "pTest", &records[0].sequence, false) {
+            Err(DupScoopError::InvalidBases { positions, .. }) => assert_eq!(positions, vec![(7, '*'), (13, '-')]),
+            other => panic!("expected an invalid base error, got {:?}", other.map(|result| result.0)),
+        }
+
+        // soft-masked lowercase comes through like it does from FASTA
+        let masking = Deduplicator { invalid_bases: InvalidBaseMode::Mask, soft_mask: true, ..Deduplicator::new(Scores::default_scores(), 1000, 0.9, 10) };
+        let (masked, _, _) = process_record(&masking, "pTest", &records[0].sequence, false).unwrap();
+        assert_eq!(masked.iter().collect::<String>(), "acgtACNGttcaNg");
+    }
+
+    #[test]
+    fn test_too_long_for_the_budget() {
+        let directory = std::env::temp_dir().join(format!("dupscoop_pipeline_long_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = |name: &str| directory.join(name).to_string_lossy().to_string();
+        fs::write(path("input.fa"), ">contig_1\nACGTACGGTCAGTTCATTGACCGTAGCA\n").unwrap();
+
+        let deduplicator = Deduplicator { max_memory: 64, ..Deduplicator::new(Scores::default_scores(), 1000, 0.9, 10) };
+        match deduplicate_file(&deduplicator, &path("input.fa"), &path("output.fa"), false, None) {
+            Err(error @ DupScoopError::SequenceTooLong { .. }) => assert_eq!(error.exit_code(), EXIT_TOO_LONG),
+            other => panic!("expected a too-long error, got {:?}", other.map(|result| result.events)),
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
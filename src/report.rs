@@ -0,0 +1,124 @@
+use std::fs::File;
+use std::io;
+
+use csv;
+use serde::Serialize;
+use serde_json;
+
+/// The kind of step a report row describes
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Normalized,
+    Duplication,
+    InvertedRepeat,
+    EndOverlap,
+    FeatureDropped,
+    FeatureFlagged,
+    Summary,
+}
+
+/// A single row of the dedup report: either one iteration of the duplication removal loop, or the
+/// final summary for a record. Fields that don't apply to a row are left empty (TSV) or null (JSON).
+#[derive(Serialize, Debug, Clone)]
+pub struct ReportEvent {
+    pub record: String,
+    pub event: EventKind,
+    pub iteration: Option<usize>,
+    pub start_x: Option<usize>,
+    pub end_x: Option<usize>,
+    /// the second copy, with end_y < start_y if it wraps the origin; for inverted repeats this is the
+    /// reverse-complement copy on the forward strand
+    pub start_y: Option<usize>,
+    pub end_y: Option<usize>,
+    pub score: Option<f64>,
+    pub identity: Option<f64>,
+    /// the offset handed to rotate_reference before the duplicate was removed
+    pub rotation: Option<usize>,
+    /// the removed interval [removed_start, removed_end), in coordinates of the rotated sequence after any
+    /// earlier rows of the same pass were cut out, so it lies within length_before
+    pub removed_start: Option<usize>,
+    pub removed_end: Option<usize>,
+    pub length_before: usize,
+    pub length_after: usize,
+    /// free text, such as the description of a dropped feature
+    pub detail: Option<String>,
+}
+
+impl ReportEvent {
+    pub fn summary(record: &str, iterations: usize, length_before: usize, length_after: usize) -> ReportEvent {
+        ReportEvent {
+            record: record.to_string(),
+            event: EventKind::Summary,
+            iteration: Some(iterations),
+            start_x: None,
+            end_x: None,
+            start_y: None,
+            end_y: None,
+            score: None,
+            identity: None,
+            rotation: None,
+            removed_start: None,
+            removed_end: None,
+            length_before,
+            length_after,
+            detail: None,
+        }
+    }
+
+    /// a row noting what happened to an annotated feature during deduplication
+    pub fn feature(record: &str, event: EventKind, feature: &str, length_before: usize, length_after: usize) -> ReportEvent {
+        ReportEvent {
+            event,
+            iteration: None,
+            detail: Some(feature.to_string()),
+            ..ReportEvent::summary(record, 0, length_before, length_after)
+        }
+    }
+}
+
+/// write the report (or any other table of rows) as JSON if the file name ends in .json, and as a
+/// tab-separated table otherwise
+pub fn write_report<T: Serialize>(report_file: &str, events: &[T]) -> Result<(), io::Error> {
+    let file = File::create(report_file)?;
+    if report_file.to_lowercase().ends_with(".json") {
+        serde_json::to_writer_pretty(file, events)?;
+        Ok(())
+    } else {
+        write_tsv(file, events)
+    }
+}
+
+pub fn write_tsv<W: io::Write, T: Serialize>(writer: W, events: &[T]) -> Result<(), io::Error> {
+    let mut tsv = csv::WriterBuilder::new().delimiter(b'\t').from_writer(writer);
+    for event in events {
+        tsv.serialize(event)?;
+    }
+    tsv.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_tsv() {
+        let events = vec![ReportEvent::summary("contig_1", 2, 5000, 4000)];
+        let mut buffer = Vec::new();
+        write_tsv(&mut buffer, &events).unwrap();
+
+        let output = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("record\tevent\titeration\tstart_x"));
+        assert_eq!(lines[1], "contig_1\tsummary\t2\t\t\t\t\t\t\t\t\t\t5000\t4000\t");
+    }
+
+    #[test]
+    fn test_summary_json() {
+        let events = vec![ReportEvent::summary("contig_1", 0, 5000, 5000)];
+        let json = serde_json::to_string(&events).unwrap();
+        assert!(json.contains("\"event\":\"summary\""));
+        assert!(json.contains("\"score\":null"));
+    }
+}
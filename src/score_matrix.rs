@@ -1,51 +1,170 @@
-use std::collections::HashMap;
+use std::fs;
 
+use error::DupScoopError;
+
+/// table entries are indexed by ASCII code, so anything outside it has no score
+const TABLE_SIZE: usize = 128;
+
+/// the nucleotides each IUPAC code can stand for
+const IUPAC_CODES: [(char, &str); 16] = [
+    ('A', "A"), ('C', "C"), ('G', "G"), ('T', "T"), ('U', "T"),
+    ('R', "AG"), ('Y', "CT"), ('K', "GT"), ('M', "AC"), ('S', "CG"), ('W', "AT"),
+    ('B', "CGT"), ('D', "AGT"), ('H', "ACT"), ('V', "ACG"), ('N', "ACGT"),
+];
+
+/// A substitution matrix: a score for every pair of bases it knows about. Lookups ignore case, and pairs
+/// the matrix doesn't cover have no score.
 #[allow(dead_code)]
 pub struct PairedScores {
-    counts: HashMap<String, f64>,
-    name: &'static str,
+    scores: Vec<Option<f64>>,
+    name: String,
 }
 
 #[allow(dead_code)]
 impl PairedScores {
+    /// The IUPAC-aware table: two codes match when they could stand for the same nucleotide (so R matches
+    /// A or G but not C), and mismatch otherwise. N says nothing about the base, so it scores 0 against
+    /// everything, including another N; otherwise a run of Ns would look like a perfect repeat. The table
+    /// is symmetric.
     pub fn default_scores(match_score: &f64, mismatch_score: &f64) -> PairedScores {
-        let mut my_counts: HashMap<String, f64> = HashMap::new();
-
-        let bases = vec!['A', 'C', 'G', 'T', 'U', 'R', 'Y', 'K', 'M', 'S', 'W', 'B', 'D', 'H', 'V', 'N'];
-        for base1_index in 0..bases.len() {
-            for base2_index in 0..bases.len() {
-                let key = bases.get(base1_index).unwrap().to_string() + "-" + &bases.get(base2_index).unwrap().to_string();
-                my_counts.insert(key, match (bases.get(base1_index).unwrap(),bases.get(base2_index).unwrap()) {
-                    (_x, _y) if _x == _y => {*match_score} // they match regardless of what they are
-                    ('R','A') | ('R','G') => {*match_score} // they match regardless of what they are
-                    ('Y','C') | ('Y','T') => {*match_score} // they match regardless of what they are
-                    ('M','A') | ('M','C') => {*match_score} // they match regardless of what they are
-                    ('S','C') | ('S','G') => {*match_score} // they match regardless of what they are
-                    ('W','A') | ('W','T') => {*match_score} // they match regardless of what they are
-                    ('B','C') | ('B','G') | ('B','T') => {*match_score} // they match regardless of what they are
-                    ('D','A') | ('D','G') | ('D','T') => {*match_score} // they match regardless of what they are
-                    ('H','A') | ('H','C') | ('H','T') => {*match_score} // they match regardless of what they are
-                    ('V','A') | ('V','C') | ('V','G') => {*match_score} // they match regardless of what they are
-                    ('N',_) => {*match_score} // they match regardless of what they are
-                    (_,_) => {*mismatch_score}
-                });
-                let key2 = bases.get(base1_index).unwrap().to_string() + "-" + &bases.get(base2_index).unwrap().to_string();
-                my_counts.insert(key2, match (bases.get(base1_index).unwrap(),bases.get(base2_index).unwrap()) {
-                    (_x, _y) if _x == _y => {*match_score} // they match regardless of what they are
-                    ('R','A') | ('R','G') => {*match_score} // they match regardless of what they are
-                    ('Y','C') | ('Y','T') => {*match_score} // they match regardless of what they are
-                    ('M','A') | ('M','C') => {*match_score} // they match regardless of what they are
-                    ('S','C') | ('S','G') => {*match_score} // they match regardless of what they are
-                    ('W','A') | ('W','T') => {*match_score} // they match regardless of what they are
-                    ('B','C') | ('B','G') | ('B','T') => {*match_score} // they match regardless of what they are
-                    ('D','A') | ('D','G') | ('D','T') => {*match_score} // they match regardless of what they are
-                    ('H','A') | ('H','C') | ('H','T') => {*match_score} // they match regardless of what they are
-                    ('V','A') | ('V','C') | ('V','G') => {*match_score} // they match regardless of what they are
-                    ('N',_) => {*match_score} // they match regardless of what they are
-                    (_,_) => {*mismatch_score}
-                });
+        let mut table = PairedScores::empty("IUPAC");
+        for (base1, possible1) in IUPAC_CODES.iter() {
+            for (base2, possible2) in IUPAC_CODES.iter() {
+                let overlap = possible1.chars().any(|nucleotide| possible2.contains(nucleotide));
+                let score = if *base1 == 'N' || *base2 == 'N' { 0.0 } else if overlap { *match_score } else { *mismatch_score };
+                table.set(*base1, *base2, score);
             }
         }
-        PairedScores{counts: my_counts,name: "Default"}
+        table
+    }
+
+    /// Load a matrix in the NCBI / EMBOSS text format (like NUC.4.4): '#' comment lines, a header line of
+    /// column bases, then one line per row base starting with the base and followed by its scores.
+    pub fn from_file(path: &str) -> Result<PairedScores, DupScoopError> {
+        let contents = fs::read_to_string(path)?;
+        PairedScores::parse(path, &contents)
+    }
+
+    /// parse the contents of a matrix file, naming it for error messages
+    pub fn parse(name: &str, contents: &str) -> Result<PairedScores, DupScoopError> {
+        let invalid = |message: String| DupScoopError::InvalidMatrix { name: name.to_string(), message };
+        let mut lines = contents.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        let columns = match lines.next() {
+            Some(header) => header.split_whitespace().map(|column| single_base(column).ok_or_else(|| invalid(format!("{} isn't a single base", column)))).collect::<Result<Vec<char>, DupScoopError>>()?,
+            None => return Err(invalid("there's no header line".to_string())),
+        };
+
+        let mut table = PairedScores::empty(name);
+        let mut rows = 0;
+        for line in lines {
+            rows += 1;
+            let mut fields = line.split_whitespace();
+            let row_label = fields.next().unwrap();
+            let row = single_base(row_label).ok_or_else(|| invalid(format!("{} isn't a single base", row_label)))?;
+            let values: Vec<&str> = fields.collect();
+            if values.len() != columns.len() {
+                return Err(invalid(format!("row {} has {} scores for {} columns", row, values.len(), columns.len())));
+            }
+            for (column, value) in columns.iter().zip(values) {
+                let score = value.parse::<f64>().map_err(|_| invalid(format!("{} isn't a score", value)))?;
+                table.set(row, *column, score);
+            }
+        }
+        if rows == 0 {
+            return Err(invalid("there are no rows of scores".to_string()));
+        }
+        Ok(table)
+    }
+
+    fn empty(name: &str) -> PairedScores {
+        PairedScores { scores: vec![None; TABLE_SIZE * TABLE_SIZE], name: name.to_string() }
+    }
+
+    fn set(&mut self, base1: char, base2: char, score: f64) {
+        let index = PairedScores::index(base1, base2).expect("matrix bases are ASCII");
+        self.scores[index] = Some(score);
+    }
+
+    #[inline]
+    fn index(base1: char, base2: char) -> Option<usize> {
+        let (row, column) = (base1.to_ascii_uppercase() as usize, base2.to_ascii_uppercase() as usize);
+        if row < TABLE_SIZE && column < TABLE_SIZE { Some(row * TABLE_SIZE + column) } else { None }
+    }
+
+    /// the score for aligning base1 against base2, if the matrix covers the pair
+    #[inline]
+    pub fn score(&self, base1: char, base2: char) -> Option<f64> {
+        PairedScores::index(base1, base2).and_then(|index| self.scores[index])
     }
-}
\ No newline at end of file
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// every score in the matrix
+    pub fn values(&self) -> impl Iterator<Item = f64> + '_ {
+        self.scores.iter().filter_map(|score| *score)
+    }
+
+    /// the highest and lowest scores in the matrix
+    pub fn score_range(&self) -> (f64, f64) {
+        self.values().fold((f64::MIN, f64::MAX), |(high, low), score| (high.max(score), low.min(score)))
+    }
+}
+
+fn single_base(field: &str) -> Option<char> {
+    let mut characters = field.chars();
+    match (characters.next(), characters.next()) {
+        (Some(base), None) if base.is_ascii() => Some(base),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iupac_table() {
+        let table = PairedScores::default_scores(&5.0, &-4.0);
+        assert_eq!(table.score('A', 'A'), Some(5.0));
+        assert_eq!(table.score('A', 'C'), Some(-4.0));
+        assert_eq!(table.score('R', 'a'), Some(5.0));
+        assert_eq!(table.score('A', 'R'), Some(5.0));
+        assert_eq!(table.score('R', 'Y'), Some(-4.0));
+        assert_eq!(table.score('S', 'K'), Some(5.0));
+        assert_eq!(table.score('N', 'T'), Some(0.0));
+        assert_eq!(table.score('N', 'n'), Some(0.0));
+        assert_eq!(table.score('U', 'T'), Some(5.0));
+        assert_eq!(table.score('A', '*'), None);
+        for (base1, _) in IUPAC_CODES.iter() {
+            for (base2, _) in IUPAC_CODES.iter() {
+                assert_eq!(table.score(*base1, *base2), table.score(*base2, *base1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_ncbi_matrix() {
+        let contents = "#\n# a cut down NUC.4.4\n#\n    A   T   G   C   N\nA   5  -4  -4  -4  -2\nT  -4   5  -4  -4  -2\nG  -4  -4   5  -4  -2\nC  -4  -4  -4   5  -2\nN  -2  -2  -2  -2  -1\n";
+        let table = PairedScores::parse("NUC.4.4", contents).unwrap();
+        assert_eq!(table.name(), "NUC.4.4");
+        assert_eq!(table.score('A', 'A'), Some(5.0));
+        assert_eq!(table.score('g', 'T'), Some(-4.0));
+        assert_eq!(table.score('N', 'C'), Some(-2.0));
+        assert_eq!(table.score('R', 'A'), None);
+        assert_eq!(table.score_range(), (5.0, -4.0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(PairedScores::parse("bad", "# nothing\n").is_err());
+        assert!(PairedScores::parse("bad", "  A C\n").is_err());
+        assert!(PairedScores::parse("bad", "  A C\nA 1\n").is_err());
+        assert!(PairedScores::parse("bad", "  A C\nA 1 x\n").is_err());
+        assert!(PairedScores::parse("bad", "  A CG\nA 1 2\n").is_err());
+    }
+}
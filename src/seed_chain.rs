@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use kmer_orientation::ReferenceKmers;
+use mymatrix::DiagonalBand;
+use smith_waterman_no_diag::MatrixWindow;
+
+/// k-mers seen more often than this are low-complexity sequence (poly-A, simple repeats) and would swamp
+/// the chains with every-against-every seeds, so they're left out of the index
+const MAX_KMER_OCCURRENCES: usize = 32;
+
+/// Exact k-mer matches that sit on the same (or a nearby) off-diagonal of the self-alignment matrix. The
+/// diagonal is the distance from the first copy to the second going forward around the plasmid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedChain {
+    pub x_start: usize,
+    pub x_end: usize,
+    pub min_diagonal: usize,
+    pub max_diagonal: usize,
+    pub seeds: usize,
+}
+
+impl SeedChain {
+    /// the region of the reference vs. doubled reference matrix to run the full alignment in
+    pub fn window(&self, reference_length: usize, flank: usize) -> MatrixWindow {
+        MatrixWindow {
+            x_start: self.x_start.saturating_sub(flank),
+            x_end: (self.x_end + flank).min(reference_length),
+            y_start: (self.x_start + self.min_diagonal).saturating_sub(flank),
+            y_end: (self.x_end + self.max_diagonal + flank).min(2 * reference_length),
+        }
+    }
+
+    /// the diagonals of the reference vs. doubled reference matrix the chain lies on, widened by flank
+    pub fn band(&self, flank: usize) -> DiagonalBand {
+        DiagonalBand {
+            diagonal: ((self.min_diagonal + self.max_diagonal) / 2) as isize,
+            width: (self.max_diagonal - self.min_diagonal).div_ceil(2) + flank,
+        }
+    }
+}
+
+/// Index every k-mer of the circular plasmid and chain the repeated ones into candidate duplications.
+/// Seeds closer than diag_dist to the trivial diagonal are ignored, seeds on the same diagonal chain when
+/// they're within max_gap of each other, and chains whose diagonals differ by less than max_gap (an indel)
+/// are merged. Only chains spanning at least min_span bases are returned, longest first.
+pub fn find_seed_chains(reference: &[char], kmer_size: usize, diag_dist: usize, max_gap: usize, min_span: usize) -> Vec<SeedChain> {
+    let reference_length = reference.len();
+    if reference_length <= kmer_size {
+        return Vec::new();
+    }
+    let circular: String = reference.iter().chain(reference[..kmer_size - 1].iter()).collect();
+
+    // every k-mer start gives one window, so if there are as many distinct k-mers as positions nothing repeats
+    if ReferenceKmers::sequence_to_kmers(&circular, &kmer_size).len() == reference_length {
+        return Vec::new();
+    }
+
+    let circular_chars: Vec<char> = circular.chars().collect();
+    let mut index: HashMap<&[char], Vec<usize>> = HashMap::new();
+    // runs of N aren't evidence of a repeat, so k-mers with an N in them don't seed anything
+    for (position, kmer) in circular_chars.windows(kmer_size).enumerate().filter(|(_, kmer)| !kmer.contains(&'N')) {
+        index.entry(kmer).or_default().push(position);
+    }
+
+    let mut diagonals: HashMap<usize, Vec<usize>> = HashMap::new();
+    for positions in index.values().filter(|positions| positions.len() > 1 && positions.len() <= MAX_KMER_OCCURRENCES) {
+        for first in positions {
+            for second in positions {
+                let diagonal = (second + reference_length - first) % reference_length;
+                if diagonal >= diag_dist && reference_length - diagonal >= diag_dist {
+                    diagonals.entry(diagonal).or_default().push(*first);
+                }
+            }
+        }
+    }
+
+    let mut chains = Vec::new();
+    for (diagonal, mut starts) in diagonals {
+        starts.sort_unstable();
+        let mut chain = SeedChain { x_start: starts[0], x_end: starts[0] + kmer_size, min_diagonal: diagonal, max_diagonal: diagonal, seeds: 0 };
+        for start in starts {
+            if start > chain.x_end + max_gap {
+                chains.push(chain.clone());
+                chain = SeedChain { x_start: start, x_end: start, min_diagonal: diagonal, max_diagonal: diagonal, seeds: 0 };
+            }
+            chain.x_end = chain.x_end.max(start + kmer_size);
+            chain.seeds += 1;
+        }
+        chains.push(chain);
+    }
+
+    let mut chains = merge_chains(chains, max_gap);
+    chains.retain(|chain| chain.x_end - chain.x_start >= min_span);
+    chains.sort_by(|one, two| (two.x_end - two.x_start).cmp(&(one.x_end - one.x_start)).then(one.x_start.cmp(&two.x_start)));
+    chains
+}
+
+/// merge chains that overlap on the x axis and whose diagonals are close enough to be joined by an indel
+fn merge_chains(mut chains: Vec<SeedChain>, max_gap: usize) -> Vec<SeedChain> {
+    chains.sort_by_key(|chain| (chain.min_diagonal, chain.x_start));
+    let mut merged: Vec<SeedChain> = Vec::new();
+    for chain in chains {
+        let partner = merged.iter_mut().find(|existing| {
+            chain.min_diagonal <= existing.max_diagonal + max_gap &&
+                chain.x_start <= existing.x_end + max_gap &&
+                existing.x_start <= chain.x_end + max_gap
+        });
+        match partner {
+            Some(existing) => {
+                existing.x_start = existing.x_start.min(chain.x_start);
+                existing.x_end = existing.x_end.max(chain.x_end);
+                existing.max_diagonal = existing.max_diagonal.max(chain.max_diagonal);
+                existing.seeds += chain.seeds;
+            }
+            None => merged.push(chain),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::random_sequence;
+
+    #[test]
+    fn test_no_repeats() {
+        let reference = random_sequence(2000, 3);
+        assert!(find_seed_chains(&reference, 15, 10, 100, 100).is_empty());
+    }
+
+    #[test]
+    fn test_repeat_with_mismatch_is_one_chain_per_copy() {
+        let repeat = random_sequence(300, 5);
+        let mut reference = random_sequence(400, 7);
+        reference.extend_from_slice(&repeat);
+        reference.extend(random_sequence(500, 9));
+        let mut mutated = repeat.clone();
+        mutated[150] = if mutated[150] == 'A' { 'C' } else { 'A' };
+        reference.extend(mutated);
+        reference.extend(random_sequence(200, 11));
+
+        let chains = find_seed_chains(&reference, 15, 10, 100, 200);
+        assert_eq!(chains.len(), 2);
+
+        // the copy at 400 sees the one at 1200 going forward, and the other way round wraps the origin
+        let forward = chains.iter().find(|chain| chain.x_start == 400).unwrap();
+        assert_eq!((forward.x_end, forward.min_diagonal, forward.max_diagonal), (700, 800, 800));
+        let window = forward.window(reference.len(), 50);
+        assert_eq!(window, MatrixWindow { x_start: 350, x_end: 750, y_start: 1150, y_end: 1550 });
+        assert_eq!(forward.band(50), DiagonalBand { diagonal: 800, width: 50 });
+        let backward = chains.iter().find(|chain| chain.x_start == 1200).unwrap();
+        assert_eq!(backward.min_diagonal, reference.len() - 800);
+    }
+}
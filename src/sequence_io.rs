@@ -0,0 +1,246 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+use bio::io::fasta;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use bgzf::BgzfWriter;
+use error::DupScoopError;
+use genbank;
+
+/// the file name that stands for stdin when reading and stdout when writing
+pub const STDIO: &str = "-";
+/// the first two bytes of every gzip (and so every BGZF) file
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The file formats we can read plasmids from and write them back out to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequenceFormat {
+    Fasta,
+    GenBank,
+}
+
+impl SequenceFormat {
+    /// guess the format from a file name, ignoring a .gz or .bgz extension and defaulting to FASTA
+    pub fn from_path(path: &str) -> SequenceFormat {
+        let path = path.to_lowercase();
+        let path = path.trim_end_matches(".gz").trim_end_matches(".bgz");
+        if path.ends_with(".gb") || path.ends_with(".gbk") || path.ends_with(".genbank") {
+            SequenceFormat::GenBank
+        } else {
+            SequenceFormat::Fasta
+        }
+    }
+
+    /// Tell the format from the first line of an (already decompressed) input, which is all we have to go on
+    /// for stdin, falling back on the file name if the start doesn't look like either format
+    pub fn detect(path: &str, reader: &mut dyn BufRead) -> Result<SequenceFormat, io::Error> {
+        let start = reader.fill_buf()?;
+        let first = start.iter().position(|byte| !byte.is_ascii_whitespace()).map(|index| &start[index..]).unwrap_or(&[]);
+        if first.starts_with(b">") {
+            Ok(SequenceFormat::Fasta)
+        } else if first.starts_with(b"LOCUS") {
+            Ok(SequenceFormat::GenBank)
+        } else {
+            Ok(SequenceFormat::from_path(path))
+        }
+    }
+}
+
+/// A single named sequence from an input file, e.g. one circular contig from an assembly
+pub struct SequenceRecord {
+    pub id: String,
+    pub description: Option<String>,
+    pub sequence: Vec<char>,
+}
+
+/// Open a file for reading, or stdin for "-", decompressing it if it starts with the gzip magic bytes.
+/// BGZF files are a series of gzip members, so the multi-member decoder reads those too.
+pub fn open_input(path: &str) -> Result<Box<dyn BufRead>, io::Error> {
+    let mut reader: Box<dyn BufRead> = if path == STDIO {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(File::open(path).map_err(|error| with_path(path, error))?))
+    };
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        reader = Box::new(BufReader::new(MultiGzDecoder::new(reader)));
+    }
+    Ok(reader)
+}
+
+/// name the file in an error opening it, which otherwise just says e.g. that there's no such file
+fn with_path(path: &str, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {}", path, error))
+}
+
+/// read every record from a FASTA file (or stdin), keeping each record's name and description
+pub fn read_fasta_records(reference_file: &str) -> Result<Vec<SequenceRecord>, DupScoopError> {
+    read_fasta_input(open_input(reference_file)?, reference_file)
+}
+
+/// read every record from an opened FASTA input, failing if there aren't any
+pub fn read_fasta_input<R: Read>(reader: R, reference_file: &str) -> Result<Vec<SequenceRecord>, DupScoopError> {
+    let records = read_fasta(reader).map_err(|error| DupScoopError::reading(reference_file, error))?;
+    if records.is_empty() {
+        return Err(DupScoopError::Parse { path: reference_file.to_string(), message: "there are no FASTA records in it".to_string() });
+    }
+    Ok(records)
+}
+
+/// parse FASTA records from any reader, keeping the case of each sequence (lowercase may be soft-masking)
+pub fn read_fasta<R: Read>(reader: R) -> Result<Vec<SequenceRecord>, io::Error> {
+    let mut records = Vec::new();
+    for result in fasta::Reader::new(reader).records() {
+        // the reader reports malformed records as Other errors; real I/O errors keep their own kind
+        let record = result.map_err(|error| match error.kind() {
+            io::ErrorKind::Other => io::Error::new(io::ErrorKind::InvalidData, error.to_string()),
+            _ => error,
+        })?;
+        records.push(SequenceRecord {
+            id: record.id().to_string(),
+            description: record.desc().map(|desc| desc.to_string()),
+            sequence: String::from_utf8_lossy(record.seq()).chars().collect(),
+        });
+    }
+    Ok(records)
+}
+
+/// read the records of a FASTA or GenBank file (or stdin), keeping just the names and sequences
+pub fn read_sequence_records(path: &str) -> Result<Vec<SequenceRecord>, DupScoopError> {
+    let mut input = open_input(path)?;
+    match SequenceFormat::detect(path, &mut input)? {
+        SequenceFormat::Fasta => read_fasta_input(input, path),
+        SequenceFormat::GenBank => Ok(genbank::read_genbank_input(input, path)?.into_iter()
+            .map(|record| SequenceRecord { id: record.name, description: None, sequence: record.sequence })
+            .collect()),
+    }
+}
+
+/// write a sequence out under a record name and description
+pub fn write_fasta_record<W: Write>(writer: &mut fasta::Writer<W>, id: &str, description: Option<&str>, sequence: &[char]) -> Result<(), io::Error> {
+    let sequence_as_string: String = sequence.iter().collect();
+    writer.write(id, description, sequence_as_string.as_bytes())
+}
+
+/// Where the output sequences go: a file or stdout, gzip or BGZF compressed if the file name ends in .gz or
+/// .bgz. finish has to be called once everything is written to complete the compressed stream.
+pub enum OutputStream {
+    Plain(BufWriter<Box<dyn Write>>),
+    Gzip(GzEncoder<BufWriter<Box<dyn Write>>>),
+    Bgzf(BgzfWriter<BufWriter<Box<dyn Write>>>),
+}
+
+impl OutputStream {
+    pub fn finish(self) -> Result<(), io::Error> {
+        let mut inner = match self {
+            OutputStream::Plain(writer) => writer,
+            OutputStream::Gzip(encoder) => encoder.finish()?,
+            OutputStream::Bgzf(writer) => writer.finish()?,
+        };
+        inner.flush()
+    }
+}
+
+impl Write for OutputStream {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputStream::Plain(writer) => writer.write(data),
+            OutputStream::Gzip(encoder) => encoder.write(data),
+            OutputStream::Bgzf(writer) => writer.write(data),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputStream::Plain(writer) => writer.flush(),
+            OutputStream::Gzip(encoder) => encoder.flush(),
+            OutputStream::Bgzf(writer) => writer.flush(),
+        }
+    }
+}
+
+/// create an output file, or write to stdout for "-", compressing by the file name's extension
+pub fn create_output(path: &str) -> Result<OutputStream, io::Error> {
+    let inner: Box<dyn Write> = if path == STDIO { Box::new(io::stdout()) } else { Box::new(File::create(path).map_err(|error| with_path(path, error))?) };
+    let writer = BufWriter::new(inner);
+    let lowercase = path.to_lowercase();
+    Ok(if lowercase.ends_with(".bgz") {
+        OutputStream::Bgzf(BgzfWriter::new(writer))
+    } else if lowercase.ends_with(".gz") {
+        OutputStream::Gzip(GzEncoder::new(writer, Compression::default()))
+    } else {
+        OutputStream::Plain(writer)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(SequenceFormat::from_path("plasmid.fa"), SequenceFormat::Fasta);
+        assert_eq!(SequenceFormat::from_path("plasmid.GBK"), SequenceFormat::GenBank);
+        assert_eq!(SequenceFormat::from_path("plasmid.gb"), SequenceFormat::GenBank);
+        assert_eq!(SequenceFormat::from_path("plasmid.gbk.gz"), SequenceFormat::GenBank);
+        assert_eq!(SequenceFormat::from_path("plasmid.fa.bgz"), SequenceFormat::Fasta);
+    }
+
+    #[test]
+    fn test_detect_format_from_contents() {
+        assert_eq!(SequenceFormat::detect(STDIO, &mut "\nLOCUS       pUC19".as_bytes()).unwrap(), SequenceFormat::GenBank);
+        assert_eq!(SequenceFormat::detect("plasmid.gb", &mut ">contig_1\nACGT\n".as_bytes()).unwrap(), SequenceFormat::Fasta);
+        assert_eq!(SequenceFormat::detect("plasmid.gb", &mut "".as_bytes()).unwrap(), SequenceFormat::GenBank);
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let directory = std::env::temp_dir().join(format!("dupscoop_sequence_io_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let record = SequenceRecord { id: "contig_1".to_string(), description: None, sequence: vec!['A', 'C'] };
+        for name in &["plasmid.fa.gz", "plasmid.fa.bgz"] {
+            let path = directory.join(name).to_str().unwrap().to_string();
+            let mut output = create_output(&path).unwrap();
+            {
+                let mut writer = fasta::Writer::new(&mut output);
+                write_fasta_record(&mut writer, &record.id, None, &"ACGTTGCA".chars().collect::<Vec<char>>()).unwrap();
+                writer.flush().unwrap();
+            }
+            output.finish().unwrap();
+
+            assert_eq!(&std::fs::read(&path).unwrap()[..2], &GZIP_MAGIC);
+            let records = read_sequence_records(&path).unwrap();
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].sequence.iter().collect::<String>(), "ACGTTGCA");
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_read_multiple_records() {
+        let input = ">contig_1 circular=true\nACGT\nacgt\n>contig_2\nTTTT\n";
+        let records = read_fasta(input.as_bytes()).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "contig_1");
+        assert_eq!(records[0].description, Some("circular=true".to_string()));
+        assert_eq!(records[0].sequence.iter().collect::<String>(), "ACGTacgt");
+        assert_eq!(records[1].id, "contig_2");
+        assert_eq!(records[1].description, None);
+        assert_eq!(records[1].sequence.iter().collect::<String>(), "TTTT");
+    }
+
+    #[test]
+    fn test_write_keeps_header() {
+        let record = SequenceRecord { id: "contig_1".to_string(), description: Some("circular=true".to_string()), sequence: vec!['A', 'C'] };
+        let mut buffer = Vec::new();
+        {
+            let mut writer = fasta::Writer::new(&mut buffer);
+            write_fasta_record(&mut writer, &record.id, record.description.as_deref(), &['G', 'G', 'T']).unwrap();
+        }
+        assert_eq!(String::from_utf8(buffer).unwrap(), ">contig_1 circular=true\nGGT\n");
+    }
+}
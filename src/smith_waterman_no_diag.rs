@@ -1,33 +1,198 @@
+use std::cmp::Ordering;
 use std::f64;
-use indicatif::ProgressBar;
+use std::mem::size_of;
 
 use mymatrix;
+use mymatrix::{BandedMatrix, DiagonalBand, MatrixStorage};
 use needleman::Alignment;
 use needleman::Direction;
 use needleman::Direction::{Diag, Done, Left, Up};
 use needleman::Scores;
 
+/// the bytes each candidate cell kept for the suboptimal search costs, counting the room the list grows
+/// into between prunings
+pub const CANDIDATE_BYTES: usize = 2 * size_of::<(f64, usize, usize)>();
+/// the fewest candidate cells worth running the suboptimal search with
+pub const MIN_CANDIDATES: usize = 1 << 16;
+
 /// Aligns two sequences using the Needleman Wunsch global alignment with simple gap scoring
-pub fn smith_waterman_no_diag(seq1: &Vec<char>, seq2: &Vec<char>, scores: &Scores, min_diag_distance: i32) -> Alignment {
+pub fn smith_waterman_no_diag(seq1: &[char], seq2: &[char], scores: &Scores, min_diag_distance: i32) -> Alignment {
     let seq1_limit = seq1.len() + 1;
     let seq2_limit = seq2.len() + 1;
 
     let mut mtx = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0.0);
 
-    println!("Created an alignment matrix of size [{},{}]", mtx.rows(), mtx.cols());
     let mut trc: mymatrix::MyMatrix<Direction> = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, Done);
     smith_waterman_no_diag_borrow(seq1, seq2, &mut mtx, &mut trc, scores, min_diag_distance)
 }
 
-pub fn smith_waterman_no_diag_borrow(seq1: &Vec<char>,
-                                     seq2: &Vec<char>,
+pub fn smith_waterman_no_diag_borrow(seq1: &[char],
+                                     seq2: &[char],
                                      mtx: &mut mymatrix::MyMatrix<f64>,
                                      trc: &mut mymatrix::MyMatrix<Direction>,
                                      scores: &Scores,
                                      min_diag_distance: i32) -> Alignment {
+    let (top_score, topx, topy) = fill_matrix(seq1, seq2, mtx, trc, scores, min_diag_distance, &MatrixWindow::whole(seq1, seq2));
+    traceback(seq1, seq2, trc, mtx, top_score, topx, topy)
+}
+
+/// A rectangle of the full alignment matrix, in sequence coordinates [start, end)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatrixWindow {
+    pub x_start: usize,
+    pub x_end: usize,
+    pub y_start: usize,
+    pub y_end: usize,
+}
+
+impl MatrixWindow {
+    pub fn whole(seq1: &[char], seq2: &[char]) -> MatrixWindow {
+        MatrixWindow { x_start: 0, x_end: seq1.len(), y_start: 0, y_end: seq2.len() }
+    }
+}
+
+/// the best local alignment that lies entirely inside a window of the full matrix. The diagonal band is
+/// still measured in full-matrix coordinates, and so are the coordinates of the returned alignment.
+pub fn smith_waterman_no_diag_window(seq1: &[char], seq2: &[char], scores: &Scores, min_diag_distance: i32, window: &MatrixWindow) -> Alignment {
+    let window_one = &seq1[window.x_start..window.x_end];
+    let window_two = &seq2[window.y_start..window.y_end];
+
+    let mut mtx = mymatrix::MyMatrix::new(window_one.len() + 1, window_two.len() + 1, 0.0);
+    let mut trc: mymatrix::MyMatrix<Direction> = mymatrix::MyMatrix::new(window_one.len() + 1, window_two.len() + 1, Done);
+    let (top_score, topx, topy) = fill_matrix(seq1, seq2, &mut mtx, &mut trc, scores, min_diag_distance, window);
+
+    let mut alignment = traceback(window_one, window_two, &trc, &mtx, top_score, topx, topy);
+    alignment.seq_one = seq1.to_vec();
+    alignment.seq_two = seq2.to_vec();
+    alignment.start_x += window.x_start;
+    alignment.end_x += window.x_start;
+    alignment.start_y += window.y_start;
+    alignment.end_y += window.y_start;
+    alignment
+}
+
+/// Find a ranked list of non-overlapping local alignments from a single matrix fill, in the spirit of
+/// Waterman-Eggert. Every cell scoring at least min_score is a candidate end point; candidates are traced
+/// back from the highest score down, skipping any cell already on a traced path, and an alignment is kept
+/// only if the overlaps function says it doesn't collide with an alignment we've already kept.
+pub fn smith_waterman_no_diag_suboptimal<F>(seq1: &[char],
+                                            seq2: &[char],
+                                            scores: &Scores,
+                                            min_diag_distance: i32,
+                                            min_score: f64,
+                                            max_candidates: usize,
+                                            overlaps: F) -> Vec<Alignment> where F: Fn(&Alignment, &Alignment) -> bool {
     let seq1_limit = seq1.len() + 1;
     let seq2_limit = seq2.len() + 1;
 
+    let mut mtx = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0.0);
+    let mut trc: mymatrix::MyMatrix<Direction> = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, Done);
+    fill_matrix(seq1, seq2, &mut mtx, &mut trc, scores, min_diag_distance, &MatrixWindow::whole(seq1, seq2));
+
+    suboptimal_from_matrices(seq1, seq2, &trc, &mtx, min_score, max_candidates, overlaps)
+}
+
+/// the bytes the suboptimal search needs on top of the matrices: the visited-cell bitmap and the candidates
+pub fn suboptimal_bytes(seq1_length: usize, seq2_length: usize, max_candidates: usize) -> usize {
+    VisitedCells::bytes(seq1_length + 1, seq2_length + 1) + max_candidates * CANDIDATE_BYTES
+}
+
+/// the Waterman-Eggert selection for already filled score and traceback matrices
+pub fn suboptimal_from_matrices<D, S, F>(seq1: &[char],
+                                         seq2: &[char],
+                                         trc: &D,
+                                         mtx: &S,
+                                         min_score: f64,
+                                         max_candidates: usize,
+                                         overlaps: F) -> Vec<Alignment>
+    where D: MatrixStorage<Direction>, S: MatrixStorage<f64>, F: Fn(&Alignment, &Alignment) -> bool {
+    let candidates = best_cells(mtx, seq1.len() + 1, seq2.len() + 1, min_score, max_candidates);
+    let mut traced = VisitedCells::new(seq1.len() + 1, seq2.len() + 1);
+    let mut alignments: Vec<Alignment> = Vec::new();
+    for (score, ix, iy) in candidates {
+        if traced.contains(ix, iy) {
+            continue;
+        }
+        let alignment = traceback_path(seq1, seq2, trc, mtx, score, ix, iy, Some(&mut traced));
+        if !alignments.iter().any(|kept| overlaps(kept, &alignment)) {
+            alignments.push(alignment);
+        }
+    }
+    alignments
+}
+
+/// The cells to start tracebacks from: every cell scoring above 0 and at least min_score, best first. A
+/// stable sort keeps row-major order for ties, so the first candidate is the cell the single-best search
+/// picks. Only the best max_candidates are kept; whenever the list doubles past that it's sorted and cut
+/// back, and after that a cell has to beat the last one kept to get in.
+pub fn best_cells<S: MatrixStorage<f64>>(mtx: &S, rows: usize, cols: usize, min_score: f64, max_candidates: usize) -> Vec<(f64, usize, usize)> {
+    let by_score = |a: &(f64, usize, usize), b: &(f64, usize, usize)| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal);
+    let max_candidates = max_candidates.max(1);
+    let mut candidates = Vec::new();
+    let mut floor: Option<f64> = None;
+    for ix in 1..rows {
+        for iy in 1..cols {
+            let score = mtx.get(ix, iy);
+            let wanted = match floor {
+                Some(floor) => score > floor,
+                None => score > 0.0 && score >= min_score,
+            };
+            if wanted {
+                candidates.push((score, ix, iy));
+                if candidates.len() == 2 * max_candidates {
+                    candidates.sort_by(by_score);
+                    candidates.truncate(max_candidates);
+                    floor = candidates.last().map(|last| last.0);
+                }
+            }
+        }
+    }
+    candidates.sort_by(by_score);
+    candidates.truncate(max_candidates);
+    candidates
+}
+
+/// one bit per matrix cell, marking the cells a traceback has passed through
+pub struct VisitedCells {
+    bits: Vec<u64>,
+    cols: usize,
+}
+
+impl VisitedCells {
+    pub fn new(rows: usize, cols: usize) -> VisitedCells {
+        VisitedCells { bits: vec![0; (rows * cols).div_ceil(64)], cols }
+    }
+
+    /// the bytes the bitmap for a rows x cols matrix takes
+    pub fn bytes(rows: usize, cols: usize) -> usize {
+        (rows * cols).div_ceil(64) * size_of::<u64>()
+    }
+
+    #[inline]
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        let cell = row * self.cols + col;
+        self.bits[cell / 64] & (1 << (cell % 64)) != 0
+    }
+
+    #[inline]
+    pub fn insert(&mut self, row: usize, col: usize) {
+        let cell = row * self.cols + col;
+        self.bits[cell / 64] |= 1 << (cell % 64);
+    }
+}
+
+/// fill in the score and traceback matrices for a window of the full matrix, returning the best score and
+/// the cell it was found in (in window coordinates)
+fn fill_matrix(seq1: &[char],
+               seq2: &[char],
+               mtx: &mut mymatrix::MyMatrix<f64>,
+               trc: &mut mymatrix::MyMatrix<Direction>,
+               scores: &Scores,
+               min_diag_distance: i32,
+               window: &MatrixWindow) -> (f64, usize, usize) {
+    let seq1_limit = window.x_end - window.x_start + 1;
+    let seq2_limit = window.y_end - window.y_start + 1;
+
     // first square
     mtx.set(0, 0, 0.0);
 
@@ -45,16 +210,12 @@ pub fn smith_waterman_no_diag_borrow(seq1: &Vec<char>,
     let mut topx = 0;
     let mut topy = 0;
 
-    println!("Aligning (status by rows)...");
     let bar = indicatif::ProgressBar::new(seq1_limit as u64);
 
     // fill in the matrix
     for ix in 1..seq1_limit {
         for iy in 1..seq2_limit {
-            let score = Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
-            //if ((ix == iy || ((iy % seq1.len()) == ix) || ((ix % seq2.len()) == iy)) && ix % 100 == 0) {
-            //    println!("NOGO is from {},{}", ix, iy);
-            //}
+            let score = Scores::scoring_function(seq1[window.x_start + ix - 1], seq2[window.y_start + iy - 1], scores);
             let up_t = (mtx.get(ix - 1, iy) + scores.gap_ext, Up);
             let left_t = (mtx.get(ix, iy - 1) + scores.gap_ext, Left);
             let diag_t = (mtx.get(ix - 1, iy - 1) + score, Diag);
@@ -65,9 +226,7 @@ pub fn smith_waterman_no_diag_borrow(seq1: &Vec<char>,
                 topx = ix;
                 topy = iy;
             }
-            if (ix as i32 - iy as i32 ).abs() < min_diag_distance ||
-                ((iy as i32 % seq1.len() as i32) - ix as i32).abs() < min_diag_distance ||
-                ((ix as i32 % seq2.len() as i32) - iy as i32).abs() < min_diag_distance {
+            if in_diagonal_band(window.x_start + ix, window.y_start + iy, seq1.len(), seq2.len(), min_diag_distance) {
                 max = (0.0, max.1);
             }
 
@@ -77,11 +236,69 @@ pub fn smith_waterman_no_diag_borrow(seq1: &Vec<char>,
         bar.inc(1);
     }
     bar.finish();
-    //trc.print_matrix(8);
-    //println!("max isnow from {},{}", topx, topy);
+    (top_score, topx, topy)
+}
+
+/// the best local alignment using only the cells inside a set of diagonal bands, in memory proportional to
+/// the band width. Cells outside the bands score zero, so an alignment can start at the edge of a band.
+pub fn smith_waterman_no_diag_banded(seq1: &[char], seq2: &[char], scores: &Scores, min_diag_distance: i32, bands: &[DiagonalBand]) -> Alignment {
+    let mut mtx = BandedMatrix::new(seq1.len() + 1, seq2.len() + 1, bands, 0.0, 0.0);
+    let mut trc = BandedMatrix::new(seq1.len() + 1, seq2.len() + 1, bands, Done, Done);
+    smith_waterman_no_diag_banded_borrow(seq1, seq2, &mut mtx, &mut trc, scores, min_diag_distance)
+}
+
+pub fn smith_waterman_no_diag_banded_borrow(seq1: &[char],
+                                            seq2: &[char],
+                                            mtx: &mut BandedMatrix<f64>,
+                                            trc: &mut BandedMatrix<Direction>,
+                                            scores: &Scores,
+                                            min_diag_distance: i32) -> Alignment {
+    let mut top_score = 0.0;
+    let mut topx = 0;
+    let mut topy = 0;
+
+    let bar = indicatif::ProgressBar::new(seq1.len() as u64 + 1);
+    for ix in 0..seq1.len() + 1 {
+        for (col_start, col_end) in mtx.row_ranges(ix) {
+            for iy in col_start..col_end {
+                if ix == 0 || iy == 0 {
+                    mtx.set(ix, iy, 0.0);
+                    trc.set(ix, iy, if ix == 0 { Left } else { Up });
+                    continue;
+                }
+                let score = Scores::scoring_function(seq1[ix - 1], seq2[iy - 1], scores);
+                let up_t = (mtx.get(ix - 1, iy) + scores.gap_ext, Up);
+                let left_t = (mtx.get(ix, iy - 1) + scores.gap_ext, Left);
+                let diag_t = (mtx.get(ix - 1, iy - 1) + score, Diag);
+
+                let mut max = max2(max2(max2(up_t, left_t), diag_t), (0.0, Diag));
+                if max.0 > top_score {
+                    top_score = max.0;
+                    topx = ix;
+                    topy = iy;
+                }
+                if in_diagonal_band(ix, iy, seq1.len(), seq2.len(), min_diag_distance) {
+                    max = (0.0, max.1);
+                }
+                mtx.set(ix, iy, max.0);
+                trc.set(ix, iy, max.1);
+            }
+        }
+        bar.inc(1);
+    }
+    bar.finish();
     traceback(seq1, seq2, trc, mtx, top_score, topx, topy)
 }
 
+/// whether a cell sits too close to the trivial self-alignment diagonal (or its copy in a doubled sequence)
+/// to be part of a duplicate
+#[inline]
+pub fn in_diagonal_band(ix: usize, iy: usize, seq1_length: usize, seq2_length: usize, min_diag_distance: i32) -> bool {
+    (ix as i32 - iy as i32).abs() < min_diag_distance ||
+        ((iy as i32 % seq1_length as i32) - ix as i32).abs() < min_diag_distance ||
+        ((ix as i32 % seq2_length as i32) - iy as i32).abs() < min_diag_distance
+}
+
 #[inline]
 fn max2(x: (f64, Direction), y: (f64, Direction)) -> (f64, Direction) {
     if x.0 > y.0 { x } else { y }
@@ -96,13 +313,26 @@ struct TruthSet {
 }
 
 /// traceback a matrix into an alignment struct
-pub fn traceback(seq1: &Vec<char>,
-                 seq2: &Vec<char>,
-                 trc: &mymatrix::MyMatrix<Direction>,
-                 mtx: &mymatrix::MyMatrix<f64>,
+pub fn traceback<D: MatrixStorage<Direction>, S: MatrixStorage<f64>>(seq1: &[char],
+                 seq2: &[char],
+                 trc: &D,
+                 mtx: &S,
                  top_score: f64,
                  topx: usize,
                  topy: usize) -> Alignment {
+    traceback_path(seq1, seq2, trc, mtx, top_score, topx, topy, None)
+}
+
+/// traceback from a cell, recording every cell the path passes through
+#[allow(clippy::too_many_arguments)]
+fn traceback_path<D: MatrixStorage<Direction>, S: MatrixStorage<f64>>(seq1: &[char],
+                  seq2: &[char],
+                  trc: &D,
+                  mtx: &S,
+                  top_score: f64,
+                  topx: usize,
+                  topy: usize,
+                  mut visited: Option<&mut VisitedCells>) -> Alignment {
     assert_eq!(seq1.len(), trc.rows() - 1, "The matrix doesn't have the right number of rows; rows: {}, expected: {}", seq1.len(), trc.rows() - 1);
     assert_eq!(seq2.len(), trc.cols() - 1, "The matrix doesn't have the right number of columns; columns: {}, expected: {}", seq2.len(), trc.cols() - 1);
 
@@ -112,28 +342,25 @@ pub fn traceback(seq1: &Vec<char>,
     let mut row_index = topx;
     let mut column_index = topy;
 
-    //println!("Tops: {},{} score {}", topx, topy, top_score);
 
     let gap = '-';
-    //trc.print_matrix(4);
 
     let mut current_pointer = trc.get(row_index, column_index);
     let mut current_score = mtx.get(row_index, column_index);
     loop {
+        if let Some(ref mut visited) = visited {
+            visited.insert(row_index, column_index);
+        }
         match (current_pointer, current_score) {
             (_, x) if x <= 0.0 => {
-                //println!("DONE 0: {},{} score {}",row_index,column_index,current_score);
                 break;
             }
             (Up, _) => {
                 alignment1.push(seq1[row_index - 1]);
                 alignment2.push(gap);
-                //println!("UP: Moving from {},{} to {},{} score {}", row_index, column_index, row_index - 1, column_index, current_score);
                 row_index -= 1;
             }
             (Left, _) => {
-                //println!("LEFT: Moving from {},{} to {},{} score {}", row_index, column_index, row_index, column_index - 1, current_score);
-
                 alignment1.push(gap);
                 alignment2.push(seq2[column_index - 1]);
                 column_index -= 1;
@@ -141,24 +368,20 @@ pub fn traceback(seq1: &Vec<char>,
             (Diag, _) => {
                 alignment1.push(seq1[row_index - 1]);
                 alignment2.push(seq2[column_index - 1]);
-                //println!("DIAG: Moving from {},{} to {},{} score {}", row_index, column_index, row_index - 1, column_index - 1, current_score);
                 row_index -= 1;
                 column_index -= 1;
             }
             (Done, _) => {
-                //println!("DONE: {},{} score {}",row_index,column_index,current_score);
                 break;
             }
         }
-        //println!("looooop");
         current_pointer = trc.get(row_index, column_index);
         current_score = mtx.get(row_index, column_index);
     }
 
     alignment1.reverse();
     alignment2.reverse();
-    println!("Alignment lengths of {} and {}", alignment1.len(), alignment2.len());
-    return Alignment {
+    Alignment {
         seq_one: seq1.to_vec(),
         seq_two: seq2.to_vec(),
         score: top_score,
@@ -168,18 +391,19 @@ pub fn traceback(seq1: &Vec<char>,
         end_y: topy,
         seq_one_aligned: alignment1,
         seq_two_aligned: alignment2,
-    };
+    }
 }
 
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use test_support::test_scores;
 
     #[test]
     fn test_basic_alignment() {
         let scores = Scores::default_scores();
-        let alignment = smith_waterman_no_diag(&vec!['A', 'A', 'A', 'A', 'A', 'A'], &vec!['A', 'A', 'A', 'A', 'A', 'A'], &scores, 1);
+        let alignment = smith_waterman_no_diag(&['A', 'A', 'A', 'A', 'A', 'A'], &['A', 'A', 'A', 'A', 'A', 'A'], &scores, 1);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -197,7 +421,7 @@ mod tests {
     #[test]
     fn test_basic_alignment_forced() {
         let scores = Scores::default_scores();
-        let alignment = smith_waterman_no_diag(&vec!['A', 'C', 'G', 'T', 'A', 'C'], &vec!['A', 'C', 'G', 'T', 'A', 'C'], &scores, 1);
+        let alignment = smith_waterman_no_diag(&['A', 'C', 'G', 'T', 'A', 'C'], &['A', 'C', 'G', 'T', 'A', 'C'], &scores, 1);
 
         let str1: String = alignment.seq_one.into_iter().collect();
         let str2: String = alignment.seq_two.into_iter().collect();
@@ -214,10 +438,8 @@ mod tests {
     #[test]
     fn test_offset_best() {
         let scores = Scores::default_scores();
-        let alignment = smith_waterman_no_diag(&vec!['T','T','A', 'C', 'G', 'T', 'A', 'C','A', 'C', 'G', 'T', 'A', 'C'], &vec!['A', 'C', 'G', 'T', 'A', 'C','A', 'C', 'G', 'T', 'A', 'C'], &scores, 1);
+        let alignment = smith_waterman_no_diag(&vec!['T','T','A', 'C', 'G', 'T', 'A', 'C','A', 'C', 'G', 'T', 'A', 'C'], &['A', 'C', 'G', 'T', 'A', 'C','A', 'C', 'G', 'T', 'A', 'C'], &scores, 1);
 
-        let str1: String = alignment.seq_one.into_iter().collect();
-        let str2: String = alignment.seq_two.into_iter().collect();
         let str1align: String = alignment.seq_one_aligned.into_iter().collect();
         let str2align: String = alignment.seq_two_aligned.into_iter().collect();
         println!("{},{}", str1align, str2align);
@@ -226,4 +448,61 @@ mod tests {
         assert_eq!(str1align, "ACGTACACGTAC");
         assert_eq!(str2align, "ACGTACACGTAC");
     }
+
+    #[test]
+    fn test_suboptimal_finds_both_repeats() {
+        let scores = test_scores();
+        let seq1: Vec<char> = "GATTACACCCCCGGTTAAGGTTTTTTTGATTACATTTGGTTAAGG".chars().collect();
+        // in a self-alignment each repeat shows up twice, mirrored across the diagonal, so compare every copy
+        let overlapping = |a: &Alignment, b: &Alignment| {
+            let segments = |al: &Alignment| vec![(al.start_x, al.end_x), (al.start_y, al.end_y)];
+            segments(a).iter().any(|one| segments(b).iter().any(|two| one.0 < two.1 && two.0 < one.1))
+        };
+        let alignments = smith_waterman_no_diag_suboptimal(&seq1, &seq1, &scores, 5, 7.0, MIN_CANDIDATES, overlapping);
+
+        // each repeat is found once, best first
+        assert_eq!(alignments.len(), 2);
+        assert_eq!(alignments[0].score, 8.0);
+        assert_eq!(alignments[0].seq_one_aligned.iter().collect::<String>(), "GGTTAAGG");
+        assert_eq!(alignments[1].score, 7.0);
+        assert_eq!(alignments[1].seq_one_aligned.iter().collect::<String>(), "GATTACA");
+
+        let best = smith_waterman_no_diag(&seq1, &seq1, &scores, 5);
+        assert_eq!((best.start_x, best.end_x, best.start_y, best.end_y),
+                   (alignments[0].start_x, alignments[0].end_x, alignments[0].start_y, alignments[0].end_y));
+    }
+
+    #[test]
+    fn test_banded_matches_full_matrix() {
+        let scores = Scores { gap_ext: -2.0, ..test_scores() };
+        let seq1: Vec<char> = "CCCCGATTACAGGTTAATTTTTTTTGATTACAGGTAATCCCCC".chars().collect();
+        let full = smith_waterman_no_diag(&seq1, &seq1, &scores, 5);
+        let banded = smith_waterman_no_diag_banded(&seq1, &seq1, &scores, 5, &[DiagonalBand { diagonal: 21, width: 3 }]);
+
+        assert_eq!(banded.score, full.score);
+        assert_eq!((banded.start_x, banded.end_x, banded.start_y, banded.end_y), (full.start_x, full.end_x, full.start_y, full.end_y));
+        assert_eq!(banded.seq_one_aligned, full.seq_one_aligned);
+        assert_eq!(banded.seq_two_aligned, full.seq_two_aligned);
+
+        // a band that misses the repeat finds nothing worth keeping
+        let elsewhere = smith_waterman_no_diag_banded(&seq1, &seq1, &scores, 5, &[DiagonalBand { diagonal: 35, width: 1 }]);
+        assert!(elsewhere.score < full.score);
+    }
+
+    #[test]
+    fn test_best_cells_within_a_budget() {
+        // plenty of tied scores, so pruning has to keep the row-major order of ties to match the full sort
+        let mut mtx = mymatrix::MyMatrix::new(40, 50, 0.0);
+        for ix in 1..40 {
+            for iy in 1..50 {
+                mtx.set(ix, iy, ((ix * 7 + iy * 13) % 11) as f64 - 2.0);
+            }
+        }
+        let everything = best_cells(&mtx, 40, 50, 3.0, usize::MAX / 2);
+        assert!(everything.iter().all(|cell| cell.0 >= 3.0));
+        assert!(everything.len() > 500);
+        for max_candidates in &[1, 7, 100, 500] {
+            assert_eq!(best_cells(&mtx, 40, 50, 3.0, *max_candidates), everything[..*max_candidates].to_vec());
+        }
+    }
 }
@@ -0,0 +1,464 @@
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+#[cfg(target_arch = "x86_64")]
+use std::collections::HashMap;
+
+use mymatrix;
+use mymatrix::MatrixStorage;
+use needleman::{Alignment, Direction, Scores};
+use needleman::Direction::{Diag, Done, Left, Up};
+use smith_waterman_no_diag;
+use smith_waterman_no_diag::{in_diagonal_band, MatrixWindow};
+
+/// scores bigger than this in magnitude stay on the f64 engine, so the 16 bit fill has room to work
+const MAX_INTEGER_SCORE: f64 = 1000.0;
+
+/// 16 bit scores per SSE2 register
+#[cfg(target_arch = "x86_64")]
+const LANES: usize = 8;
+
+/// the bytes the integer engine needs for a window this size: the 32 bit refill after a 16 bit overflow is
+/// the most it ever holds at once
+pub fn integer_matrix_bytes(seq1_length: usize, seq2_length: usize) -> usize {
+    (seq1_length + 1) * (seq2_length + 1) * std::mem::size_of::<i32>()
+}
+
+/// Scores that are all whole numbers, for the integer engine. Alignments come out identical to the f64
+/// engine because every f64 score it computes is then a small integer too.
+pub struct IntegerScores {
+    scores: Scores,
+    gap_ext: i32,
+}
+
+impl IntegerScores {
+    /// None if a score isn't a whole number, is too big, or the gap score isn't a penalty
+    pub fn from_scores(scores: &Scores) -> Option<IntegerScores> {
+        let whole = |value: f64| value.fract() == 0.0 && value.abs() <= MAX_INTEGER_SCORE;
+        if !whole(scores.match_score) || !whole(scores.mismatch_score) || !whole(scores.gap_ext) || scores.gap_ext > 0.0 {
+            return None;
+        }
+        if let Some(ref matrix) = scores.substitution_matrix {
+            if !matrix.values().all(whole) {
+                return None;
+            }
+        }
+        Some(IntegerScores { scores: scores.clone(), gap_ext: scores.gap_ext as i32 })
+    }
+
+    #[inline]
+    fn score(&self, base1: char, base2: char) -> i32 {
+        Scores::scoring_function(base1, base2, &self.scores) as i32
+    }
+}
+
+/// A filled integer score matrix in window coordinates; the first row and column are zero
+enum IntegerMatrix {
+    /// the striped fill's columns, each segment_length registers of LANES scores
+    Striped { values: Vec<i16>, segment_length: usize },
+    Scalar(mymatrix::MyMatrix<i32>),
+}
+
+impl IntegerMatrix {
+    #[inline]
+    fn get(&self, ix: usize, iy: usize) -> i32 {
+        match self {
+            IntegerMatrix::Scalar(mtx) => mtx.get(ix, iy),
+            IntegerMatrix::Striped { .. } if ix == 0 || iy == 0 => 0,
+            #[cfg(target_arch = "x86_64")]
+            IntegerMatrix::Striped { values, segment_length } => {
+                let row = ix - 1;
+                values[(iy - 1) * segment_length * LANES + (row % segment_length) * LANES + row / segment_length] as i32
+            }
+            #[cfg(not(target_arch = "x86_64"))]
+            IntegerMatrix::Striped { .. } => unreachable!("the striped fill only runs on x86_64"),
+        }
+    }
+}
+
+/// Lets the f64 engine's traceback walk an integer matrix: scores convert losslessly, and directions are
+/// recomputed from the neighbouring cells with the same tie-breaking as the f64 fill.
+struct IntegerTraceback<'a> {
+    matrix: &'a IntegerMatrix,
+    seq1: &'a [char],
+    seq2: &'a [char],
+    scores: &'a IntegerScores,
+}
+
+impl<'a> MatrixStorage<f64> for IntegerTraceback<'a> {
+    fn rows(&self) -> usize { self.seq1.len() + 1 }
+    fn cols(&self) -> usize { self.seq2.len() + 1 }
+    fn get(&self, row: usize, col: usize) -> f64 { self.matrix.get(row, col) as f64 }
+}
+
+impl<'a> MatrixStorage<Direction> for IntegerTraceback<'a> {
+    fn rows(&self) -> usize { self.seq1.len() + 1 }
+    fn cols(&self) -> usize { self.seq2.len() + 1 }
+    fn get(&self, row: usize, col: usize) -> Direction {
+        match (row, col) {
+            (0, 0) => Done,
+            (0, _) => Left,
+            (_, 0) => Up,
+            _ => best_predecessor(self.matrix, self.seq1, self.seq2, self.scores, row, col).1,
+        }
+    }
+}
+
+/// the unmasked score of a cell and where it came from, preferring the later option on ties like the f64 fill
+#[inline]
+fn best_predecessor(matrix: &IntegerMatrix, seq1: &[char], seq2: &[char], scores: &IntegerScores, ix: usize, iy: usize) -> (i32, Direction) {
+    let up_t = (matrix.get(ix - 1, iy) + scores.gap_ext, Up);
+    let left_t = (matrix.get(ix, iy - 1) + scores.gap_ext, Left);
+    let diag_t = (matrix.get(ix - 1, iy - 1) + scores.score(seq1[ix - 1], seq2[iy - 1]), Diag);
+    max2(max2(max2(up_t, left_t), diag_t), (0, Diag))
+}
+
+#[inline]
+fn max2(x: (i32, Direction), y: (i32, Direction)) -> (i32, Direction) {
+    if x.0 > y.0 { x } else { y }
+}
+
+/// The same alignment smith_waterman_no_diag_window finds, filled with integer scores: a striped SIMD fill
+/// on x86_64 (Farrar's layout, 16 bit lanes) and a scalar 32 bit fill elsewhere or when 16 bits overflow.
+pub fn smith_waterman_no_diag_integer(seq1: &[char], seq2: &[char], scores: &IntegerScores, min_diag_distance: i32, window: &MatrixWindow) -> Alignment {
+    let window_one = &seq1[window.x_start..window.x_end];
+    let window_two = &seq2[window.y_start..window.y_end];
+    let (matrix, top_score, topx, topy) = fill_integer(seq1, seq2, scores, min_diag_distance, window);
+
+    let view = IntegerTraceback { matrix: &matrix, seq1: window_one, seq2: window_two, scores };
+    let mut alignment = smith_waterman_no_diag::traceback(window_one, window_two, &view, &view, top_score as f64, topx, topy);
+    alignment.seq_one = seq1.to_vec();
+    alignment.seq_two = seq2.to_vec();
+    alignment.start_x += window.x_start;
+    alignment.end_x += window.x_start;
+    alignment.start_y += window.y_start;
+    alignment.end_y += window.y_start;
+    alignment
+}
+
+/// smith_waterman_no_diag_suboptimal on the integer engine
+pub fn smith_waterman_no_diag_integer_suboptimal<F>(seq1: &[char],
+                                                    seq2: &[char],
+                                                    scores: &IntegerScores,
+                                                    min_diag_distance: i32,
+                                                    min_score: f64,
+                                                    max_candidates: usize,
+                                                    overlaps: F) -> Vec<Alignment> where F: Fn(&Alignment, &Alignment) -> bool {
+    let (matrix, _, _, _) = fill_integer(seq1, seq2, scores, min_diag_distance, &MatrixWindow::whole(seq1, seq2));
+    let view = IntegerTraceback { matrix: &matrix, seq1, seq2, scores };
+    smith_waterman_no_diag::suboptimal_from_matrices(seq1, seq2, &view, &view, min_score, max_candidates, overlaps)
+}
+
+/// fill the window's matrix, returning it with the best (unmasked) score and its cell in window coordinates
+fn fill_integer(seq1: &[char], seq2: &[char], scores: &IntegerScores, min_diag_distance: i32, window: &MatrixWindow) -> (IntegerMatrix, i32, usize, usize) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        // SSE2 is part of the x86_64 baseline, so there's nothing to detect at runtime
+        if let Some(filled) = unsafe { fill_striped(seq1, seq2, scores, min_diag_distance, window) } {
+            return filled;
+        }
+    }
+    let (mtx, top_score, topx, topy) = fill_scalar(seq1, seq2, scores, min_diag_distance, window);
+    (IntegerMatrix::Scalar(mtx), top_score, topx, topy)
+}
+
+/// the row-by-row fill, the integer twin of smith_waterman_no_diag's fill_matrix
+fn fill_scalar(seq1: &[char], seq2: &[char], scores: &IntegerScores, min_diag_distance: i32, window: &MatrixWindow) -> (mymatrix::MyMatrix<i32>, i32, usize, usize) {
+    let seq1_limit = window.x_end - window.x_start + 1;
+    let seq2_limit = window.y_end - window.y_start + 1;
+    let mut mtx = mymatrix::MyMatrix::new(seq1_limit, seq2_limit, 0);
+
+    let mut top_score = 0;
+    let mut topx = 0;
+    let mut topy = 0;
+    for ix in 1..seq1_limit {
+        for iy in 1..seq2_limit {
+            let score = scores.score(seq1[window.x_start + ix - 1], seq2[window.y_start + iy - 1]);
+            let best = (mtx.get(ix - 1, iy) + scores.gap_ext)
+                .max(mtx.get(ix, iy - 1) + scores.gap_ext)
+                .max(mtx.get(ix - 1, iy - 1) + score)
+                .max(0);
+            if best > top_score {
+                top_score = best;
+                topx = ix;
+                topy = iy;
+            }
+            let stored = if in_diagonal_band(window.x_start + ix, window.y_start + iy, seq1.len(), seq2.len(), min_diag_distance) { 0 } else { best };
+            mtx.set(ix, iy, stored);
+        }
+    }
+    (mtx, top_score, topx, topy)
+}
+
+/// The rows of one full-matrix column that in_diagonal_band excludes, as [start, end) ranges. Each of its
+/// three conditions is an interval of rows (the last one repeats every seq2_length rows).
+fn band_rows(iy: usize, seq1_length: usize, seq2_length: usize, min_diag_distance: i32) -> Vec<(i64, i64)> {
+    if min_diag_distance <= 0 {
+        return Vec::new();
+    }
+    let distance = min_diag_distance as i64;
+    let iy = iy as i64;
+    let wrapped = iy % seq1_length as i64;
+    let mut ranges = vec![(iy - distance + 1, iy + distance), (wrapped - distance + 1, wrapped + distance)];
+    let seq2_length = seq2_length as i64;
+    for repeat in 0..(seq1_length as i64 / seq2_length) + 1 {
+        let start = (iy - distance + 1).max(0);
+        let end = (iy + distance).min(seq2_length);
+        if start < end {
+            ranges.push((repeat * seq2_length + start, repeat * seq2_length + end));
+        }
+    }
+    ranges
+}
+
+/// Farrar's striped fill: row i of the window lives in register i % segment_length, lane i / segment_length,
+/// so the cells a register holds never depend on each other within a column. The vertical gaps that cross
+/// from one lane into the next are patched up afterwards by the lazy-F loop. Cells inside the diagonal band
+/// are zeroed with a per-column mask, and the best score is tracked before masking like the scalar fill.
+/// Returns None if a score saturates 16 bits.
+#[cfg(target_arch = "x86_64")]
+unsafe fn fill_striped(seq1: &[char], seq2: &[char], scores: &IntegerScores, min_diag_distance: i32, window: &MatrixWindow) -> Option<(IntegerMatrix, i32, usize, usize)> {
+    let rows = window.x_end - window.x_start;
+    let cols = window.y_end - window.y_start;
+    if rows == 0 || cols == 0 {
+        return Some((IntegerMatrix::Striped { values: Vec::new(), segment_length: 1 }, 0, 0, 0));
+    }
+    let segment_length = rows.div_ceil(LANES);
+    let column_size = segment_length * LANES;
+    let lane_row = |segment: usize, lane: usize| lane * segment_length + segment;
+
+    // one query profile per base that shows up in the window of seq2
+    let mut profiles: HashMap<char, Vec<i16>> = HashMap::new();
+    for base in &seq2[window.y_start..window.y_end] {
+        profiles.entry(*base).or_insert_with(|| {
+            let mut profile = vec![0i16; column_size];
+            for segment in 0..segment_length {
+                for lane in 0..LANES {
+                    let row = lane_row(segment, lane);
+                    if row < rows {
+                        profile[segment * LANES + lane] = scores.score(seq1[window.x_start + row], *base) as i16;
+                    }
+                }
+            }
+            profile
+        });
+    }
+    // padding lanes past the last row are always masked out
+    let mut valid = vec![0i16; column_size];
+    for segment in 0..segment_length {
+        for lane in 0..LANES {
+            if lane_row(segment, lane) < rows {
+                valid[segment * LANES + lane] = -1;
+            }
+        }
+    }
+    let mut mask = valid.clone();
+
+    let mut values = vec![0i16; cols * column_size];
+    let zero_column = vec![0i16; column_size];
+    let mut gaps = vec![scores.gap_ext as i16; column_size];
+    let mut column_max = vec![0i16; cols];
+    let load = |slice: &[i16], segment: usize| _mm_loadu_si128(slice.as_ptr().add(segment * LANES) as *const __m128i);
+    let v_gap = _mm_set1_epi16(scores.gap_ext as i16);
+    let v_zero = _mm_setzero_si128();
+
+    for col in 0..cols {
+        let full_col = window.y_start + col + 1;
+        let mut masked_rows = Vec::new();
+        for (start, end) in band_rows(full_col, seq1.len(), seq2.len(), min_diag_distance) {
+            let start = start.max(window.x_start as i64 + 1);
+            let end = end.min(window.x_end as i64 + 1);
+            for full_row in start..end {
+                let row = full_row as usize - window.x_start - 1;
+                mask[(row % segment_length) * LANES + row / segment_length] = 0;
+                masked_rows.push(row);
+            }
+        }
+
+        let (previous, current) = values.split_at_mut(col * column_size);
+        let previous: &[i16] = if col == 0 { &zero_column } else { &previous[(col - 1) * column_size..] };
+        let current = &mut current[..column_size];
+        let profile = &profiles[&seq2[window.y_start + col]];
+
+        let mut v_max = v_zero;
+        let mut v_f = v_gap;
+        // the diagonal into each lane's first row comes from the row above it, one lane down
+        let mut v_h = _mm_slli_si128::<2>(load(previous, segment_length - 1));
+        for segment in 0..segment_length {
+            v_h = _mm_adds_epi16(v_h, load(profile, segment));
+            v_h = _mm_max_epi16(v_h, load(&gaps, segment));
+            v_h = _mm_max_epi16(v_h, v_f);
+            v_h = _mm_max_epi16(v_h, v_zero);
+            v_max = _mm_max_epi16(v_max, _mm_and_si128(v_h, load(&valid, segment)));
+            v_h = _mm_and_si128(v_h, load(&mask, segment));
+            _mm_storeu_si128(current.as_mut_ptr().add(segment * LANES) as *mut __m128i, v_h);
+            let v_next = _mm_adds_epi16(v_h, v_gap);
+            _mm_storeu_si128(gaps.as_mut_ptr().add(segment * LANES) as *mut __m128i, v_next);
+            v_f = v_next;
+            v_h = load(previous, segment);
+        }
+
+        // carry vertical gaps across lanes until they stop improving anything
+        'lazy: for _ in 0..LANES {
+            v_f = _mm_insert_epi16::<0>(_mm_slli_si128::<2>(v_f), scores.gap_ext);
+            for segment in 0..segment_length {
+                let v_old = load(current, segment);
+                v_max = _mm_max_epi16(v_max, _mm_and_si128(v_f, load(&valid, segment)));
+                let v_new = _mm_and_si128(_mm_max_epi16(v_old, v_f), load(&mask, segment));
+                if _mm_movemask_epi8(_mm_cmpeq_epi16(v_new, v_old)) == 0xFFFF {
+                    break 'lazy;
+                }
+                _mm_storeu_si128(current.as_mut_ptr().add(segment * LANES) as *mut __m128i, v_new);
+                v_f = _mm_adds_epi16(v_new, v_gap);
+                _mm_storeu_si128(gaps.as_mut_ptr().add(segment * LANES) as *mut __m128i, v_f);
+            }
+        }
+
+        let mut lanes = [0i16; LANES];
+        _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, v_max);
+        column_max[col] = *lanes.iter().max().unwrap();
+        if column_max[col] == i16::MAX {
+            return None;
+        }
+        for row in masked_rows {
+            mask[(row % segment_length) * LANES + row / segment_length] = -1;
+        }
+    }
+
+    // the scalar fill keeps the first best cell in row order; band cells count with their unmasked score
+    let top_score = *column_max.iter().max().unwrap();
+    let matrix = IntegerMatrix::Striped { values, segment_length };
+    if top_score <= 0 {
+        return Some((matrix, 0, 0, 0));
+    }
+    let window_one = &seq1[window.x_start..window.x_end];
+    let window_two = &seq2[window.y_start..window.y_end];
+    let (topx, topy) = (0..cols).filter(|&col| column_max[col] == top_score)
+        .filter_map(|col| (1..rows + 1)
+            .find(|&row| best_predecessor(&matrix, window_one, window_two, scores, row, col + 1).0 == top_score as i32)
+            .map(|row| (row, col + 1)))
+        .min()
+        .unwrap();
+    Some((matrix, top_score as i32, topx, topy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use score_matrix::PairedScores;
+    use smith_waterman_no_diag::{smith_waterman_no_diag_suboptimal, smith_waterman_no_diag_window, MIN_CANDIDATES};
+    use test_support::{random_sequence, test_scores};
+
+    fn plasmid_with_repeat(seed: u64) -> Vec<char> {
+        let repeat = random_sequence(45, seed);
+        let mut reference = random_sequence(30, seed + 1);
+        reference.extend_from_slice(&repeat);
+        reference.extend(random_sequence(23, seed + 2));
+        let mut mutated = repeat.clone();
+        mutated[20] = if mutated[20] == 'A' { 'C' } else { 'A' };
+        mutated.remove(33);
+        reference.extend(mutated);
+        reference.extend(random_sequence(17, seed + 3));
+        reference
+    }
+
+    fn assert_same_alignment(one: &Alignment, two: &Alignment) {
+        assert_eq!(one.score, two.score);
+        assert_eq!((one.start_x, one.end_x, one.start_y, one.end_y), (two.start_x, two.end_x, two.start_y, two.end_y));
+        assert_eq!(one.seq_one_aligned, two.seq_one_aligned);
+        assert_eq!(one.seq_two_aligned, two.seq_two_aligned);
+    }
+
+    #[test]
+    fn test_band_rows_match_diagonal_band() {
+        for &(seq1_length, seq2_length, distance) in &[(30, 60, 5), (13, 26, 1), (20, 7, 3), (9, 9, 0)] {
+            for iy in 1..seq2_length + 1 {
+                let ranges = band_rows(iy, seq1_length, seq2_length, distance);
+                for ix in 1..seq1_length + 1 {
+                    let in_ranges = ranges.iter().any(|&(start, end)| start <= ix as i64 && (ix as i64) < end);
+                    assert_eq!(in_ranges, in_diagonal_band(ix, iy, seq1_length, seq2_length, distance), "cell {},{}", ix, iy);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_f64_engine() {
+        let scores = Scores { gap_ext: -2.0, ..test_scores() };
+        let integer_scores = IntegerScores::from_scores(&scores).unwrap();
+        for seed in 0..6 {
+            let reference = plasmid_with_repeat(seed * 10);
+            let mut doubled = reference.clone();
+            doubled.extend_from_slice(&reference);
+            for window in &[MatrixWindow::whole(&reference, &doubled), MatrixWindow { x_start: 20, x_end: 83, y_start: 90, y_end: 170 }] {
+                let expected = smith_waterman_no_diag_window(&reference, &doubled, &scores, 10, window);
+                let integer = smith_waterman_no_diag_integer(&reference, &doubled, &integer_scores, 10, window);
+                assert_same_alignment(&integer, &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_suboptimal_matches_f64_engine() {
+        let scores = Scores { gap_ext: -2.0, ..test_scores() };
+        let integer_scores = IntegerScores::from_scores(&scores).unwrap();
+        let reference = plasmid_with_repeat(3);
+        let overlapping = |one: &Alignment, two: &Alignment| one.start_x < two.end_x && two.start_x < one.end_x;
+
+        let expected = smith_waterman_no_diag_suboptimal(&reference, &reference, &scores, 10, 8.0, MIN_CANDIDATES, overlapping);
+        let integer = smith_waterman_no_diag_integer_suboptimal(&reference, &reference, &integer_scores, 10, 8.0, MIN_CANDIDATES, overlapping);
+        assert_eq!(integer.len(), expected.len());
+        for (one, two) in integer.iter().zip(expected.iter()) {
+            assert_same_alignment(one, two);
+        }
+    }
+
+    #[test]
+    fn test_striped_matches_scalar_fill() {
+        let scores = IntegerScores::from_scores(&Scores::default_scores()).unwrap();
+        for &length in &[1, 5, 8, 9, 17, 64] {
+            let seq1 = random_sequence(length, length as u64);
+            let seq2 = random_sequence(40, 99);
+            let window = MatrixWindow::whole(&seq1, &seq2);
+            let (matrix, top_score, topx, topy) = fill_integer(&seq1, &seq2, &scores, 3, &window);
+            let (scalar, scalar_top, scalar_x, scalar_y) = fill_scalar(&seq1, &seq2, &scores, 3, &window);
+            assert_eq!((top_score, topx, topy), (scalar_top, scalar_x, scalar_y));
+            for ix in 0..length + 1 {
+                for iy in 0..41 {
+                    assert_eq!(matrix.get(ix, iy), scalar.get(ix, iy), "cell {},{}", ix, iy);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_overflow_falls_back_to_32_bits() {
+        let scores = Scores { match_score: 1000.0, mismatch_score: -1000.0, gap_open: -1000.0, gap_ext: -1000.0, gap_start: -1000.0, gap_end: -1000.0, substitution_matrix: None };
+        let integer_scores = IntegerScores::from_scores(&scores).unwrap();
+        let seq1 = random_sequence(50, 1);
+        let window = MatrixWindow::whole(&seq1, &seq1);
+        let (matrix, top_score, _, _) = fill_integer(&seq1, &seq1, &integer_scores, 0, &window);
+        assert_eq!(top_score, 50000);
+        assert!(matches!(matrix, IntegerMatrix::Scalar(_)));
+    }
+
+    #[test]
+    fn test_substitution_matrix_matches_f64_engine() {
+        let scores = Scores { match_score: 2.0, mismatch_score: -3.0, gap_open: -5.0, gap_ext: -2.0, gap_start: -5.0, gap_end: -5.0, substitution_matrix: None };
+        let scores = scores.with_substitution_matrix(PairedScores::default_scores(&2.0, &-3.0));
+        let integer_scores = IntegerScores::from_scores(&scores).unwrap();
+        let mut reference = plasmid_with_repeat(5);
+        reference[40] = 'R';
+        reference[102] = 'N';
+        let window = MatrixWindow::whole(&reference, &reference);
+
+        let expected = smith_waterman_no_diag_window(&reference, &reference, &scores, 10, &window);
+        let integer = smith_waterman_no_diag_integer(&reference, &reference, &integer_scores, 10, &window);
+        assert_same_alignment(&integer, &expected);
+    }
+
+    #[test]
+    fn test_only_whole_scores() {
+        assert!(IntegerScores::from_scores(&Scores::default_scores()).is_some());
+        assert!(IntegerScores::from_scores(&Scores::plasmid_aligmment_scores()).is_none());
+    }
+}
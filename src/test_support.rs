@@ -0,0 +1,26 @@
+use needleman::Scores;
+
+/// bases drawn from an alphabet with a 64 bit LCG, the same sequence for the same seed on every platform
+pub fn random_bases(length: usize, seed: u64, alphabet: &[char]) -> Vec<char> {
+    let mut state = seed;
+    (0..length).map(|_| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        alphabet[(state >> 33) as usize % alphabet.len()]
+    }).collect()
+}
+
+/// a random stretch of A, C, G and T
+pub fn random_sequence(length: usize, seed: u64) -> Vec<char> {
+    random_bases(length, seed, &['A', 'C', 'G', 'T'])
+}
+
+/// random_sequence as bytes, for the Deduplicator's API
+pub fn random_bytes(length: usize, seed: u64) -> Vec<u8> {
+    random_sequence(length, seed).into_iter().map(|base| base as u8).collect()
+}
+
+/// strict linear-gap scores: a mismatch or a gapped base undoes several matches, so repeats in the tests
+/// align end to end and stop where they stop
+pub fn test_scores() -> Scores {
+    Scores { match_score: 1.0, mismatch_score: -3.0, gap_open: -5.0, gap_ext: -5.0, gap_start: -5.0, gap_end: -5.0, substitution_matrix: None }
+}
@@ -0,0 +1,136 @@
+use error::DupScoopError;
+
+/// the IUPAC nucleotide codes: the four bases, U, and the ambiguity codes
+const IUPAC_CODES: &str = "ACGTURYSWKMBDHVN";
+/// the most masked positions listed in a report row
+const LISTED_POSITIONS: usize = 10;
+
+/// what to do with characters in a sequence that aren't IUPAC nucleotide codes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvalidBaseMode {
+    /// fail the record with an error listing the positions
+    Reject,
+    /// replace them with N and carry on
+    Mask,
+}
+
+impl InvalidBaseMode {
+    pub fn from_name(name: &str) -> Option<InvalidBaseMode> {
+        match name.to_lowercase().as_str() {
+            "reject" => Some(InvalidBaseMode::Reject),
+            "mask" => Some(InvalidBaseMode::Mask),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InvalidBaseMode::Reject => "reject",
+            InvalidBaseMode::Mask => "mask",
+        }
+    }
+}
+
+/// What normalizing a record's sequence changed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Normalization {
+    /// whitespace dropped from inside the sequence, including the \r of Windows line endings
+    pub whitespace: usize,
+    /// digits dropped from the sequence, such as GenBank ORIGIN-style position numbers
+    pub digits: usize,
+    /// lowercase (soft-masked) bases, which are aligned as uppercase
+    pub lowercase: usize,
+    /// characters that aren't IUPAC codes and were replaced with N, with their 1-based positions
+    pub masked: Vec<(usize, char)>,
+}
+
+impl Normalization {
+    /// whether the sequence was used exactly as it was read
+    pub fn is_empty(&self) -> bool {
+        self.whitespace == 0 && self.digits == 0 && self.lowercase == 0 && self.masked.is_empty()
+    }
+
+    /// a summary of the changes for the report
+    pub fn describe(&self) -> String {
+        let mut changes = Vec::new();
+        if self.whitespace > 0 {
+            changes.push(format!("dropped {} whitespace characters", self.whitespace));
+        }
+        if self.digits > 0 {
+            changes.push(format!("dropped {} digits", self.digits));
+        }
+        if self.lowercase > 0 {
+            changes.push(format!("aligned {} lowercase bases as uppercase", self.lowercase));
+        }
+        if !self.masked.is_empty() {
+            let listed: Vec<String> = self.masked.iter().take(LISTED_POSITIONS).map(|(position, character)| format!("{:?} at {}", character, position)).collect();
+            changes.push(format!("masked {} invalid characters as N ({}{})", self.masked.len(), listed.join(", "),
+                                 if self.masked.len() > LISTED_POSITIONS { ", ..." } else { "" }));
+        }
+        changes.join("; ")
+    }
+}
+
+/// Clean up a record's sequence before aligning it: drop whitespace and digits, then check that everything
+/// left is an IUPAC code, masking anything else as N or rejecting the record. Case is kept so soft-masked
+/// bases can be restored in the output; positions are 1-based in the cleaned sequence.
+pub fn normalize_sequence(record_id: &str, sequence: &[char], mode: InvalidBaseMode) -> Result<(Vec<char>, Normalization), DupScoopError> {
+    let mut normalization = Normalization::default();
+    let mut cleaned = Vec::with_capacity(sequence.len());
+    for &character in sequence {
+        if character.is_whitespace() {
+            normalization.whitespace += 1;
+        } else if character.is_ascii_digit() {
+            normalization.digits += 1;
+        } else if IUPAC_CODES.contains(character.to_ascii_uppercase()) {
+            if character.is_ascii_lowercase() {
+                normalization.lowercase += 1;
+            }
+            cleaned.push(character);
+        } else {
+            normalization.masked.push((cleaned.len() + 1, character));
+            cleaned.push('N');
+        }
+    }
+    if mode == InvalidBaseMode::Reject && !normalization.masked.is_empty() {
+        return Err(DupScoopError::InvalidBases { record: record_id.to_string(), positions: normalization.masked });
+    }
+    Ok((cleaned, normalization))
+}
+
+/// the position in the raw sequence of each character normalize_sequence keeps, so coordinates in the
+/// cleaned sequence can be lifted back to the input as it was read
+pub fn kept_positions(sequence: &[char]) -> Vec<usize> {
+    sequence.iter().enumerate()
+        .filter(|(_, character)| !character.is_whitespace() && !character.is_ascii_digit())
+        .map(|(position, _)| position)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drops_line_endings_and_numbers() {
+        let raw: Vec<char> = "        1 acgtACGT\r\n       9 ryN\r\n".chars().collect();
+        let (cleaned, normalization) = normalize_sequence("contig_1", &raw, InvalidBaseMode::Reject).unwrap();
+        assert_eq!(cleaned.iter().collect::<String>(), "acgtACGTryN");
+        assert_eq!(normalization.digits, 2);
+        assert_eq!(normalization.whitespace, 21);
+        assert_eq!(normalization.lowercase, 6);
+        assert_eq!(normalization.describe(), "dropped 21 whitespace characters; dropped 2 digits; aligned 6 lowercase bases as uppercase");
+    }
+
+    #[test]
+    fn test_invalid_characters() {
+        let raw: Vec<char> = "AC GT*ACGXT".chars().collect();
+        match normalize_sequence("contig_1", &raw, InvalidBaseMode::Reject) {
+            Err(DupScoopError::InvalidBases { positions, .. }) => assert_eq!(positions, vec![(5, '*'), (9, 'X')]),
+            _ => panic!("expected an invalid bases error"),
+        }
+        let (cleaned, normalization) = normalize_sequence("contig_1", &raw, InvalidBaseMode::Mask).unwrap();
+        assert_eq!(cleaned.iter().collect::<String>(), "ACGTNACGNT");
+        assert_eq!(normalization.describe(), "dropped 1 whitespace characters; masked 2 invalid characters as N ('*' at 5, 'X' at 9)");
+    }
+}